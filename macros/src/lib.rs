@@ -0,0 +1,395 @@
+//! Derive macros for `role-assignment-tui`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::format_ident;
+use quote::quote;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Expr;
+use syn::Fields;
+use syn::GenericArgument;
+use syn::LitChar;
+use syn::LitStr;
+use syn::PathArguments;
+use syn::Token;
+use syn::Type;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+
+/// Derive `ToArgs` from the same clap attributes that parse the type, so the two cannot drift.
+///
+/// Structs emit their options and flags (`long`, `short`, positional, `bool` flags,
+/// `ArgAction::Count`, `Option` and repeatable `Vec` values, `value_enum`), recurse into
+/// `flatten` and `subcommand` fields, and omit `skip` fields. Subcommand enums emit the variant's
+/// kebab-case name (or `name = "..."`) followed by its arguments. Parsing the output yields an
+/// equal value.
+///
+/// Option values are joined to their flag as `--flag=value`, so values starting with `-`
+/// survive. Positionals come after every option and a hyphenated one is preceded by `--`; clap
+/// then reads everything after it as positional, so such a value cannot be followed by a
+/// subcommand.
+#[proc_macro_derive(ToArgs, attributes(clap, arg, command))]
+pub fn derive_to_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (bindings, pushes) = fields(&data.fields)?;
+            quote! {
+                let Self #bindings = self;
+                #pushes
+            }
+        }
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let attrs = ClapAttrs::parse(&variant.attrs)?;
+                let ident = &variant.ident;
+                let (bindings, pushes) = fields(&variant.fields)?;
+                if attrs.skip {
+                    arms.push(quote! { Self::#ident { .. } => {} });
+                    continue;
+                }
+                let name = match (attrs.flatten, attrs.name) {
+                    (true, _) => quote! {},
+                    (false, Some(name)) => quote! { args.push(#name.into()); },
+                    (false, None) => {
+                        let name = kebab_case(&ident.to_string());
+                        quote! { args.push(#name.into()); }
+                    }
+                };
+                arms.push(quote! {
+                    Self::#ident #bindings => {
+                        #name
+                        #pushes
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                input.span(),
+                "ToArgs cannot be derived for unions",
+            ));
+        }
+    };
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics crate::cli::to_args::ToArgs for #ident #type_generics #where_clause {
+            #[allow(unused_variables, unused_mut)]
+            fn to_args(&self) -> ::std::vec::Vec<::std::ffi::OsString> {
+                let mut args: ::std::vec::Vec<::std::ffi::OsString> = ::std::vec::Vec::new();
+                #body
+                args
+            }
+        }
+    })
+}
+
+/// A destructuring pattern for `fields` and the statements pushing their arguments.
+fn fields(fields: &Fields) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let mut names = Vec::new();
+    let mut pushes = Vec::new();
+    // Positionals go after every option, since escaping one with `--` ends option parsing, and
+    // subcommands after those
+    let mut positional_pushes = Vec::new();
+    let mut subcommand_pushes = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let binding = format_ident!("field_{i}");
+        let mut attrs = ClapAttrs::parse(&field.attrs)?;
+        // clap reads a tuple variant's field as the subcommand's arguments
+        if matches!(fields, Fields::Unnamed(_)) && !attrs.subcommand {
+            attrs.flatten = true;
+        }
+        if !attrs.skip {
+            let long_default = field
+                .ident
+                .as_ref()
+                .map(|x| x.to_string().trim_start_matches("r#").replace('_', "-"));
+            let push = field_args(&binding, &field.ty, &attrs, long_default)?;
+            if attrs.subcommand {
+                subcommand_pushes.push(push);
+            } else if !attrs.flatten && attrs.long.is_none() && attrs.short.is_none() {
+                positional_pushes.push(push);
+            } else {
+                pushes.push(push);
+            }
+        }
+        names.push((field.ident.clone(), binding));
+    }
+    let bindings = match fields {
+        Fields::Named(_) => {
+            let pairs = names
+                .iter()
+                .map(|(ident, binding)| quote! { #ident: #binding });
+            quote! { { #(#pairs),* } }
+        }
+        Fields::Unnamed(_) => {
+            let bindings = names.iter().map(|(_, binding)| binding);
+            quote! { ( #(#bindings),* ) }
+        }
+        Fields::Unit => quote! {},
+    };
+    Ok((
+        bindings,
+        quote! { #(#pushes)* #(#positional_pushes)* #(#subcommand_pushes)* },
+    ))
+}
+
+/// Statements pushing the arguments for one field, bound by reference as `binding`.
+fn field_args(
+    binding: &syn::Ident,
+    ty: &Type,
+    attrs: &ClapAttrs,
+    long_default: Option<String>,
+) -> syn::Result<TokenStream2> {
+    let to_args = quote! { crate::cli::to_args::ToArgs::to_args };
+    if attrs.flatten {
+        return Ok(quote! { args.extend(#to_args(#binding)); });
+    }
+    if attrs.subcommand {
+        return Ok(match inner_type(ty, "Option") {
+            Some(_) => quote! {
+                if let Some(value) = #binding {
+                    args.extend(#to_args(value));
+                }
+            },
+            None => quote! { args.extend(#to_args(#binding)); },
+        });
+    }
+
+    let flag = match (&attrs.long, &attrs.short) {
+        (Some(Some(long)), _) => Some(format!("--{long}")),
+        (Some(None), _) => match long_default {
+            Some(long) => Some(format!("--{long}")),
+            None => {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "`long` without a name needs a named field",
+                ));
+            }
+        },
+        (None, Some(Some(short))) => Some(format!("-{short}")),
+        (None, Some(None)) => match long_default.and_then(|x| x.chars().next()) {
+            Some(short) => Some(format!("-{short}")),
+            None => {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "`short` without a name needs a named field",
+                ));
+            }
+        },
+        (None, None) => None,
+    };
+    let push_flag = match &flag {
+        Some(flag) => quote! { args.push(#flag.into()); },
+        None => quote! {},
+    };
+    let push_value = |value: TokenStream2| {
+        let value = if attrs.value_enum {
+            quote! {
+                ::std::ffi::OsString::from(
+                    ::clap::ValueEnum::to_possible_value(#value)
+                        .expect("value_enum variants used as arguments must not be skipped")
+                        .get_name(),
+                )
+            }
+        } else {
+            quote! { crate::cli::to_args::ToArg::to_arg(#value) }
+        };
+        match &flag {
+            // Joined to the flag so a value starting with `-` is not read as another flag
+            Some(flag) => {
+                let prefix = format!("{flag}=");
+                quote! {
+                    let mut arg = ::std::ffi::OsString::from(#prefix);
+                    arg.push(#value);
+                    args.push(arg);
+                }
+            }
+            // Positionals can only be escaped by ending option parsing first
+            None => quote! {
+                let value = #value;
+                if value.as_encoded_bytes().starts_with(b"-")
+                    && !args.iter().any(|x| x.as_os_str() == "--")
+                {
+                    args.push("--".into());
+                }
+                args.push(value);
+            },
+        }
+    };
+
+    if attrs.count {
+        return Ok(quote! {
+            for _ in 0..*#binding {
+                #push_flag
+            }
+        });
+    }
+    if is_bool(ty) && flag.is_some() {
+        return Ok(quote! {
+            if *#binding {
+                #push_flag
+            }
+        });
+    }
+    if inner_type(ty, "Option").is_some() {
+        let push_value = push_value(quote! { value });
+        return Ok(quote! {
+            if let Some(value) = #binding {
+                #push_value
+            }
+        });
+    }
+    if inner_type(ty, "Vec").is_some() {
+        let push_value = push_value(quote! { value });
+        return Ok(quote! {
+            for value in #binding {
+                #push_value
+            }
+        });
+    }
+    let push_value = push_value(quote! { #binding });
+    Ok(quote! {
+        {
+            #push_value
+        }
+    })
+}
+
+/// The clap settings that affect how a field or variant is written back out.
+#[derive(Default)]
+struct ClapAttrs {
+    /// `Some(None)` for a bare `long`, which defaults to the field name
+    long: Option<Option<String>>,
+    short: Option<Option<char>>,
+    name: Option<String>,
+    flatten: bool,
+    subcommand: bool,
+    value_enum: bool,
+    skip: bool,
+    count: bool,
+}
+
+impl ClapAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<ClapAttrs> {
+        let mut rtn = ClapAttrs::default();
+        for attr in attrs {
+            let path = attr.path();
+            if !(path.is_ident("clap") || path.is_ident("arg") || path.is_ident("command")) {
+                continue;
+            }
+            // Bare `#[arg]` and `#[command]` carry no settings
+            if matches!(attr.meta, syn::Meta::Path(_)) {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                let key = meta
+                    .path
+                    .get_ident()
+                    .map(|x| x.to_string())
+                    .unwrap_or_default();
+                let has_value = meta.input.peek(Token![=]);
+                match key.as_str() {
+                    "long" if has_value => {
+                        rtn.long = Some(Some(meta.value()?.parse::<LitStr>()?.value()))
+                    }
+                    "long" => rtn.long = Some(None),
+                    "short" if has_value => {
+                        rtn.short = Some(Some(meta.value()?.parse::<LitChar>()?.value()))
+                    }
+                    "short" => rtn.short = Some(None),
+                    "name" if has_value => rtn.name = Some(meta.value()?.parse::<LitStr>()?.value()),
+                    "flatten" => rtn.flatten = true,
+                    "subcommand" => rtn.subcommand = true,
+                    "value_enum" => rtn.value_enum = true,
+                    "skip" => {
+                        rtn.skip = true;
+                        if has_value {
+                            meta.value()?.parse::<Expr>()?;
+                        }
+                    }
+                    "action" if has_value => {
+                        let action = meta.value()?.parse::<Expr>()?;
+                        rtn.count = matches!(
+                            &action,
+                            Expr::Path(path) if path.path.segments.last().is_some_and(|x| x.ident == "Count")
+                        );
+                    }
+                    _ if has_value => {
+                        meta.value()?.parse::<Expr>()?;
+                    }
+                    _ if meta.input.peek(syn::token::Paren) => {
+                        meta.input.parse::<proc_macro2::Group>()?;
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })?;
+        }
+        Ok(rtn)
+    }
+}
+
+/// `T` if `ty` is `wrapper<T>`, matching on the last path segment.
+fn inner_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    match generics.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("bool"))
+}
+
+/// `ListResourceGroups` -> `list-resource-groups`, matching clap's subcommand naming.
+///
+/// Like clap, a run of capitals is one word, so `HTMLReport` -> `html-report`, and digits belong
+/// to the word before them.
+fn kebab_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut rtn = String::new();
+    // Case of the last letter seen; `None` at the start
+    let mut previous_upper = None;
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let next_is_lower = chars.get(i + 1).is_some_and(|x| x.is_lowercase());
+            match previous_upper {
+                Some(false) => rtn.push('-'),
+                Some(true) if next_is_lower => rtn.push('-'),
+                _ => {}
+            }
+            previous_upper = Some(true);
+        } else if c.is_lowercase() {
+            previous_upper = Some(false);
+        }
+        rtn.extend(c.to_lowercase());
+    }
+    rtn
+}
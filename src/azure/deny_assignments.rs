@@ -0,0 +1,225 @@
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::rest::list_arm_per_subscription;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Principal id Azure uses in deny assignments to mean every principal.
+pub const EVERYONE: &str = "00000000-0000-0000-0000-000000000000";
+
+/// A deny assignment, which blocks actions even when a role assignment grants them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DenyAssignment {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub scope: String,
+    pub actions: Vec<String>,
+    pub not_actions: Vec<String>,
+    pub data_actions: Vec<String>,
+    pub not_data_actions: Vec<String>,
+    /// Principal ids the deny applies to; [`EVERYONE`] matches all principals
+    pub principal_ids: Vec<String>,
+    pub exclude_principal_ids: Vec<String>,
+    pub do_not_apply_to_child_scopes: bool,
+}
+
+impl DenyAssignment {
+    /// Whether this deny assignment is in effect at `scope`, either directly or inherited.
+    pub fn applies_at(&self, scope: &str) -> bool {
+        let own = self.scope.trim_end_matches('/').to_lowercase();
+        let scope = scope.trim_end_matches('/').to_lowercase();
+        if own == scope {
+            return true;
+        }
+        if self.do_not_apply_to_child_scopes {
+            return false;
+        }
+        // The root scope `/` trims to the empty string and contains everything
+        own.is_empty() || scope.starts_with(&format!("{own}/"))
+    }
+
+    /// Whether this deny assignment names `principal_id`.
+    ///
+    /// Only direct matches are considered; a deny naming a group does not reach its members.
+    pub fn applies_to(&self, principal_id: &str) -> bool {
+        let matches = |ids: &[String]| {
+            ids.iter()
+                .any(|id| id == EVERYONE || id.eq_ignore_ascii_case(principal_id))
+        };
+        matches(&self.principal_ids)
+            && !self
+                .exclude_principal_ids
+                .iter()
+                .any(|id| id.eq_ignore_ascii_case(principal_id))
+    }
+
+    /// Whether this deny assignment blocks the control-plane `action`.
+    pub fn denies_action(&self, action: &str) -> bool {
+        self.actions.iter().any(|x| action_matches(x, action))
+            && !self.not_actions.iter().any(|x| action_matches(x, action))
+    }
+
+    /// Whether `principal_id` is blocked from `action` at `scope`.
+    pub fn denies(&self, principal_id: &str, action: &str, scope: &str) -> bool {
+        self.applies_at(scope) && self.applies_to(principal_id) && self.denies_action(action)
+    }
+}
+
+/// Case-insensitive match of an action against a pattern where `*` matches any run of characters.
+pub fn action_matches(pattern: &str, action: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let action = action.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = action.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[derive(Deserialize)]
+struct RawDenyAssignment {
+    id: String,
+    properties: RawDenyAssignmentProperties,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawDenyAssignmentProperties {
+    deny_assignment_name: String,
+    description: Option<String>,
+    scope: String,
+    #[serde(default)]
+    permissions: Vec<RawPermission>,
+    #[serde(default)]
+    principals: Vec<RawPrincipal>,
+    #[serde(default)]
+    exclude_principals: Vec<RawPrincipal>,
+    #[serde(default)]
+    do_not_apply_to_child_scopes: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPermission {
+    #[serde(default)]
+    actions: Vec<String>,
+    #[serde(default)]
+    not_actions: Vec<String>,
+    #[serde(default)]
+    data_actions: Vec<String>,
+    #[serde(default)]
+    not_data_actions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawPrincipal {
+    id: String,
+}
+
+impl From<RawDenyAssignment> for DenyAssignment {
+    fn from(raw: RawDenyAssignment) -> Self {
+        let properties = raw.properties;
+        let mut rtn = DenyAssignment {
+            id: raw.id,
+            name: properties.deny_assignment_name,
+            description: properties.description.filter(|x| !x.is_empty()),
+            scope: properties.scope,
+            actions: Vec::new(),
+            not_actions: Vec::new(),
+            data_actions: Vec::new(),
+            not_data_actions: Vec::new(),
+            principal_ids: properties.principals.into_iter().map(|x| x.id).collect(),
+            exclude_principal_ids: properties
+                .exclude_principals
+                .into_iter()
+                .map(|x| x.id)
+                .collect(),
+            do_not_apply_to_child_scopes: properties.do_not_apply_to_child_scopes,
+        };
+        for permission in properties.permissions {
+            rtn.actions.extend(permission.actions);
+            rtn.not_actions.extend(permission.not_actions);
+            rtn.data_actions.extend(permission.data_actions);
+            rtn.not_data_actions.extend(permission.not_data_actions);
+        }
+        rtn
+    }
+}
+
+const API_VERSION: &str = "2022-04-01";
+
+/// Fetch the deny assignments visible from every subscription in `hierarchy`, without duplicates.
+///
+/// Listing at a subscription also returns the deny assignments inherited from above it.
+pub async fn fetch_all_deny_assignments(
+    hierarchy: &ScopeHierarchy,
+) -> eyre::Result<Vec<DenyAssignment>> {
+    let raw = list_arm_per_subscription::<RawDenyAssignment>(
+        hierarchy,
+        &format!("/providers/Microsoft.Authorization/denyAssignments?api-version={API_VERSION}"),
+        |x| &x.id,
+    )
+    .await?;
+    Ok(raw.into_iter().map(DenyAssignment::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deny(scope: &str, actions: &[&str]) -> DenyAssignment {
+        DenyAssignment {
+            id: format!("{scope}/providers/Microsoft.Authorization/denyAssignments/test"),
+            name: "test".to_string(),
+            description: None,
+            scope: scope.to_string(),
+            actions: actions.iter().map(|x| x.to_string()).collect(),
+            not_actions: Vec::new(),
+            data_actions: Vec::new(),
+            not_data_actions: Vec::new(),
+            principal_ids: vec![EVERYONE.to_string()],
+            exclude_principal_ids: vec!["exempt".to_string()],
+            do_not_apply_to_child_scopes: false,
+        }
+    }
+
+    #[test]
+    fn wildcard_actions() {
+        assert!(action_matches("*", "Microsoft.General/read"));
+        assert!(action_matches("*/read", "Microsoft.General/read"));
+        assert!(action_matches(
+            "microsoft.storage/*/delete",
+            "Microsoft.Storage/storageAccounts/delete"
+        ));
+        assert!(!action_matches("*/delete", "Microsoft.General/read"));
+        assert!(!action_matches(
+            "Microsoft.Storage/*",
+            "Microsoft.Compute/read"
+        ));
+    }
+
+    #[test]
+    fn scope_inheritance_and_exclusions() {
+        let mut deny = deny("/subscriptions/abc", &["*"]);
+        let rg = "/subscriptions/abc/resourceGroups/rg-app";
+        assert!(deny.denies("alice", "Microsoft.General/read", rg));
+        assert!(!deny.denies("exempt", "Microsoft.General/read", rg));
+        assert!(!deny.applies_at("/subscriptions/abcdef"));
+
+        deny.do_not_apply_to_child_scopes = true;
+        assert!(!deny.applies_at(rg));
+        assert!(deny.applies_at("/subscriptions/ABC"));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::rest::list_arm_per_subscription;
+use chrono::DateTime;
+use chrono::Utc;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A Privileged Identity Management eligibility: a role the principal can activate on demand.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EligibleAssignment {
+    pub id: String,
+    pub scope: String,
+    pub role_definition_id: String,
+    pub principal_id: PrincipalId,
+    /// `Direct`, `Group` or `Inherited`, as reported by PIM
+    pub member_type: Option<String>,
+    pub start_date_time: Option<DateTime<Utc>>,
+    /// `None` for permanently eligible assignments
+    pub end_date_time: Option<DateTime<Utc>>,
+}
+
+impl EligibleAssignment {
+    /// The role definition this eligibility is for.
+    ///
+    /// PIM reports role definition ids in their subscription-scoped form, so match on the
+    /// trailing GUID rather than the whole id.
+    pub fn role_definition<'a>(
+        &self,
+        rbac: &'a RoleDefinitionsAndAssignments,
+    ) -> Option<&'a RoleDefinition> {
+        let name = last_segment(&self.role_definition_id);
+        rbac.role_definitions
+            .values()
+            .find(|rd| last_segment(&rd.id.expanded_form()).eq_ignore_ascii_case(name))
+    }
+
+    /// Human readable eligibility window, e.g. `2026-01-01 → 2026-06-30`.
+    pub fn window(&self) -> String {
+        let format = |x: &Option<DateTime<Utc>>| x.map(|x| x.format("%Y-%m-%d").to_string());
+        match (format(&self.start_date_time), format(&self.end_date_time)) {
+            (Some(start), Some(end)) => format!("{start} → {end}"),
+            (Some(start), None) => format!("{start} → permanent"),
+            (None, Some(end)) => format!("until {end}"),
+            (None, None) => "permanent".to_string(),
+        }
+    }
+}
+
+fn last_segment(id: &str) -> &str {
+    id.rsplit('/').next().unwrap_or(id)
+}
+
+#[derive(Deserialize)]
+struct RawEligibilityInstance {
+    id: String,
+    properties: RawEligibilityProperties,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawEligibilityProperties {
+    scope: String,
+    role_definition_id: String,
+    principal_id: PrincipalId,
+    member_type: Option<String>,
+    start_date_time: Option<DateTime<Utc>>,
+    end_date_time: Option<DateTime<Utc>>,
+}
+
+impl From<RawEligibilityInstance> for EligibleAssignment {
+    fn from(raw: RawEligibilityInstance) -> Self {
+        let properties = raw.properties;
+        EligibleAssignment {
+            id: raw.id,
+            scope: properties.scope,
+            role_definition_id: properties.role_definition_id,
+            principal_id: properties.principal_id,
+            member_type: properties.member_type,
+            start_date_time: properties.start_date_time,
+            end_date_time: properties.end_date_time,
+        }
+    }
+}
+
+const API_VERSION: &str = "2020-10-01";
+
+/// Fetch the PIM eligibilities at, above and below every subscription in `hierarchy`, without
+/// duplicates.
+pub async fn fetch_all_eligible_assignments(
+    hierarchy: &ScopeHierarchy,
+) -> eyre::Result<Vec<EligibleAssignment>> {
+    let raw = list_arm_per_subscription::<RawEligibilityInstance>(
+        hierarchy,
+        &format!(
+            "/providers/Microsoft.Authorization/roleEligibilityScheduleInstances?api-version={API_VERSION}"
+        ),
+        |x| &x.id,
+    )
+    .await?;
+    Ok(raw.into_iter().map(EligibleAssignment::from).collect())
+}
@@ -0,0 +1,118 @@
+use crate::azure::resource_graph::query_resource_graph;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManagementGroupEntry {
+    pub name: String,
+    pub display_name: String,
+    /// `None` for the tenant root group
+    pub parent_name: Option<String>,
+}
+
+impl ManagementGroupEntry {
+    pub fn id(&self) -> String {
+        format!(
+            "/providers/Microsoft.Management/managementGroups/{}",
+            self.name
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionEntry {
+    pub subscription_id: String,
+    pub display_name: String,
+    pub management_group_name: Option<String>,
+}
+
+impl SubscriptionEntry {
+    pub fn id(&self) -> String {
+        format!("/subscriptions/{}", self.subscription_id)
+    }
+}
+
+/// Management groups and subscriptions along with where each sits in the tenant.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeHierarchy {
+    pub management_groups: Vec<ManagementGroupEntry>,
+    pub subscriptions: Vec<SubscriptionEntry>,
+}
+
+impl ScopeHierarchy {
+    /// Ids of the subscription and each management group above it, nearest first.
+    ///
+    /// Role assignments at any of these scopes are inherited by everything in the subscription.
+    pub fn scopes_above_subscription(&self, subscription_id: &str) -> Vec<String> {
+        let mut rtn = vec![format!("/subscriptions/{subscription_id}")];
+        let mut parent = self
+            .subscriptions
+            .iter()
+            .find(|x| x.subscription_id.eq_ignore_ascii_case(subscription_id))
+            .and_then(|x| x.management_group_name.clone());
+        while let Some(name) = parent.take() {
+            let Some(group) = self.management_groups.iter().find(|x| x.name == name) else {
+                break;
+            };
+            // Guard against a malformed hierarchy that loops back on itself
+            if rtn.contains(&group.id()) {
+                break;
+            }
+            rtn.push(group.id());
+            parent = group.parent_name.clone();
+        }
+        rtn
+    }
+
+    pub fn subscription_name(&self, subscription_id: &str) -> Option<&str> {
+        self.subscriptions
+            .iter()
+            .find(|x| x.subscription_id.eq_ignore_ascii_case(subscription_id))
+            .map(|x| x.display_name.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+struct ContainerRow {
+    kind: String,
+    name: String,
+    display_name: String,
+    subscription_id: String,
+    parent_name: String,
+}
+
+const HIERARCHY_QUERY: &str = r#"resourcecontainers
+| where type =~ 'microsoft.management/managementgroups' or type =~ 'microsoft.resources/subscriptions'
+| extend is_subscription = type =~ 'microsoft.resources/subscriptions'
+| project
+    kind = iff(is_subscription, 'subscription', 'management_group'),
+    name,
+    display_name = iff(is_subscription, name, tostring(properties.displayName)),
+    subscription_id = iff(is_subscription, subscriptionId, ''),
+    parent_name = iff(
+        is_subscription,
+        tostring(properties.managementGroupAncestorsChain[0].name),
+        tostring(properties.details.parent.name)
+    )"#;
+
+pub async fn fetch_scope_hierarchy() -> eyre::Result<ScopeHierarchy> {
+    let rows = query_resource_graph::<ContainerRow>(HIERARCHY_QUERY).await?;
+    let mut rtn = ScopeHierarchy::default();
+    for row in rows {
+        let parent_name = Some(row.parent_name).filter(|x| !x.is_empty());
+        if row.kind == "subscription" {
+            rtn.subscriptions.push(SubscriptionEntry {
+                subscription_id: row.subscription_id,
+                display_name: row.display_name,
+                management_group_name: parent_name,
+            });
+        } else {
+            rtn.management_groups.push(ManagementGroupEntry {
+                name: row.name,
+                display_name: row.display_name,
+                parent_name,
+            });
+        }
+    }
+    Ok(rtn)
+}
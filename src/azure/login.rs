@@ -0,0 +1,94 @@
+use cloud_terrastodon_command::CommandBuilder;
+use cloud_terrastodon_command::CommandKind;
+use eyre::Context;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Log the Azure CLI in as the service principal given by `AZURE_CLIENT_ID`,
+/// `AZURE_CLIENT_SECRET` and `AZURE_TENANT_ID`.
+///
+/// The login lands in whichever config dir `AZURE_CONFIG_DIR` points at.
+pub async fn login_service_principal() -> eyre::Result<()> {
+    let var = |name: &str| {
+        std::env::var(name)
+            .wrap_err_with(|| format!("{name} must be set to log in as a service principal"))
+    };
+    let client_id = var("AZURE_CLIENT_ID")?;
+    let client_secret = var("AZURE_CLIENT_SECRET")?;
+    let tenant_id = var("AZURE_TENANT_ID")?;
+    // The Azure CLI reads `@<file>` arguments from the file, which keeps the secret out of
+    // process listings and command logging
+    let secret_file = SecretFile::write(&client_secret)?;
+    let password = format!("@{}", secret_file.path.display());
+    let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+    cmd.args([
+        "login",
+        "--service-principal",
+        "--username",
+        &client_id,
+        "--password",
+        &password,
+        "--tenant",
+        &tenant_id,
+        "--allow-no-subscriptions",
+    ]);
+    cmd.run_raw()
+        .await
+        .wrap_err_with(|| format!("Logging in as service principal {client_id}"))?;
+    Ok(())
+}
+
+/// A secret in a file only the current user can read, removed on drop.
+///
+/// It lives in the login's own config dir when there is one, which is private to the user.
+struct SecretFile {
+    path: PathBuf,
+}
+
+impl SecretFile {
+    fn write(secret: &str) -> eyre::Result<Self> {
+        let dir = std::env::var_os("AZURE_CONFIG_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        std::fs::create_dir_all(&dir).wrap_err_with(|| format!("Creating {}", dir.display()))?;
+        let path = dir.join(format!("client-secret-{}", std::process::id()));
+        // Left behind only if a previous process with our pid was killed mid-login
+        let _ = std::fs::remove_file(&path);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let rtn = Self { path };
+        let mut file = options
+            .open(&rtn.path)
+            .wrap_err_with(|| format!("Creating {}", rtn.path.display()))?;
+        file.write_all(secret.as_bytes())?;
+        Ok(rtn)
+    }
+}
+
+impl Drop for SecretFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_file_is_private_and_removed_on_drop() {
+        let secret_file = SecretFile::write("s3cret").unwrap();
+        let path = secret_file.path.clone();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "s3cret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(secret_file);
+        assert!(!path.exists());
+    }
+}
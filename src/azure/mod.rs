@@ -0,0 +1,12 @@
+//! Fetches for Azure data that the upstream crates do not provide.
+
+pub mod deny_assignments;
+pub mod eligible_assignments;
+pub mod hierarchy;
+pub mod login;
+pub mod principals;
+pub mod privilege;
+pub mod resource_graph;
+pub mod resources;
+pub mod rest;
+pub mod role_assignments;
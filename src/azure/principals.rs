@@ -0,0 +1,283 @@
+use crate::azure::rest::list_graph;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::GroupId;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ServicePrincipal;
+use cloud_terrastodon_azure::prelude::User;
+use cloud_terrastodon_azure::prelude::fetch_all_security_groups;
+use cloud_terrastodon_azure::prelude::fetch_all_service_principals;
+use cloud_terrastodon_azure::prelude::fetch_all_users;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::try_join;
+use tracing::warn;
+
+/// The kinds of principal a role can be assigned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrincipalKind {
+    User,
+    ServicePrincipal,
+    Group,
+}
+
+impl std::fmt::Display for PrincipalKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PrincipalKind::User => "User",
+            PrincipalKind::ServicePrincipal => "Service Principal",
+            PrincipalKind::Group => "Group",
+        })
+    }
+}
+
+impl PrincipalKind {
+    /// The `principalType` Azure expects, as in `--assignee-principal-type`.
+    pub fn principal_type(&self) -> &'static str {
+        match self {
+            PrincipalKind::User => "User",
+            PrincipalKind::ServicePrincipal => "ServicePrincipal",
+            PrincipalKind::Group => "Group",
+        }
+    }
+}
+
+/// What the directory knows about one principal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrincipalEntry {
+    pub kind: PrincipalKind,
+    pub display_name: String,
+    /// The user principal name of a user or the application id of a service principal
+    pub alias: Option<String>,
+}
+
+/// Users, service principals and groups by object id, for showing and looking up principals.
+#[derive(Debug, Clone, Default)]
+pub struct PrincipalDirectory {
+    entries: HashMap<PrincipalId, PrincipalEntry>,
+}
+
+impl PrincipalDirectory {
+    /// Fetch every principal, serving fresh cached copies where there are some.
+    pub async fn fetch(cache: &Cache) -> eyre::Result<Self> {
+        let (users, service_principals, groups) = try_join!(
+            cache.get_or_fetch(FetchKind::Users, fetch_all_users()),
+            cache.get_or_fetch(FetchKind::ServicePrincipals, fetch_all_service_principals()),
+            cache.get_or_fetch(FetchKind::SecurityGroups, fetch_all_security_groups()),
+        )?;
+        let mut rtn = Self::default();
+        rtn.add_users(&users);
+        rtn.add_service_principals(&service_principals);
+        rtn.add_groups(&groups);
+        Ok(rtn)
+    }
+
+    /// Fetch every principal, ignoring cached copies but writing the result back.
+    ///
+    /// For reports that call principals missing from the directory out, which a stale copy
+    /// would do for anyone created since it was fetched.
+    pub async fn fetch_live(cache: &Cache) -> eyre::Result<Self> {
+        Self::fetch(&cache.refreshing()).await
+    }
+
+    pub fn add_users(&mut self, users: &[User]) {
+        for user in users {
+            self.entries.insert(
+                user.id.into(),
+                PrincipalEntry {
+                    kind: PrincipalKind::User,
+                    display_name: user.display_name.clone(),
+                    alias: Some(user.user_principal_name.clone()),
+                },
+            );
+        }
+    }
+
+    pub fn add_service_principals(&mut self, service_principals: &[ServicePrincipal]) {
+        for sp in service_principals {
+            self.entries.insert(
+                sp.id.into(),
+                PrincipalEntry {
+                    kind: PrincipalKind::ServicePrincipal,
+                    display_name: sp.display_name.clone(),
+                    alias: Some(sp.app_id.to_string()),
+                },
+            );
+        }
+    }
+
+    pub fn add_groups(&mut self, groups: &[Group]) {
+        for group in groups {
+            self.entries.insert(
+                group.id.into(),
+                PrincipalEntry {
+                    kind: PrincipalKind::Group,
+                    display_name: group.display_name.clone(),
+                    alias: None,
+                },
+            );
+        }
+    }
+
+    pub fn get(&self, principal_id: &PrincipalId) -> Option<&PrincipalEntry> {
+        self.entries.get(principal_id)
+    }
+
+    pub fn kind(&self, principal_id: &PrincipalId) -> Option<PrincipalKind> {
+        self.get(principal_id).map(|x| x.kind)
+    }
+
+    /// Display string with a type prefix, e.g. `(User) Alice`, falling back to the raw id.
+    pub fn display(&self, principal_id: &PrincipalId) -> String {
+        match self.get(principal_id) {
+            Some(entry) => format!("({}) {}", entry.kind, entry.display_name),
+            None => principal_id.to_string(),
+        }
+    }
+
+    /// Display name without the type prefix, falling back to the raw id.
+    pub fn name(&self, principal_id: &PrincipalId) -> String {
+        match self.get(principal_id) {
+            Some(entry) => entry.display_name.clone(),
+            None => principal_id.to_string(),
+        }
+    }
+
+    /// Every principal, sorted by display string.
+    pub fn iter(&self) -> impl Iterator<Item = (&PrincipalId, &PrincipalEntry)> {
+        self.entries
+            .iter()
+            .sorted_by_cached_key(|(id, _)| (self.display(id), id.to_string()))
+    }
+
+    /// Find a principal by object id, user principal name, application id or group name.
+    ///
+    /// An object id missing from the directory is still accepted, since a deleted principal's
+    /// role assignments outlive it.
+    pub fn find(&self, query: &str) -> eyre::Result<PrincipalId> {
+        let matches = self
+            .entries
+            .iter()
+            .filter(|(id, entry)| {
+                id.to_string().eq_ignore_ascii_case(query)
+                    || entry
+                        .alias
+                        .as_deref()
+                        .is_some_and(|x| x.eq_ignore_ascii_case(query))
+                    || (entry.kind == PrincipalKind::Group
+                        && entry.display_name.eq_ignore_ascii_case(query))
+            })
+            .map(|(id, _)| *id)
+            .sorted_by_key(|id| id.to_string())
+            .collect_vec();
+        match matches.as_slice() {
+            [id] => Ok(*id),
+            [] if let Ok(id) = parse_object_id(query) => {
+                warn!("{id} is not in the directory; it may have been deleted");
+                Ok(id)
+            }
+            [] => eyre::bail!(
+                "No user, service principal or group matches {query:?}; pass an object id, user principal name, application id or group name"
+            ),
+            _ => eyre::bail!(
+                "{query:?} matches several principals; pass one of their object ids instead: {}",
+                matches
+                    .iter()
+                    .map(|id| format!("{} {id}", self.display(id)))
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+fn parse_object_id(query: &str) -> serde_json::Result<PrincipalId> {
+    serde_json::from_value(serde_json::Value::String(query.to_owned()))
+}
+
+/// Ids of every group `principal_id` belongs to, directly or through nested groups.
+pub async fn fetch_transitive_group_ids(principal_id: &PrincipalId) -> eyre::Result<Vec<GroupId>> {
+    #[derive(Deserialize)]
+    struct Row {
+        id: GroupId,
+    }
+    let rows: Vec<Row> = match list_graph(&format!(
+        "/directoryObjects/{principal_id}/transitiveMemberOf/microsoft.graph.group?$select=id"
+    ))
+    .await
+    {
+        Ok(rows) => rows,
+        // A deleted principal still has role assignments but no memberships to list
+        Err(e) if is_not_found(&e) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    Ok(rows.into_iter().map(|x| x.id).collect())
+}
+
+/// Whether a Graph request failed because the object it names does not exist.
+fn is_not_found(error: &eyre::Report) -> bool {
+    error
+        .chain()
+        .any(|x| x.to_string().contains("Request_ResourceNotFound"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn recognises_graph_not_found_errors() {
+        let not_found = eyre::eyre!(
+            "{}",
+            r#"Not Found({"error":{"code":"Request_ResourceNotFound","message":"Resource does not exist."}})"#
+        )
+        .wrap_err("Listing /directoryObjects/x/transitiveMemberOf");
+        assert!(is_not_found(&not_found));
+        let forbidden = eyre::eyre!(
+            "{}",
+            r#"Forbidden({"error":{"code":"Authorization_RequestDenied"}})"#
+        )
+        .wrap_err("Listing /directoryObjects/x/transitiveMemberOf");
+        assert!(!is_not_found(&forbidden));
+    }
+
+    #[test]
+    fn finds_principals_by_any_identifier() {
+        let alice = fixtures::user("Alice");
+        let deployer = fixtures::service_principal("deployer");
+        let admins = fixtures::group("Admins");
+        let mut principals = PrincipalDirectory::default();
+        principals.add_users(std::slice::from_ref(&alice));
+        principals.add_service_principals(std::slice::from_ref(&deployer));
+        principals.add_groups(std::slice::from_ref(&admins));
+
+        assert_eq!(
+            principals
+                .find(&alice.user_principal_name.to_uppercase())
+                .unwrap(),
+            alice.id.into()
+        );
+        assert_eq!(
+            principals.find(&alice.id.to_string()).unwrap(),
+            alice.id.into()
+        );
+        assert_eq!(
+            principals.find(&deployer.app_id.to_string()).unwrap(),
+            deployer.id.into()
+        );
+        assert_eq!(principals.find("admins").unwrap(), admins.id.into());
+        // Only group names are unique enough to look up by
+        assert!(principals.find("Alice").is_err());
+        // Object ids of principals that have since been deleted are taken as they are
+        let deleted = fixtures::user("Deleted");
+        assert_eq!(
+            principals.find(&deleted.id.to_string()).unwrap(),
+            deleted.id.into()
+        );
+        assert_eq!(principals.kind(&deleted.id.into()), None);
+        assert!(principals.find("not-an-object-id").is_err());
+        assert_eq!(principals.display(&admins.id.into()), "(Group) Admins");
+    }
+}
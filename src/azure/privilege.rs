@@ -0,0 +1,142 @@
+use crate::azure::deny_assignments::action_matches;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use serde::Serialize;
+
+/// A coarse summary of what a role lets its holder do, ordered from least to most.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeLevel {
+    /// Only `*/read` style actions
+    Read,
+    /// Can change resources but not who has access to them
+    Write,
+    /// Can grant access by writing role assignments, as Owner and User Access Administrator do
+    Admin,
+}
+
+impl PrivilegeLevel {
+    /// Classify by control plane actions; data actions do not raise the level.
+    pub fn of(role_definition: &RoleDefinition) -> Self {
+        const GRANT: &str = "Microsoft.Authorization/roleAssignments/write";
+        // Contributor has `*` but excludes `Microsoft.Authorization/*/Write`, so the exclusions
+        // decide between Admin and Write
+        let can_grant = role_definition.permissions.iter().any(|p| {
+            p.actions.iter().any(|x| action_matches(x, GRANT))
+                && !p.not_actions.iter().any(|x| action_matches(x, GRANT))
+        });
+        if can_grant {
+            PrivilegeLevel::Admin
+        } else if role_definition
+            .permissions
+            .iter()
+            .any(|p| can_change(&p.actions, &p.not_actions))
+        {
+            PrivilegeLevel::Write
+        } else {
+            PrivilegeLevel::Read
+        }
+    }
+}
+
+/// Whether `actions` minus `not_actions` leave anything but reads.
+///
+/// Each pattern is probed with a stand-in action per kind of change, so `*` with `*/write`,
+/// `*/delete` and `*/action` excluded counts as read-only.
+fn can_change(actions: &[String], not_actions: &[String]) -> bool {
+    actions.iter().any(|pattern| {
+        ["write", "delete", "action"].into_iter().any(|verb| {
+            let probe = match pattern.strip_suffix('*') {
+                Some(prefix) => format!("{}x/{verb}", prefix.replace('*', "x")),
+                None => pattern.replace('*', "x"),
+            };
+            !probe.to_lowercase().ends_with("/read")
+                && !not_actions.iter().any(|x| action_matches(x, &probe))
+        })
+    })
+}
+
+impl std::fmt::Display for PrivilegeLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PrivilegeLevel::Read => "Read",
+            PrivilegeLevel::Write => "Write",
+            PrivilegeLevel::Admin => "Admin",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn classifies_built_in_roles() {
+        let level =
+            |actions: &[&str]| PrivilegeLevel::of(&fixtures::built_in_role("Role", actions));
+        assert_eq!(level(&["*"]), PrivilegeLevel::Admin);
+        assert_eq!(
+            level(&["*/read", "Microsoft.Authorization/roleAssignments/write"]),
+            PrivilegeLevel::Admin
+        );
+        assert_eq!(level(&["Microsoft.Compute/*"]), PrivilegeLevel::Write);
+        assert_eq!(level(&["*/read"]), PrivilegeLevel::Read);
+        assert_eq!(level(&["*/Read"]), PrivilegeLevel::Read);
+    }
+
+    #[test]
+    fn excluded_changes_leave_read() {
+        let reader = fixtures::built_in_role_excluding(
+            "Everything but changes",
+            &["*"],
+            &["*/write", "*/delete", "*/action"],
+        );
+        assert_eq!(PrivilegeLevel::of(&reader), PrivilegeLevel::Read);
+        let no_deletes = fixtures::built_in_role_excluding(
+            "No deletes",
+            &["Microsoft.Compute/*"],
+            &["*/delete", "*/action"],
+        );
+        assert_eq!(PrivilegeLevel::of(&no_deletes), PrivilegeLevel::Write);
+    }
+
+    #[test]
+    fn contributor_can_write_but_not_grant() {
+        let contributor = fixtures::built_in_role_excluding(
+            "Contributor",
+            &["*"],
+            &[
+                "Microsoft.Authorization/*/Delete",
+                "Microsoft.Authorization/*/Write",
+                "Microsoft.Authorization/elevateAccess/Action",
+            ],
+        );
+        assert_eq!(PrivilegeLevel::of(&contributor), PrivilegeLevel::Write);
+        let user_access_administrator = fixtures::built_in_role_excluding(
+            "User Access Administrator",
+            &["*/read", "Microsoft.Authorization/*"],
+            &[],
+        );
+        assert_eq!(
+            PrivilegeLevel::of(&user_access_administrator),
+            PrivilegeLevel::Admin
+        );
+    }
+
+    #[test]
+    fn matches_actions_with_wildcards() {
+        let grant = "Microsoft.Authorization/roleAssignments/write";
+        assert!(action_matches("*", grant));
+        assert!(action_matches("Microsoft.Authorization/*/Write", grant));
+        assert!(action_matches(
+            "microsoft.authorization/roleassignments/WRITE",
+            grant
+        ));
+        assert!(!action_matches("Microsoft.Authorization/*/Delete", grant));
+        assert!(!action_matches("*/read", grant));
+        assert!(!action_matches(
+            "Microsoft.Authorization/roleAssignments",
+            grant
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+use cloud_terrastodon_command::CommandBuilder;
+use cloud_terrastodon_command::CommandKind;
+use eyre::Context;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+/// Largest page size Azure Resource Graph allows.
+const PAGE_SIZE: &str = "1000";
+
+#[derive(Deserialize)]
+struct ResourceGraphPage<T> {
+    data: Vec<T>,
+    skip_token: Option<String>,
+}
+
+/// Run a Resource Graph query across the whole tenant, following skip tokens until exhausted.
+pub async fn query_resource_graph<T: DeserializeOwned>(query: &str) -> eyre::Result<Vec<T>> {
+    let mut rtn = Vec::new();
+    let mut skip_token: Option<String> = None;
+    loop {
+        let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+        cmd.args([
+            "graph",
+            "query",
+            "--graph-query",
+            query,
+            "--first",
+            PAGE_SIZE,
+        ]);
+        if let Some(skip_token) = &skip_token {
+            cmd.args(["--skip-token", skip_token]);
+        }
+        let page: ResourceGraphPage<T> =
+            cmd.run().await.wrap_err("Querying Azure Resource Graph")?;
+        debug!("Resource Graph returned {} rows", page.data.len());
+        rtn.extend(page.data);
+        match page.skip_token {
+            Some(next) if !next.is_empty() => skip_token = Some(next),
+            _ => return Ok(rtn),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::azure::resource_graph::query_resource_graph;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A resource inside a resource group, e.g. a storage account or key vault.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResourceEntry {
+    /// Full resource id, which is also its RBAC scope
+    pub id: String,
+    pub name: String,
+    /// Resource type, e.g. `Microsoft.Storage/storageAccounts`
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl ResourceEntry {
+    /// The `/subscriptions/<id>/resourceGroups/<name>` prefix of the resource id.
+    pub fn resource_group_id(&self) -> Option<String> {
+        let segments = self
+            .id
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .take(4)
+            .collect::<Vec<_>>();
+        match segments.as_slice() {
+            [subscriptions, subscription_id, resource_groups, name]
+                if subscriptions.eq_ignore_ascii_case("subscriptions")
+                    && resource_groups.eq_ignore_ascii_case("resourceGroups") =>
+            {
+                Some(format!(
+                    "/subscriptions/{subscription_id}/resourceGroups/{name}"
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// The last segment of the resource type, e.g. `storageAccounts`.
+    pub fn short_kind(&self) -> &str {
+        self.kind.rsplit('/').next().unwrap_or(&self.kind)
+    }
+}
+
+const RESOURCES_QUERY: &str = r#"resources
+| where isnotempty(resourceGroup)
+| project id, name, type"#;
+
+pub async fn fetch_all_resources() -> eyre::Result<Vec<ResourceEntry>> {
+    query_resource_graph(RESOURCES_QUERY).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_group_id_from_resource_id() {
+        let resource = ResourceEntry {
+            id: "/subscriptions/abc/resourceGroups/rg-app/providers/Microsoft.KeyVault/vaults/kv-app"
+                .to_string(),
+            name: "kv-app".to_string(),
+            kind: "Microsoft.KeyVault/vaults".to_string(),
+        };
+        assert_eq!(
+            resource.resource_group_id().as_deref(),
+            Some("/subscriptions/abc/resourceGroups/rg-app")
+        );
+        assert_eq!(resource.short_kind(), "vaults");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::azure::hierarchy::ScopeHierarchy;
+use cloud_terrastodon_command::CommandBuilder;
+use cloud_terrastodon_command::CommandKind;
+use eyre::Context;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use tracing::debug;
+use tracing::warn;
+
+#[derive(Deserialize)]
+struct ArmPage<T> {
+    value: Vec<T>,
+    #[serde(rename = "nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GraphPage<T> {
+    value: Vec<T>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+/// GET a Microsoft Graph list endpoint, following `@odata.nextLink` until exhausted.
+///
+/// `path` is everything after `https://graph.microsoft.com/v1.0`, including any query.
+pub async fn list_graph<T: DeserializeOwned>(path: &str) -> eyre::Result<Vec<T>> {
+    let mut rtn = Vec::new();
+    let mut url = Some(format!("https://graph.microsoft.com/v1.0{path}"));
+    while let Some(next) = url.take() {
+        let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+        cmd.args(["rest", "--method", "get", "--url", &next]);
+        let page: GraphPage<T> = cmd
+            .run()
+            .await
+            .wrap_err_with(|| format!("Listing {path}"))?;
+        debug!("{path} returned {} items", page.value.len());
+        rtn.extend(page.value);
+        url = page.next_link.filter(|x| !x.is_empty());
+    }
+    Ok(rtn)
+}
+
+/// GET an Azure Resource Manager list endpoint, following `nextLink` until exhausted.
+///
+/// `path` is everything after the management endpoint, including the `api-version` query.
+pub async fn list_arm<T: DeserializeOwned>(path: &str) -> eyre::Result<Vec<T>> {
+    let mut rtn = Vec::new();
+    let mut url = Some(format!("https://management.azure.com{path}"));
+    while let Some(next) = url.take() {
+        let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+        cmd.args(["rest", "--method", "get", "--url", &next]);
+        let page: ArmPage<T> = cmd
+            .run()
+            .await
+            .wrap_err_with(|| format!("Listing {path}"))?;
+        debug!("{path} returned {} items", page.value.len());
+        rtn.extend(page.value);
+        url = page.next_link.filter(|x| !x.is_empty());
+    }
+    Ok(rtn)
+}
+
+/// [`list_arm`] the provider `path` under every subscription, keeping the first item per `id`.
+///
+/// `path` starts at `/providers/` and includes the `api-version` query. A subscription that
+/// cannot be listed, such as one the caller cannot read, is logged and skipped so the others
+/// still load; the error is only returned when every subscription fails.
+pub async fn list_arm_per_subscription<T: DeserializeOwned>(
+    hierarchy: &ScopeHierarchy,
+    path: &str,
+    id: impl Fn(&T) -> &str,
+) -> eyre::Result<Vec<T>> {
+    let mut seen = HashSet::new();
+    let mut rtn = Vec::new();
+    let mut any_listed = false;
+    let mut last_error = None;
+    for subscription in &hierarchy.subscriptions {
+        match list_arm::<T>(&format!("{}{path}", subscription.id())).await {
+            Ok(page) => {
+                any_listed = true;
+                for item in page {
+                    if seen.insert(id(&item).to_lowercase()) {
+                        rtn.push(item);
+                    }
+                }
+            }
+            Err(error) => {
+                warn!(
+                    "Skipping subscription {}: {error:?}",
+                    subscription.display_name
+                );
+                last_error = Some(error);
+            }
+        }
+    }
+    match last_error {
+        Some(error) if !any_listed => Err(error),
+        _ => Ok(rtn),
+    }
+}
@@ -0,0 +1,56 @@
+use cloud_terrastodon_command::CommandBuilder;
+use cloud_terrastodon_command::CommandKind;
+use eyre::Context;
+use serde::Deserialize;
+
+/// The arguments of `az role assignment create`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewRoleAssignment {
+    pub assignee_object_id: String,
+    /// `User`, `ServicePrincipal` or `Group`; saves a Graph lookup when known
+    pub assignee_principal_type: Option<String>,
+    /// Role name or role definition id
+    pub role: String,
+    pub scope: String,
+    pub condition: Option<String>,
+    pub condition_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreatedRoleAssignment {
+    id: String,
+}
+
+/// Create a role assignment, returning its id.
+pub async fn create_role_assignment(new: &NewRoleAssignment) -> eyre::Result<String> {
+    let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+    cmd.args(["role", "assignment", "create"]);
+    cmd.args(["--assignee-object-id", &new.assignee_object_id]);
+    if let Some(kind) = &new.assignee_principal_type {
+        cmd.args(["--assignee-principal-type", kind]);
+    }
+    cmd.args(["--role", &new.role, "--scope", &new.scope]);
+    if let Some(condition) = &new.condition {
+        cmd.args(["--condition", condition]);
+    }
+    if let Some(version) = &new.condition_version {
+        cmd.args(["--condition-version", version]);
+    }
+    let created = cmd.run::<CreatedRoleAssignment>().await.wrap_err_with(|| {
+        format!(
+            "Creating {} assignment for {} at {}",
+            new.role, new.assignee_object_id, new.scope
+        )
+    })?;
+    Ok(created.id)
+}
+
+/// Remove a role assignment, given its expanded id.
+pub async fn delete_role_assignment(id: &str) -> eyre::Result<()> {
+    let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+    cmd.args(["role", "assignment", "delete", "--ids", id]);
+    cmd.run_raw()
+        .await
+        .wrap_err_with(|| format!("Deleting role assignment {id}"))?;
+    Ok(())
+}
@@ -0,0 +1,452 @@
+use crate::cli::global_args::GlobalArgs;
+use crate::config::Config;
+use cloud_terrastodon_command::CommandBuilder;
+use cloud_terrastodon_command::CommandKind;
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::debug;
+use tracing::warn;
+
+/// Cached data older than this is refetched unless the `cache_ttl` setting says otherwise.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The kinds of Azure data we cache, each stored in its own file per tenant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchKind {
+    ResourceGroups,
+    RoleDefinitionsAndAssignments,
+    Users,
+    ServicePrincipals,
+    SecurityGroups,
+    Hierarchy,
+    Resources,
+    DenyAssignments,
+    EligibleAssignments,
+}
+
+impl FetchKind {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            FetchKind::ResourceGroups => "resource_groups.json",
+            FetchKind::RoleDefinitionsAndAssignments => "role_definitions_and_assignments.json",
+            FetchKind::Users => "users.json",
+            FetchKind::ServicePrincipals => "service_principals.json",
+            FetchKind::SecurityGroups => "security_groups.json",
+            FetchKind::Hierarchy => "hierarchy.json",
+            FetchKind::Resources => "resources.json",
+            FetchKind::DenyAssignments => "deny_assignments.json",
+            FetchKind::EligibleAssignments => "eligible_assignments.json",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FetchKind::ResourceGroups => "fetch_all_resource_groups",
+            FetchKind::RoleDefinitionsAndAssignments => {
+                "fetch_all_role_definitions_and_assignments"
+            }
+            FetchKind::Users => "fetch_all_users",
+            FetchKind::ServicePrincipals => "fetch_all_service_principals",
+            FetchKind::SecurityGroups => "fetch_all_security_groups",
+            FetchKind::Hierarchy => "fetch_scope_hierarchy",
+            FetchKind::Resources => "fetch_all_resources",
+            FetchKind::DenyAssignments => "fetch_all_deny_assignments",
+            FetchKind::EligibleAssignments => "fetch_all_eligible_assignments",
+        }
+    }
+
+    /// What the data is called in messages to the user, e.g. "users".
+    pub fn label(&self) -> &'static str {
+        match self {
+            FetchKind::ResourceGroups => "resource groups",
+            FetchKind::RoleDefinitionsAndAssignments => "role assignments",
+            FetchKind::Users => "users",
+            FetchKind::ServicePrincipals => "service principals",
+            FetchKind::SecurityGroups => "security groups",
+            FetchKind::Hierarchy => "scope hierarchy",
+            FetchKind::Resources => "resources",
+            FetchKind::DenyAssignments => "deny assignments",
+            FetchKind::EligibleAssignments => "eligible assignments",
+        }
+    }
+}
+
+impl fmt::Display for FetchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Serve fresh entries from disk and write back new fetches
+    Enabled,
+    /// Ignore existing entries but write back new fetches
+    Refresh,
+    /// Neither read nor write
+    Disabled,
+}
+
+/// A cached value along with when it was fetched, in seconds since the unix epoch.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CacheEntry<T> {
+    pub fetched_at: u64,
+    pub value: T,
+}
+
+impl<T> CacheEntry<T> {
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now_secs().saturating_sub(self.fetched_at))
+    }
+}
+
+/// On-disk cache of Azure fetches, keyed by tenant and [`FetchKind`].
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    mode: CacheMode,
+    /// Known once the Azure CLI has been asked, so it is not asked again
+    tenant_id: Option<String>,
+}
+
+impl Cache {
+    /// A cache rooted at `dir`, for tests that need one outside this module.
+    #[cfg(test)]
+    pub fn at(dir: PathBuf, ttl: Duration, mode: CacheMode) -> Self {
+        Self {
+            dir,
+            ttl,
+            mode,
+            tenant_id: None,
+        }
+    }
+
+    /// Open the cache for the tenant the Azure CLI is currently logged in to.
+    ///
+    /// Fails if that is not the tenant the configuration expects.
+    pub async fn open(global_args: &GlobalArgs, config: &Config) -> eyre::Result<Self> {
+        let mode = global_args.cache_mode();
+        let ttl = config.cache_ttl();
+        let disabled = Self {
+            dir: PathBuf::new(),
+            ttl,
+            mode,
+            tenant_id: None,
+        };
+        if mode == CacheMode::Disabled && config.expected_tenant.is_none() {
+            return Ok(disabled);
+        }
+        let tenant_id = fetch_current_tenant_id().await?;
+        config.check_tenant(&tenant_id)?;
+        if mode == CacheMode::Disabled {
+            return Ok(Self {
+                tenant_id: Some(tenant_id),
+                ..disabled
+            });
+        }
+        let dir = cache_root()?.join(&tenant_id);
+        debug!("Using cache directory {}", dir.display());
+        Ok(Self {
+            dir,
+            ttl,
+            mode,
+            tenant_id: Some(tenant_id),
+        })
+    }
+
+    /// The same cache, but ignoring existing entries while still writing fetches back.
+    ///
+    /// For data that must be current, such as the directory a report flags missing principals
+    /// against; a disabled cache stays disabled.
+    pub fn refreshing(&self) -> Cache {
+        let mode = match self.mode {
+            CacheMode::Enabled | CacheMode::Refresh => CacheMode::Refresh,
+            CacheMode::Disabled => CacheMode::Disabled,
+        };
+        Cache {
+            mode,
+            ..self.clone()
+        }
+    }
+
+    /// The tenant this cache belongs to, asking the Azure CLI only if opening it did not.
+    pub async fn tenant_id(&self) -> eyre::Result<String> {
+        match &self.tenant_id {
+            Some(tenant_id) => Ok(tenant_id.clone()),
+            None => fetch_current_tenant_id().await,
+        }
+    }
+
+    /// The cache of every tenant under `root`, for lookups that cannot wait for the Azure CLI to
+    /// report the current tenant, such as shell completion.
+    ///
+    /// Directories not named for a tenant id, such as a service principal's Azure CLI config
+    /// dir, are skipped.
+    pub fn tenants_in(root: &Path) -> eyre::Result<Vec<Self>> {
+        let entries = match std::fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .wrap_err_with(|| format!("Listing cache directory {}", root.display()));
+            }
+        };
+        let mut rtn = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && is_tenant_id(&name) {
+                rtn.push(Self {
+                    dir: entry.path(),
+                    ttl: DEFAULT_CACHE_TTL,
+                    mode: CacheMode::Enabled,
+                    tenant_id: Some(name),
+                });
+            }
+        }
+        Ok(rtn)
+    }
+
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    pub fn is_fresh<T>(&self, entry: &CacheEntry<T>) -> bool {
+        entry.age() < self.ttl
+    }
+
+    /// Read a cached entry regardless of age.
+    ///
+    /// Missing or unreadable entries are treated as a cache miss.
+    pub fn read<T: DeserializeOwned>(&self, kind: FetchKind) -> Option<CacheEntry<T>> {
+        if self.mode != CacheMode::Enabled {
+            return None;
+        }
+        let path = self.dir.join(kind.file_name());
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read cache file {}: {e}", path.display());
+                return None;
+            }
+        };
+        match serde_json::from_slice(&contents) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Ignoring corrupt cache file {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Write a freshly fetched value, logging rather than failing on errors
+    /// since the cache is only ever an optimization.
+    pub fn write<T: Serialize>(&self, kind: FetchKind, value: &T) {
+        if self.mode == CacheMode::Disabled {
+            return;
+        }
+        if let Err(e) = self.try_write(kind, value) {
+            warn!("Failed to write cache entry for {kind}: {e:?}");
+        }
+    }
+
+    fn try_write<T: Serialize>(&self, kind: FetchKind, value: &T) -> eyre::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .wrap_err_with(|| format!("Creating cache directory {}", self.dir.display()))?;
+        let entry = CacheEntry {
+            fetched_at: now_secs(),
+            value,
+        };
+        let path = self.dir.join(kind.file_name());
+        // Write to a sibling file first so readers never observe a partial entry
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Return the cached value if it is still fresh, otherwise await `fetch` and cache the result.
+    pub async fn get_or_fetch<T, Fut>(&self, kind: FetchKind, fetch: Fut) -> eyre::Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        Fut: Future<Output = eyre::Result<T>>,
+    {
+        if let Some(entry) = self.read::<T>(kind)
+            && self.is_fresh(&entry)
+        {
+            debug!("Using cached {kind} from {:?} ago", entry.age());
+            return Ok(entry.value);
+        }
+        let value = fetch.await?;
+        self.write(kind, &value);
+        Ok(value)
+    }
+}
+
+/// Root directory holding one subdirectory per tenant.
+///
+/// Honours `RATUI_CACHE_DIR` so tests and CI can point it somewhere disposable.
+pub fn cache_root() -> eyre::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("RATUI_CACHE_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let Some(dir) = dirs::cache_dir() else {
+        eyre::bail!("Could not determine the user cache directory, set RATUI_CACHE_DIR instead");
+    };
+    Ok(dir.join("role-assignment-tui"))
+}
+
+/// The tenant the Azure CLI is logged in to.
+pub async fn fetch_current_tenant_id() -> eyre::Result<String> {
+    let mut cmd = CommandBuilder::new(CommandKind::AzureCLI);
+    cmd.args(["account", "show", "--query", "tenantId"]);
+    let tenant_id: String = cmd.run().await.wrap_err("Determining the current tenant")?;
+    Ok(tenant_id)
+}
+
+/// Whether `name` is a GUID, the form tenant ids take.
+fn is_tenant_id(name: &str) -> bool {
+    name.len() == 36
+        && name.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str, ttl: Duration, mode: CacheMode) -> Cache {
+        let dir = std::env::temp_dir().join(format!("cache-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Cache::at(dir, ttl, mode)
+    }
+
+    fn path(cache: &Cache) -> PathBuf {
+        cache.dir.join(FetchKind::Users.file_name())
+    }
+
+    async fn fetch(cache: &Cache, value: u32) -> u32 {
+        cache
+            .get_or_fetch(FetchKind::Users, async move { Ok(value) })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_fresh_entries_and_refetches_stale_ones() {
+        let fresh = cache("fresh", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        assert_eq!(fetch(&fresh, 1).await, 1);
+        assert_eq!(fetch(&fresh, 2).await, 1);
+
+        let stale = Cache {
+            ttl: Duration::ZERO,
+            ..fresh.clone()
+        };
+        assert_eq!(fetch(&stale, 3).await, 3);
+        assert_eq!(fetch(&fresh, 4).await, 3);
+    }
+
+    #[tokio::test]
+    async fn refreshing_ignores_entries_unless_disabled() {
+        let enabled = cache("refreshing", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        assert_eq!(fetch(&enabled, 1).await, 1);
+        assert_eq!(fetch(&enabled.refreshing(), 2).await, 2);
+        assert_eq!(fetch(&enabled, 3).await, 2);
+        let disabled = Cache {
+            mode: CacheMode::Disabled,
+            ..enabled.clone()
+        };
+        assert_eq!(disabled.refreshing().mode(), CacheMode::Disabled);
+    }
+
+    #[tokio::test]
+    async fn refresh_ignores_entries_but_writes_back() {
+        let enabled = cache("refresh", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        assert_eq!(fetch(&enabled, 1).await, 1);
+        let refresh = Cache {
+            mode: CacheMode::Refresh,
+            ..enabled.clone()
+        };
+        assert_eq!(fetch(&refresh, 2).await, 2);
+        assert_eq!(fetch(&enabled, 3).await, 2);
+    }
+
+    #[tokio::test]
+    async fn no_cache_neither_reads_nor_writes() {
+        let enabled = cache("disabled", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        assert_eq!(fetch(&enabled, 1).await, 1);
+        let disabled = Cache {
+            mode: CacheMode::Disabled,
+            ..enabled.clone()
+        };
+        assert_eq!(fetch(&disabled, 2).await, 2);
+        assert_eq!(fetch(&enabled, 3).await, 1);
+
+        let untouched = cache("disabled-untouched", DEFAULT_CACHE_TTL, CacheMode::Disabled);
+        assert_eq!(fetch(&untouched, 4).await, 4);
+        assert!(!untouched.dir.exists());
+    }
+
+    #[tokio::test]
+    async fn recovers_from_a_corrupt_entry() {
+        let cache = cache("corrupt", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        std::fs::create_dir_all(&cache.dir).unwrap();
+        std::fs::write(path(&cache), b"{\"fetched_at\":").unwrap();
+        assert!(cache.read::<u32>(FetchKind::Users).is_none());
+        assert_eq!(fetch(&cache, 1).await, 1);
+        assert_eq!(cache.read::<u32>(FetchKind::Users).unwrap().value, 1);
+    }
+
+    #[test]
+    fn lists_only_tenant_directories() {
+        let root = std::env::temp_dir().join(format!("cache-test-tenants-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let tenant = "e220a839-7b1d-4daf-ae78-9e6aa1b965f4";
+        std::fs::create_dir_all(root.join(tenant)).unwrap();
+        std::fs::create_dir_all(root.join("az-service-principal")).unwrap();
+        std::fs::write(root.join("0b7e2c5a-54f1-4c8e-9d2a-7f3b1e6a9c04"), b"").unwrap();
+
+        let tenants = Cache::tenants_in(&root).unwrap();
+        assert_eq!(
+            tenants.iter().map(|x| x.dir.clone()).collect::<Vec<_>>(),
+            [root.join(tenant)]
+        );
+        assert!(Cache::tenants_in(&root.join("missing")).unwrap().is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn writes_through_a_temporary_file() {
+        let cache = cache("atomic", DEFAULT_CACHE_TTL, CacheMode::Enabled);
+        let tmp = path(&cache).with_extension("json.tmp");
+        // A writer that died part way leaves its temporary file behind
+        std::fs::create_dir_all(&cache.dir).unwrap();
+        std::fs::write(&tmp, b"partial").unwrap();
+        assert!(cache.read::<u32>(FetchKind::Users).is_none());
+
+        cache.write(FetchKind::Users, &7u32);
+        assert!(!tmp.exists());
+        let entry = cache.read::<u32>(FetchKind::Users).unwrap();
+        assert_eq!(entry.value, 7);
+        assert!(cache.is_fresh(&entry));
+    }
+}
@@ -0,0 +1,35 @@
+use arbitrary::Unstructured;
+use std::path::PathBuf;
+
+/// Characters that survive a round trip through the command line unquoted.
+const PATH_CHARS: &[char] = &['a', 'b', 'c', 'x', 'y', 'z', '0', '1', '9', '.', '_', '/'];
+
+/// Arbitrary option values that clap will parse back to the same value.
+///
+/// The derived impl produces empty strings and leading hyphens, which clap rejects.
+pub fn value(u: &mut Unstructured) -> arbitrary::Result<String> {
+    let len = u.int_in_range(1..=16)?;
+    let mut value = String::with_capacity(len);
+    for _ in 0..len {
+        value.push(*u.choose(PATH_CHARS)?);
+    }
+    Ok(value)
+}
+
+pub fn optional_value(u: &mut Unstructured) -> arbitrary::Result<Option<String>> {
+    if !u.arbitrary::<bool>()? {
+        return Ok(None);
+    }
+    value(u).map(Some)
+}
+
+/// Arbitrary repeated option values that clap will parse back to the same values.
+pub fn values(u: &mut Unstructured) -> arbitrary::Result<Vec<String>> {
+    let len = u.int_in_range(0..=3)?;
+    (0..len).map(|_| value(u)).collect()
+}
+
+/// Arbitrary optional paths that clap will parse back to the same value.
+pub fn optional_path(u: &mut Unstructured) -> arbitrary::Result<Option<PathBuf>> {
+    Ok(optional_value(u)?.map(PathBuf::from))
+}
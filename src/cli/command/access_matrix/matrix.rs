@@ -0,0 +1,440 @@
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::privilege::PrivilegeLevel;
+use arbitrary::Arbitrary;
+use clap::ValueEnum;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use itertools::Itertools;
+use rust_xlsxwriter::Format;
+use rust_xlsxwriter::Workbook;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// What each cell of the matrix shows.
+#[derive(ValueEnum, Clone, Copy, Default, Arbitrary, PartialEq, Eq, Debug)]
+pub enum CellContent {
+    /// Role names, with inherited ones marked
+    #[default]
+    Roles,
+    /// The highest privilege level among the roles: Read, Write or Admin, with a higher level
+    /// held only on resources in the group marked separately
+    Privilege,
+}
+
+/// Principals down the side and resource groups across the top.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccessMatrix {
+    /// Column headers, one per resource group
+    pub resource_groups: Vec<String>,
+    pub rows: Vec<MatrixRow>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatrixRow {
+    pub principal: String,
+    pub principal_type: String,
+    pub object_id: String,
+    /// One per resource group, empty where the principal has no access
+    pub cells: Vec<String>,
+}
+
+impl AccessMatrix {
+    /// The header row followed by one row per principal.
+    pub fn records(&self) -> Vec<Vec<&str>> {
+        let header = ["Principal", "Type", "Object id"]
+            .into_iter()
+            .chain(self.resource_groups.iter().map(String::as_str))
+            .collect();
+        let rows = self.rows.iter().map(|row| {
+            [
+                row.principal.as_str(),
+                row.principal_type.as_str(),
+                row.object_id.as_str(),
+            ]
+            .into_iter()
+            .chain(row.cells.iter().map(String::as_str))
+            .collect()
+        });
+        std::iter::once(header).chain(rows).collect()
+    }
+}
+
+struct Grant<'a> {
+    role_definition: &'a RoleDefinition,
+    origin: GrantOrigin,
+}
+
+/// Where an assignment counted in a resource group's column was made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GrantOrigin {
+    ResourceGroup,
+    /// The subscription or a management group above it
+    Inherited,
+    /// A resource in the group, by name
+    Resource(String),
+}
+
+/// Tabulate who can do what in each resource group, counting assignments inherited from the
+/// subscription and management groups above it and those made on resources inside it.
+pub fn build_matrix(
+    principals: &PrincipalDirectory,
+    hierarchy: &ScopeHierarchy,
+    resource_groups: &[ResourceGroup],
+    rbac: &RoleDefinitionsAndAssignments,
+    content: CellContent,
+) -> AccessMatrix {
+    let by_scope = rbac
+        .iter_role_assignments()
+        .into_group_map_by(|(ra, _)| ra.scope.expanded_form().to_lowercase());
+    let resource_groups = resource_groups
+        .iter()
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect_vec();
+
+    let mut grants: HashMap<PrincipalId, BTreeMap<usize, Vec<Grant>>> = HashMap::new();
+    for (column, rg) in resource_groups.iter().enumerate() {
+        let rg_scope = rg.id.expanded_form().to_lowercase();
+        let resource_prefix = format!("{rg_scope}/providers/");
+        let scopes = std::iter::once(rg_scope.clone())
+            .chain(hierarchy.scopes_above_subscription(&rg.subscription_id.to_string()));
+        let mut column_grants = Vec::new();
+        for (depth, scope) in scopes.enumerate() {
+            let origin = match depth {
+                0 => GrantOrigin::ResourceGroup,
+                _ => GrantOrigin::Inherited,
+            };
+            for (ra, rd) in by_scope.get(&scope.to_lowercase()).into_iter().flatten() {
+                column_grants.push((ra.principal_id, rd, origin.clone()));
+            }
+        }
+        for (scope, assignments) in &by_scope {
+            if !scope.starts_with(&resource_prefix) {
+                continue;
+            }
+            for (ra, rd) in assignments {
+                // From the original id so the name keeps the case Azure reported
+                let scope = ra.scope.expanded_form();
+                let name = scope.rsplit('/').next().unwrap_or(&scope).to_string();
+                column_grants.push((ra.principal_id, rd, GrantOrigin::Resource(name)));
+            }
+        }
+        for (principal_id, role_definition, origin) in column_grants {
+            grants
+                .entry(principal_id)
+                .or_default()
+                .entry(column)
+                .or_default()
+                .push(Grant {
+                    role_definition,
+                    origin,
+                });
+        }
+    }
+
+    let rows = grants
+        .into_iter()
+        .map(|(principal_id, columns)| {
+            let mut cells = vec![String::new(); resource_groups.len()];
+            for (column, grants) in columns {
+                cells[column] = cell(&grants, content);
+            }
+            MatrixRow {
+                principal: principals.name(&principal_id),
+                principal_type: principals
+                    .kind(&principal_id)
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                object_id: principal_id.to_string(),
+                cells,
+            }
+        })
+        .sorted_by(|a, b| {
+            (a.principal.to_lowercase(), &a.object_id)
+                .cmp(&(b.principal.to_lowercase(), &b.object_id))
+        })
+        .collect();
+
+    AccessMatrix {
+        resource_groups: column_headers(hierarchy, &resource_groups),
+        rows,
+    }
+}
+
+fn cell(grants: &[Grant], content: CellContent) -> String {
+    match content {
+        CellContent::Roles => grants
+            .iter()
+            .into_group_map_by(|x| x.role_definition.display_name.as_str())
+            .into_iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(|(name, grants)| {
+                // A role held across the group does not need its narrower grants called out too
+                if grants
+                    .iter()
+                    .any(|x| x.origin == GrantOrigin::ResourceGroup)
+                {
+                    name.to_string()
+                } else if grants.iter().any(|x| x.origin == GrantOrigin::Inherited) {
+                    format!("{name} (inherited)")
+                } else {
+                    let resources = grants
+                        .iter()
+                        .filter_map(|x| match &x.origin {
+                            GrantOrigin::Resource(resource) => Some(resource.as_str()),
+                            _ => None,
+                        })
+                        .sorted()
+                        .dedup()
+                        .join(", ");
+                    format!("{name} (on {resources})")
+                }
+            })
+            .join("; "),
+        CellContent::Privilege => {
+            // A grant on one resource says little about the rest of the group, so it is only
+            // called out when it goes beyond what the principal holds across the group
+            let group_wide = grants
+                .iter()
+                .filter(|x| !matches!(x.origin, GrantOrigin::Resource(_)))
+                .map(|x| PrivilegeLevel::of(x.role_definition))
+                .max();
+            let on_resources = grants
+                .iter()
+                .filter_map(|x| match &x.origin {
+                    GrantOrigin::Resource(resource) => {
+                        Some((PrivilegeLevel::of(x.role_definition), resource.as_str()))
+                    }
+                    _ => None,
+                })
+                .filter(|(level, _)| Some(*level) > group_wide)
+                .collect_vec();
+            let narrower = on_resources
+                .iter()
+                .map(|(level, _)| *level)
+                .max()
+                .map(|highest| {
+                    let count = on_resources
+                        .iter()
+                        .filter(|(level, _)| *level == highest)
+                        .map(|(_, resource)| resource)
+                        .unique()
+                        .count();
+                    let plural = if count == 1 { "" } else { "s" };
+                    format!("{highest} (on {count} resource{plural})")
+                });
+            group_wide
+                .map(|x| x.to_string())
+                .into_iter()
+                .chain(narrower)
+                .join("; ")
+        }
+    }
+}
+
+/// Resource group names, qualified by subscription where two share a name.
+fn column_headers(hierarchy: &ScopeHierarchy, resource_groups: &[&ResourceGroup]) -> Vec<String> {
+    let counts = resource_groups.iter().counts_by(|x| x.name.to_lowercase());
+    resource_groups
+        .iter()
+        .map(|rg| {
+            if counts[&rg.name.to_lowercase()] == 1 {
+                return rg.name.clone();
+            }
+            let subscription_id = rg.subscription_id.to_string();
+            let subscription = hierarchy
+                .subscription_name(&subscription_id)
+                .unwrap_or(&subscription_id);
+            format!("{} ({subscription})", rg.name)
+        })
+        .collect()
+}
+
+/// Write the matrix as CSV, which Excel and Google Sheets open directly.
+pub fn write_csv(matrix: &AccessMatrix, out: impl Write) -> eyre::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for record in matrix.records() {
+        writer.write_record(record.into_iter().map(neutralize_formula))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Spreadsheets run cells starting with these as formulas, and display names are user controlled.
+///
+/// Tab and carriage return are included because some spreadsheets skip them before looking for
+/// a formula, per the OWASP guidance on CSV injection.
+fn neutralize_formula(field: &str) -> String {
+    if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    }
+}
+
+/// Render the matrix as an XLSX workbook with a bold, frozen header and filters.
+pub fn write_xlsx(matrix: &AccessMatrix) -> eyre::Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("Access")?;
+    let bold = Format::new().set_bold();
+    let records = matrix.records();
+    for (row, record) in records.iter().enumerate() {
+        for (column, value) in record.iter().enumerate() {
+            let (row, column) = (row as u32, column as u16);
+            if row == 0 {
+                worksheet.write_string_with_format(row, column, value, &bold)?;
+            } else {
+                worksheet.write_string(row, column, value)?;
+            }
+        }
+    }
+    let last_column = (records[0].len() - 1) as u16;
+    worksheet.set_freeze_panes(1, 1)?;
+    worksheet.autofilter(0, 0, (records.len() - 1) as u32, last_column)?;
+    worksheet.autofit();
+    Ok(workbook.save_to_buffer()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn counts_inherited_and_resource_assignments_in_each_resource_group() {
+        let alice = fixtures::user("Alice");
+        let admins = fixtures::group("=Admins");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let vault = fixtures::resource(&rg_data, "kv-Data", "Microsoft.KeyVault/vaults");
+        let hierarchy = fixtures::hierarchy();
+        let rbac = fixtures::rbac(
+            [reader.clone(), owner.clone()],
+            [
+                fixtures::role_assignment(&rg_app.id, &reader, alice.id),
+                fixtures::role_assignment_at(&hierarchy.subscriptions[0].id(), &reader, alice.id),
+                fixtures::role_assignment_at(&vault.id, &owner, alice.id),
+                fixtures::role_assignment_at(&vault.id, &reader, alice.id),
+                fixtures::role_assignment_at(
+                    &hierarchy.management_groups[0].id(),
+                    &owner,
+                    admins.id,
+                ),
+            ],
+        );
+        let mut principals = PrincipalDirectory::default();
+        principals.add_users(std::slice::from_ref(&alice));
+        principals.add_groups(std::slice::from_ref(&admins));
+        let resource_groups = [rg_data, rg_app];
+
+        let matrix = build_matrix(
+            &principals,
+            &hierarchy,
+            &resource_groups,
+            &rbac,
+            CellContent::Roles,
+        );
+        assert_eq!(matrix.resource_groups, ["rg-app", "rg-data"]);
+        let cells = matrix
+            .rows
+            .iter()
+            .map(|x| (x.principal.as_str(), x.cells.clone()))
+            .collect_vec();
+        assert_eq!(
+            cells,
+            [
+                (
+                    "=Admins",
+                    vec![
+                        "Owner (inherited)".to_string(),
+                        "Owner (inherited)".to_string()
+                    ]
+                ),
+                (
+                    "Alice",
+                    vec![
+                        "Reader".to_string(),
+                        "Owner (on kv-Data); Reader (inherited)".to_string()
+                    ]
+                ),
+            ]
+        );
+
+        let matrix = build_matrix(
+            &principals,
+            &hierarchy,
+            &resource_groups,
+            &rbac,
+            CellContent::Privilege,
+        );
+        assert_eq!(matrix.rows[0].cells, ["Admin", "Admin"]);
+        assert_eq!(
+            matrix.rows[1].cells,
+            ["Read", "Read; Admin (on 1 resource)"]
+        );
+
+        let mut csv = Vec::new();
+        write_csv(&matrix, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Principal,Type,Object id,rg-app,rg-data")
+        );
+        assert!(lines.next().unwrap().starts_with("'=Admins,Group,"));
+    }
+
+    #[test]
+    fn neutralizes_every_formula_prefix() {
+        for field in ["=1+1", "+1", "-1", "@SUM(A1)", "\t=1", "\r=1"] {
+            assert_eq!(neutralize_formula(field), format!("'{field}"));
+        }
+        assert_eq!(neutralize_formula("Alice"), "Alice");
+        assert_eq!(neutralize_formula("a=b"), "a=b");
+    }
+
+    #[test]
+    fn writes_a_workbook_with_an_access_sheet() {
+        use calamine::Reader;
+        use calamine::Xlsx;
+        use calamine::open_workbook_from_rs;
+
+        let matrix = AccessMatrix {
+            resource_groups: vec!["rg-app".to_string()],
+            rows: vec![MatrixRow {
+                principal: "Alice".to_string(),
+                principal_type: "User".to_string(),
+                object_id: "afea254c-f343-41c1-8afe-254cf34341c1".to_string(),
+                cells: vec!["Reader".to_string()],
+            }],
+        };
+        let bytes = write_xlsx(&matrix).unwrap();
+        let mut workbook: Xlsx<_> = open_workbook_from_rs(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(workbook.sheet_names(), ["Access"]);
+        let rows = workbook
+            .worksheet_range("Access")
+            .unwrap()
+            .rows()
+            .map(|row| row.iter().map(|x| x.to_string()).collect_vec())
+            .collect_vec();
+        assert_eq!(
+            rows,
+            [
+                ["Principal", "Type", "Object id", "rg-app"],
+                [
+                    "Alice",
+                    "User",
+                    "afea254c-f343-41c1-8afe-254cf34341c1",
+                    "Reader"
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+pub mod matrix;
+
+use crate::azure::hierarchy::fetch_scope_hierarchy;
+use crate::azure::principals::PrincipalDirectory;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::command::access_matrix::matrix::CellContent;
+use crate::cli::command::access_matrix::matrix::build_matrix;
+use crate::cli::command::access_matrix::matrix::write_csv;
+use crate::cli::command::access_matrix::matrix::write_xlsx;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use crate::snapshot::TenantSnapshot;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::ValueEnum;
+use cloud_terrastodon_azure::prelude::fetch_all_resource_groups;
+use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments;
+use eyre::Context;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::runtime::Builder;
+use tokio::try_join;
+use tracing::info;
+
+/// Export a principal by resource group access matrix as CSV or XLSX
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct AccessMatrixArgs {
+    #[clap(long, value_enum, default_value_t = MatrixFormat::Csv)]
+    pub format: MatrixFormat,
+
+    /// What each cell shows
+    #[clap(long, value_enum, default_value_t = CellContent::Roles)]
+    pub cells: CellContent,
+
+    /// Write to this file instead of stdout
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub output: Option<PathBuf>,
+
+    /// Read from a snapshot file instead of fetching from Azure
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Arbitrary, PartialEq, Eq, Debug)]
+pub enum MatrixFormat {
+    Csv,
+    Xlsx,
+}
+
+impl AccessMatrixArgs {
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
+        if self.format == MatrixFormat::Xlsx
+            && self.output.is_none()
+            && std::io::stdout().is_terminal()
+        {
+            eyre::bail!("XLSX output is binary; pass --output <FILE> or redirect stdout");
+        }
+        let matrix = Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                let (principals, hierarchy, resource_groups, rbac) = match &self.snapshot {
+                    Some(path) => {
+                        let snapshot = TenantSnapshot::load(path)?;
+                        (
+                            snapshot.principals(),
+                            snapshot.hierarchy(),
+                            snapshot.resource_groups,
+                            snapshot.rbac,
+                        )
+                    }
+                    None => {
+                        let cache = Cache::open(global_args, config).await?;
+                        try_join!(
+                            PrincipalDirectory::fetch_live(&cache),
+                            cache.get_or_fetch(FetchKind::Hierarchy, fetch_scope_hierarchy()),
+                            cache.get_or_fetch(
+                                FetchKind::ResourceGroups,
+                                fetch_all_resource_groups()
+                            ),
+                            cache.get_or_fetch(
+                                FetchKind::RoleDefinitionsAndAssignments,
+                                fetch_all_role_definitions_and_assignments()
+                            )
+                        )?
+                    }
+                };
+                let resource_groups = resource_groups
+                    .into_iter()
+                    .filter(|rg| config.includes(rg))
+                    .collect::<Vec<_>>();
+                eyre::Ok(build_matrix(
+                    &principals,
+                    &hierarchy,
+                    &resource_groups,
+                    &rbac,
+                    self.cells,
+                ))
+            })?;
+
+        let bytes = match self.format {
+            MatrixFormat::Csv => {
+                let mut bytes = Vec::new();
+                write_csv(&matrix, &mut bytes)?;
+                bytes
+            }
+            MatrixFormat::Xlsx => write_xlsx(&matrix)?,
+        };
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, bytes)
+                    .wrap_err_with(|| format!("Writing {}", path.display()))?;
+                info!(
+                    "Wrote {} principals by {} resource groups to {}",
+                    matrix.rows.len(),
+                    matrix.resource_groups.len(),
+                    path.display()
+                );
+            }
+            None => std::io::stdout().lock().write_all(&bytes)?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::cli::Cli;
+use crate::cli::completion::COMPLETE_VAR;
+use crate::cli::to_args::ToArgs;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::CommandFactory;
+use clap::ValueEnum;
+use clap_complete::env::Bash;
+use clap_complete::env::EnvCompleter;
+use clap_complete::env::Fish;
+use clap_complete::env::Powershell;
+use clap_complete::env::Zsh;
+
+/// Print the script that registers shell completion
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct CompletionsArgs {
+    #[clap(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(ValueEnum, Clone, Copy, Arbitrary, PartialEq, Eq, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+impl Shell {
+    fn completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            Shell::Bash => &Bash,
+            Shell::Zsh => &Zsh,
+            Shell::Fish => &Fish,
+            Shell::PowerShell => &Powershell,
+        }
+    }
+}
+
+impl CompletionsArgs {
+    /// The script calls back into the binary by name, so it must be on `PATH`.
+    pub fn invoke(self) -> eyre::Result<()> {
+        let command = Cli::command();
+        let bin = command.get_name();
+        let mut stdout = std::io::stdout().lock();
+        self.shell
+            .completer()
+            .write_registration(COMPLETE_VAR, bin, bin, bin, &mut stdout)?;
+        Ok(())
+    }
+}
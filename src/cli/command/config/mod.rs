@@ -0,0 +1,27 @@
+use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::Subcommand;
+
+/// Inspect the layered configuration
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct ConfigArgs {
+    #[clap(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub enum ConfigCommand {
+    /// Print the effective configuration and where each setting came from
+    Show,
+}
+
+impl ConfigArgs {
+    pub fn invoke(self, config: &Config) -> eyre::Result<()> {
+        match self.command {
+            ConfigCommand::Show => println!("{}", config.render()),
+        }
+        Ok(())
+    }
+}
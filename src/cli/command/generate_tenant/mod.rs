@@ -0,0 +1,114 @@
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::to_args::ToArgs;
+use crate::synthetic::SyntheticTenantOptions;
+use crate::synthetic::generate;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::ValueEnum;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Generate a synthetic tenant snapshot for tests, demos and benchmarks
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct GenerateTenantArgs {
+    /// Seed for the generator; the same seed always yields the same tenant
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Preset for the size knobs, which can be overridden individually
+    #[clap(long, value_enum, default_value_t = TenantSize::Small)]
+    pub size: TenantSize,
+
+    /// Management groups below the tenant root group
+    #[clap(long)]
+    pub management_groups: Option<usize>,
+
+    #[clap(long)]
+    pub subscriptions: Option<usize>,
+
+    #[clap(long)]
+    pub resource_groups_per_subscription: Option<usize>,
+
+    #[clap(long)]
+    pub resources_per_resource_group: Option<usize>,
+
+    #[clap(long)]
+    pub users: Option<usize>,
+
+    #[clap(long)]
+    pub service_principals: Option<usize>,
+
+    #[clap(long)]
+    pub groups: Option<usize>,
+
+    #[clap(long)]
+    pub custom_roles: Option<usize>,
+
+    #[clap(long)]
+    pub role_assignments: Option<usize>,
+
+    #[clap(long)]
+    pub deny_assignments: Option<usize>,
+
+    /// PIM eligible role assignments
+    #[clap(long)]
+    pub eligible_assignments: Option<usize>,
+
+    /// Write the snapshot to this file instead of stdout
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Arbitrary, PartialEq, Eq, Debug)]
+pub enum TenantSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl GenerateTenantArgs {
+    pub fn options(&self) -> SyntheticTenantOptions {
+        let preset = match self.size {
+            TenantSize::Small => SyntheticTenantOptions::small(self.seed),
+            TenantSize::Medium => SyntheticTenantOptions::medium(self.seed),
+            TenantSize::Large => SyntheticTenantOptions::large(self.seed),
+        };
+        SyntheticTenantOptions {
+            seed: self.seed,
+            management_groups: self.management_groups.unwrap_or(preset.management_groups),
+            subscriptions: self.subscriptions.unwrap_or(preset.subscriptions),
+            resource_groups_per_subscription: self
+                .resource_groups_per_subscription
+                .unwrap_or(preset.resource_groups_per_subscription),
+            resources_per_resource_group: self
+                .resources_per_resource_group
+                .unwrap_or(preset.resources_per_resource_group),
+            users: self.users.unwrap_or(preset.users),
+            service_principals: self.service_principals.unwrap_or(preset.service_principals),
+            groups: self.groups.unwrap_or(preset.groups),
+            custom_roles: self.custom_roles.unwrap_or(preset.custom_roles),
+            role_assignments: self.role_assignments.unwrap_or(preset.role_assignments),
+            deny_assignments: self.deny_assignments.unwrap_or(preset.deny_assignments),
+            eligible_assignments: self
+                .eligible_assignments
+                .unwrap_or(preset.eligible_assignments),
+        }
+    }
+
+    pub fn invoke(self) -> eyre::Result<()> {
+        let snapshot = generate(&self.options())?;
+        match &self.output {
+            Some(path) => {
+                snapshot.save(path)?;
+                info!(
+                    "Wrote synthetic tenant with {} role assignments to {}",
+                    snapshot.rbac.role_assignments.len(),
+                    path.display()
+                );
+            }
+            None => println!("{}", serde_json::to_string_pretty(&snapshot)?),
+        }
+        Ok(())
+    }
+}
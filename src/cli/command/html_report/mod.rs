@@ -0,0 +1,107 @@
+pub mod report;
+
+use crate::azure::hierarchy::fetch_scope_hierarchy;
+use crate::azure::principals::PrincipalDirectory;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::command::html_report::report::build_report;
+use crate::cli::command::html_report::report::render_html;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use crate::snapshot::TenantSnapshot;
+use arbitrary::Arbitrary;
+use chrono::Utc;
+use clap::Args;
+use cloud_terrastodon_azure::prelude::fetch_all_resource_groups;
+use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments;
+use eyre::Context;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::runtime::Builder;
+use tokio::try_join;
+use tracing::info;
+
+/// Write a self-contained HTML access review report
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct HtmlReportArgs {
+    /// Write to this file instead of stdout
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub output: Option<PathBuf>,
+
+    /// Read from a snapshot file instead of fetching from Azure
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub snapshot: Option<PathBuf>,
+}
+
+impl HtmlReportArgs {
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
+        let report = Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                let (tenant_id, principals, hierarchy, resource_groups, rbac) = match &self.snapshot
+                {
+                    Some(path) => {
+                        let snapshot = TenantSnapshot::load(path)?;
+                        (
+                            snapshot.tenant_id.clone(),
+                            snapshot.principals(),
+                            snapshot.hierarchy(),
+                            snapshot.resource_groups,
+                            snapshot.rbac,
+                        )
+                    }
+                    None => {
+                        let cache = Cache::open(global_args, config).await?;
+                        try_join!(
+                            cache.tenant_id(),
+                            PrincipalDirectory::fetch_live(&cache),
+                            cache.get_or_fetch(FetchKind::Hierarchy, fetch_scope_hierarchy()),
+                            cache.get_or_fetch(
+                                FetchKind::ResourceGroups,
+                                fetch_all_resource_groups()
+                            ),
+                            cache.get_or_fetch(
+                                FetchKind::RoleDefinitionsAndAssignments,
+                                fetch_all_role_definitions_and_assignments()
+                            )
+                        )?
+                    }
+                };
+                let subscriptions = hierarchy
+                    .subscriptions
+                    .iter()
+                    .filter(|x| config.includes_subscription(&x.subscription_id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                eyre::Ok(build_report(
+                    tenant_id,
+                    Utc::now(),
+                    &principals,
+                    &hierarchy,
+                    &subscriptions,
+                    &resource_groups,
+                    &rbac,
+                ))
+            })?;
+
+        let html = render_html(&report);
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, html)
+                    .wrap_err_with(|| format!("Writing {}", path.display()))?;
+                info!(
+                    "Wrote access review of {} subscriptions to {}",
+                    report.subscriptions.len(),
+                    path.display()
+                );
+            }
+            None => std::io::stdout().lock().write_all(html.as_bytes())?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::hierarchy::SubscriptionEntry;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::privilege::PrivilegeLevel;
+use chrono::DateTime;
+use chrono::Utc;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Everything the access review report shows, grouped for people who only care about their
+/// own subscription or resource group.
+#[derive(Debug)]
+pub struct AccessReport {
+    pub tenant_id: String,
+    pub generated_at: DateTime<Utc>,
+    pub subscriptions: Vec<SubscriptionSection>,
+    /// Principals holding roles who are missing from the directory, usually because they were
+    /// deleted without their assignments being removed, though a principal created after the
+    /// directory was fetched lands here too
+    pub orphaned_principals: Vec<OrphanedPrincipal>,
+}
+
+#[derive(Debug)]
+pub struct SubscriptionSection {
+    pub subscription_id: String,
+    pub name: String,
+    /// Assignments at the subscription and the management groups above it, which apply to every
+    /// resource group in it
+    pub rows: Vec<AccessRow>,
+    pub resource_groups: Vec<ResourceGroupSection>,
+}
+
+#[derive(Debug)]
+pub struct ResourceGroupSection {
+    pub name: String,
+    /// Assignments at the resource group and the resources in it
+    pub rows: Vec<AccessRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRow {
+    pub principal: String,
+    pub principal_type: String,
+    pub object_id: PrincipalId,
+    pub role: String,
+    pub privilege: PrivilegeLevel,
+    /// Where the assignment was made, relative to the section it appears in
+    pub scope: String,
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedPrincipal {
+    pub object_id: PrincipalId,
+    /// Full ids of the scopes the principal still holds roles at
+    pub scopes: Vec<String>,
+}
+
+/// Sort every role assignment into the section of the subscription or resource group it was
+/// made in, skipping those outside `subscriptions`.
+pub fn build_report(
+    tenant_id: String,
+    generated_at: DateTime<Utc>,
+    principals: &PrincipalDirectory,
+    hierarchy: &ScopeHierarchy,
+    subscriptions: &[SubscriptionEntry],
+    resource_groups: &[ResourceGroup],
+    rbac: &RoleDefinitionsAndAssignments,
+) -> AccessReport {
+    let by_scope = rbac
+        .iter_role_assignments()
+        .into_group_map_by(|(ra, _)| ra.scope.expanded_form().to_lowercase());
+    let row = |ra: &RoleAssignment, rd: &RoleDefinition, scope: String| AccessRow {
+        principal: principals.name(&ra.principal_id),
+        principal_type: principals
+            .kind(&ra.principal_id)
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| "Unknown".to_string()),
+        object_id: ra.principal_id,
+        role: rd.display_name.clone(),
+        privilege: PrivilegeLevel::of(rd),
+        scope,
+        orphaned: principals.get(&ra.principal_id).is_none(),
+    };
+    let management_group_names: HashMap<String, &str> = hierarchy
+        .management_groups
+        .iter()
+        .map(|x| (x.id().to_lowercase(), x.display_name.as_str()))
+        .collect();
+
+    let mut orphaned: HashSet<PrincipalId> = HashSet::new();
+    let mut sections = Vec::new();
+    for subscription in subscriptions
+        .iter()
+        .sorted_by(|a, b| a.display_name.cmp(&b.display_name))
+    {
+        let mut rows = Vec::new();
+        for scope in hierarchy.scopes_above_subscription(&subscription.subscription_id) {
+            let scope = scope.to_lowercase();
+            let label = match management_group_names.get(&scope) {
+                Some(name) => format!("Management group {name} (inherited)"),
+                None => "Subscription".to_string(),
+            };
+            for (ra, rd) in by_scope.get(&scope).into_iter().flatten() {
+                rows.push(row(ra, rd, label.clone()));
+            }
+        }
+
+        let mut resource_group_sections = Vec::new();
+        for rg in resource_groups
+            .iter()
+            .filter(|x| {
+                x.subscription_id
+                    .to_string()
+                    .eq_ignore_ascii_case(&subscription.subscription_id)
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+        {
+            let rg_scope = rg.id.expanded_form().to_lowercase();
+            let mut rows = Vec::new();
+            let resource_prefix = format!("{rg_scope}/providers/");
+            for (scope, assignments) in &by_scope {
+                let in_resource = scope.starts_with(&resource_prefix);
+                if *scope != rg_scope && !in_resource {
+                    continue;
+                }
+                for (ra, rd) in assignments {
+                    let label = if in_resource {
+                        // Slice the original id so resource names keep the case Azure reported
+                        let scope = ra.scope.expanded_form();
+                        scope
+                            .get(resource_prefix.len()..)
+                            .unwrap_or(&scope)
+                            .to_string()
+                    } else {
+                        "Resource group".to_string()
+                    };
+                    rows.push(row(ra, rd, label));
+                }
+            }
+            resource_group_sections.push(ResourceGroupSection {
+                name: rg.name.clone(),
+                rows: sort_rows(rows),
+            });
+        }
+
+        orphaned.extend(
+            rows.iter()
+                .chain(resource_group_sections.iter().flat_map(|x| &x.rows))
+                .filter(|x| x.orphaned)
+                .map(|x| x.object_id),
+        );
+        sections.push(SubscriptionSection {
+            subscription_id: subscription.subscription_id.clone(),
+            name: subscription.display_name.clone(),
+            rows: sort_rows(rows),
+            resource_groups: resource_group_sections,
+        });
+    }
+
+    // Every scope, including those outside the report, so the cleanup is complete
+    let orphaned_principals = orphaned
+        .into_iter()
+        .map(|object_id| OrphanedPrincipal {
+            object_id,
+            scopes: rbac
+                .iter_role_assignments()
+                .filter(|(ra, _)| ra.principal_id == object_id)
+                .map(|(ra, _)| ra.scope.expanded_form())
+                .sorted()
+                .dedup()
+                .collect(),
+        })
+        .sorted_by_key(|x| x.object_id.to_string())
+        .collect();
+
+    AccessReport {
+        tenant_id,
+        generated_at,
+        subscriptions: sections,
+        orphaned_principals,
+    }
+}
+
+/// Most privileged first, so the rows reviewers should look at are at the top.
+fn sort_rows(rows: Vec<AccessRow>) -> Vec<AccessRow> {
+    rows.into_iter()
+        .sorted_by(|a, b| {
+            b.privilege
+                .cmp(&a.privilege)
+                .then_with(|| a.principal.to_lowercase().cmp(&b.principal.to_lowercase()))
+                .then_with(|| a.role.cmp(&b.role))
+                .then_with(|| a.scope.cmp(&b.scope))
+        })
+        .collect()
+}
+
+/// Escape text for use in HTML content and quoted attribute values.
+fn escape(text: &str) -> String {
+    let mut rtn = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => rtn.push_str("&amp;"),
+            '<' => rtn.push_str("&lt;"),
+            '>' => rtn.push_str("&gt;"),
+            '"' => rtn.push_str("&quot;"),
+            '\'' => rtn.push_str("&#39;"),
+            c => rtn.push(c),
+        }
+    }
+    rtn
+}
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #1b1b1b; }
+h1, h2, h3 { font-weight: 600; }
+section.subscription { margin-top: 3rem; border-top: 2px solid #ccc; }
+table { border-collapse: collapse; margin: 0.5rem 0 1.5rem; width: 100%; }
+th, td { border: 1px solid #ddd; padding: 0.3rem 0.6rem; text-align: left; vertical-align: top; }
+th { background: #f3f3f3; cursor: pointer; user-select: none; white-space: nowrap; }
+th[aria-sort="ascending"]::after { content: " \25B2"; }
+th[aria-sort="descending"]::after { content: " \25BC"; }
+tr.admin td { background: #fde2e1; }
+tr.write td { background: #fff4d6; }
+tr.orphaned td:first-child { font-style: italic; }
+.badge { border-radius: 3px; padding: 0 0.4rem; font-size: 0.85em; }
+.badge.admin { background: #c62828; color: white; }
+.badge.write { background: #f9a825; }
+.warning { background: #fff4d6; border-left: 4px solid #f9a825; padding: 0.5rem 1rem; }
+.empty { color: #666; }
+code { font-size: 0.9em; }
+"#;
+
+/// Sorts a table by the clicked column, using `data-sort` where the text does not sort well.
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach((th) => {
+  th.addEventListener("click", () => {
+    const table = th.closest("table");
+    const body = table.tBodies[0];
+    const column = Array.from(th.parentNode.children).indexOf(th);
+    const ascending = th.getAttribute("aria-sort") !== "ascending";
+    table.querySelectorAll("th").forEach((x) => x.removeAttribute("aria-sort"));
+    th.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    const key = (row) => {
+      const cell = row.children[column];
+      return cell.dataset.sort ?? cell.textContent.trim();
+    };
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const order = key(a).localeCompare(key(b), undefined, { numeric: true, sensitivity: "base" });
+      return ascending ? order : -order;
+    });
+    rows.forEach((row) => body.appendChild(row));
+  });
+});
+"#;
+
+/// Render the report as a single HTML page with no external resources, so it can be mailed
+/// around and opened offline.
+pub fn render_html(report: &AccessReport) -> String {
+    let mut html = String::new();
+    let title = format!("Access review for tenant {}", report.tenant_id);
+    // Writing to a String cannot fail
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>Generated {}. Rows highlighted in red hold roles that can grant access to others; rows in amber can change resources. Click a column header to sort.</p>\n",
+        escape(&title),
+        escape(&title),
+        report.generated_at.format("%Y-%m-%d %H:%M UTC"),
+    );
+
+    if !report.orphaned_principals.is_empty() {
+        let _ = write!(
+            html,
+            "<div class=\"warning\">\n<h2>Principals not found in the directory</h2>\n<p>{} principal(s) hold roles but were not found in the directory when this report was generated. They are usually deleted principals whose assignments grant nothing, but confirm each one in Entra ID before removing its assignments.</p>\n<table class=\"sortable\">\n<thead><tr><th>Object id</th><th>Assigned at</th></tr></thead>\n<tbody>\n",
+            report.orphaned_principals.len()
+        );
+        for orphan in &report.orphaned_principals {
+            let _ = writeln!(
+                html,
+                "<tr><td><code>{}</code></td><td>{}</td></tr>",
+                orphan.object_id,
+                orphan
+                    .scopes
+                    .iter()
+                    .map(|x| format!("<code>{}</code>", escape(x)))
+                    .join("<br>")
+            );
+        }
+        html.push_str("</tbody>\n</table>\n</div>\n");
+    }
+
+    html.push_str("<h2>Summary</h2>\n<table class=\"sortable\">\n<thead><tr><th>Subscription</th><th>Resource groups</th><th>Assignments</th><th>Admin</th><th>Write</th><th>Not found</th></tr></thead>\n<tbody>\n");
+    for subscription in &report.subscriptions {
+        let rows = subscription
+            .rows
+            .iter()
+            .chain(subscription.resource_groups.iter().flat_map(|x| &x.rows))
+            .collect_vec();
+        let count = |level| rows.iter().filter(|x| x.privilege == level).count();
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&subscription_anchor(subscription)),
+            escape(&subscription.name),
+            subscription.resource_groups.len(),
+            rows.len(),
+            count(PrivilegeLevel::Admin),
+            count(PrivilegeLevel::Write),
+            rows.iter().filter(|x| x.orphaned).count(),
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    for subscription in &report.subscriptions {
+        let anchor = subscription_anchor(subscription);
+        let _ = write!(
+            html,
+            "<section class=\"subscription\" id=\"{}\">\n<h2>{}</h2>\n<p><code>{}</code></p>\n<h3>Subscription-wide access</h3>\n<p>These apply to every resource group below.</p>\n",
+            escape(&anchor),
+            escape(&subscription.name),
+            escape(&subscription.subscription_id),
+        );
+        write_table(&mut html, &subscription.rows);
+        if !subscription.resource_groups.is_empty() {
+            html.push_str("<nav><p>Resource groups: ");
+            html.push_str(
+                &subscription
+                    .resource_groups
+                    .iter()
+                    .map(|rg| {
+                        format!(
+                            "<a href=\"#{}\">{}</a>",
+                            escape(&format!("{anchor}/{}", rg.name.to_lowercase())),
+                            escape(&rg.name)
+                        )
+                    })
+                    .join(", "),
+            );
+            html.push_str("</p></nav>\n");
+        }
+        for rg in &subscription.resource_groups {
+            let _ = write!(
+                html,
+                "<section class=\"resource-group\" id=\"{}\">\n<h3>{}</h3>\n",
+                escape(&format!("{anchor}/{}", rg.name.to_lowercase())),
+                escape(&rg.name),
+            );
+            write_table(&mut html, &rg.rows);
+            html.push_str("</section>\n");
+        }
+        html.push_str("</section>\n");
+    }
+
+    let _ = write!(html, "<script>{SCRIPT}</script>\n</body>\n</html>\n");
+    html
+}
+
+fn subscription_anchor(subscription: &SubscriptionSection) -> String {
+    format!(
+        "subscription-{}",
+        subscription.subscription_id.to_lowercase()
+    )
+}
+
+fn write_table(html: &mut String, rows: &[AccessRow]) {
+    if rows.is_empty() {
+        html.push_str("<p class=\"empty\">No role assignments.</p>\n");
+        return;
+    }
+    html.push_str("<table class=\"sortable\">\n<thead><tr><th>Principal</th><th>Type</th><th>Role</th><th>Privilege</th><th>Scope</th><th>Object id</th></tr></thead>\n<tbody>\n");
+    for row in rows {
+        let class = [
+            match row.privilege {
+                PrivilegeLevel::Admin => Some("admin"),
+                PrivilegeLevel::Write => Some("write"),
+                PrivilegeLevel::Read => None,
+            },
+            row.orphaned.then_some("orphaned"),
+        ]
+        .into_iter()
+        .flatten()
+        .join(" ");
+        let class = if class.is_empty() {
+            String::new()
+        } else {
+            format!(" class=\"{class}\"")
+        };
+        let privilege = match row.privilege {
+            PrivilegeLevel::Read => row.privilege.to_string(),
+            level => format!(
+                "<span class=\"badge {}\">{level}</span>",
+                level.to_string().to_lowercase()
+            ),
+        };
+        let principal_type = if row.orphaned {
+            "Not found".to_string()
+        } else {
+            escape(&row.principal_type)
+        };
+        let _ = writeln!(
+            html,
+            "<tr{class}><td>{}</td><td>{principal_type}</td><td>{}</td><td data-sort=\"{}\">{privilege}</td><td>{}</td><td><code>{}</code></td></tr>",
+            escape(&row.principal),
+            escape(&row.role),
+            row.privilege as u8,
+            escape(&row.scope),
+            row.object_id,
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn sections_access_and_flags_orphans() {
+        let alice = fixtures::user("<Alice>");
+        let deleted = fixtures::user("Deleted");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let vault = fixtures::resource(&rg_app, "kv-App", "Microsoft.KeyVault/vaults");
+        let hierarchy = fixtures::hierarchy();
+        let rbac = fixtures::rbac(
+            [reader.clone(), owner.clone()],
+            [
+                fixtures::role_assignment(&rg_app.id, &reader, alice.id),
+                fixtures::role_assignment_at(&vault.id, &owner, alice.id),
+                fixtures::role_assignment_at(
+                    &hierarchy.management_groups[0].id(),
+                    &owner,
+                    deleted.id,
+                ),
+            ],
+        );
+        let mut principals = PrincipalDirectory::default();
+        principals.add_users(std::slice::from_ref(&alice));
+
+        let report = build_report(
+            "tenant".to_string(),
+            DateTime::UNIX_EPOCH,
+            &principals,
+            &hierarchy,
+            &hierarchy.subscriptions,
+            &[rg_data, rg_app],
+            &rbac,
+        );
+        assert_eq!(report.subscriptions.len(), 1);
+        let subscription = &report.subscriptions[0];
+        assert_eq!(
+            subscription
+                .rows
+                .iter()
+                .map(|x| (x.scope.as_str(), x.orphaned))
+                .collect_vec(),
+            [("Management group Tenant Root Group (inherited)", true)]
+        );
+        let rg_app = &subscription.resource_groups[0];
+        assert_eq!(rg_app.name, "rg-app");
+        assert_eq!(
+            rg_app
+                .rows
+                .iter()
+                .map(|x| (x.role.as_str(), x.privilege, x.scope.as_str()))
+                .collect_vec(),
+            [
+                (
+                    "Owner",
+                    PrivilegeLevel::Admin,
+                    "Microsoft.KeyVault/vaults/kv-App"
+                ),
+                ("Reader", PrivilegeLevel::Read, "Resource group"),
+            ]
+        );
+        assert!(subscription.resource_groups[1].rows.is_empty());
+        assert_eq!(
+            report.orphaned_principals,
+            [OrphanedPrincipal {
+                object_id: deleted.id.into(),
+                scopes: vec![hierarchy.management_groups[0].id()],
+            }]
+        );
+
+        let html = render_html(&report);
+        assert!(html.contains("&lt;Alice&gt;"));
+        assert!(!html.contains("<Alice>"));
+        assert!(html.contains("<tr class=\"admin orphaned\">"));
+        assert!(html.contains("Principals not found in the directory"));
+        assert!(html.contains("<td>Not found</td>"));
+        assert!(!html.contains("src=") && !html.contains("href=\"http"));
+    }
+}
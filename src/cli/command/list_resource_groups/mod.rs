@@ -1,70 +1,127 @@
+pub mod report;
+
+use crate::azure::deny_assignments::fetch_all_deny_assignments;
+use crate::azure::eligible_assignments::fetch_all_eligible_assignments;
+use crate::azure::hierarchy::fetch_scope_hierarchy;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::arbitrary_args::values;
+use crate::cli::command::list_resource_groups::report::build_report;
+use crate::cli::command::list_resource_groups::report::render_report;
+use crate::cli::command::list_resource_groups::report::render_report_lines;
+use crate::cli::completion::resource_group_candidates;
+use crate::cli::completion::subscription_candidates;
+use crate::cli::global_args::GlobalArgs;
 use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use crate::config::OutputFormat;
+use crate::snapshot::TenantSnapshot;
 use arbitrary::Arbitrary;
 use clap::Args;
-use cloud_terrastodon_azure::prelude::RolePermissionAction;
-use cloud_terrastodon_azure::prelude::Scope;
+use clap_complete::engine::ArgValueCandidates;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
 use cloud_terrastodon_azure::prelude::fetch_all_resource_groups;
 use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments;
-use itertools::Itertools;
-use serde_json::json;
-use std::ffi::OsString;
+use std::path::PathBuf;
 use tokio::runtime::Builder;
 use tokio::try_join;
 
 /// List Azure resource groups
-#[derive(Args, Clone, Arbitrary, PartialEq, Debug)]
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
 pub struct ListResourceGroupsArgs {
-    // In the future: add flags like --subscription, --tenant, etc.
+    /// Only list resource groups in this subscription; repeat for several.
+    /// Narrows the configured `subscriptions` further
+    #[clap(
+        long = "subscription",
+        value_name = "ID",
+        add = ArgValueCandidates::new(subscription_candidates)
+    )]
+    #[arbitrary(with = values)]
+    pub subscriptions: Vec<String>,
+
+    /// Only list the resource group with this name; repeat for several
+    #[clap(
+        long = "resource-group",
+        value_name = "NAME",
+        add = ArgValueCandidates::new(resource_group_candidates)
+    )]
+    #[arbitrary(with = values)]
+    pub resource_groups: Vec<String>,
+
+    /// Read from a snapshot file instead of fetching from Azure
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub snapshot: Option<PathBuf>,
 }
 
 impl ListResourceGroupsArgs {
-    pub fn invoke(self) -> eyre::Result<()> {
+    fn includes(&self, resource_group: &ResourceGroup) -> bool {
+        let subscription_id = resource_group.subscription_id.to_string();
+        let matches = |filter: &[String], value: &str| {
+            filter.is_empty() || filter.iter().any(|x| x.eq_ignore_ascii_case(value))
+        };
+        matches(&self.subscriptions, &subscription_id)
+            && matches(&self.resource_groups, &resource_group.name)
+    }
+
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
         Builder::new_current_thread()
             .enable_all()
             .build()?
             .block_on(async {
-                let (resource_groups, rbac) = try_join!(
-                    fetch_all_resource_groups(),
-                    fetch_all_role_definitions_and_assignments()
-                )?;
-                let mut rtn = Vec::new();
-                for rg in resource_groups {
-                    let role_assignments_for_rg = rbac
-                        .iter_role_assignments()
-                        .filter_map(|(ra, rd)| {
-                            if ra.scope == rg.id.as_scope_impl() {
-                                Some((ra, rd))
-                            } else {
-                                None
-                            }
-                        })
-                        .map(|(ra, rd)| {
-                            let read_perm = RolePermissionAction::new("Microsoft.General/read");
-                            let x = rd.satisfies(&[read_perm], &[]);
-                            (ra, rd,x)
-                        })
-                        .collect_vec();
-                    rtn.push(json!({
-                        "resource_group": rg,
-                        "role_assignments": role_assignments_for_rg.iter().map(|(ra, rd, can_read)| {
-                            json!({
-                                "role_assignment": ra,
-                                "role_definition": rd,
-                                "can_read": can_read
-                            })
-                        }).collect::<Vec<_>>(),
-                    }));
-                }
-
-                let json = serde_json::to_string_pretty(&rtn)?;
-                println!("{}", json);
+                let (resource_groups, rbac, deny_assignments, eligible_assignments) =
+                    match &self.snapshot {
+                        Some(path) => {
+                            let snapshot = TenantSnapshot::load(path)?;
+                            (
+                                snapshot.resource_groups,
+                                snapshot.rbac,
+                                snapshot.deny_assignments,
+                                snapshot.eligible_assignments,
+                            )
+                        }
+                        None => {
+                            let cache = Cache::open(global_args, config).await?;
+                            let hierarchy = cache
+                                .get_or_fetch(FetchKind::Hierarchy, fetch_scope_hierarchy())
+                                .await?;
+                            try_join!(
+                                cache.get_or_fetch(
+                                    FetchKind::ResourceGroups,
+                                    fetch_all_resource_groups()
+                                ),
+                                cache.get_or_fetch(
+                                    FetchKind::RoleDefinitionsAndAssignments,
+                                    fetch_all_role_definitions_and_assignments()
+                                ),
+                                cache.get_or_fetch(
+                                    FetchKind::DenyAssignments,
+                                    fetch_all_deny_assignments(&hierarchy)
+                                ),
+                                cache.get_or_fetch(
+                                    FetchKind::EligibleAssignments,
+                                    fetch_all_eligible_assignments(&hierarchy)
+                                )
+                            )?
+                        }
+                    };
+                let resource_groups = resource_groups
+                    .into_iter()
+                    .filter(|rg| config.includes(rg) && self.includes(rg))
+                    .collect::<Vec<_>>();
+                let report = build_report(
+                    &resource_groups,
+                    &rbac,
+                    &deny_assignments,
+                    &eligible_assignments,
+                );
+                let output = match config.output_format {
+                    OutputFormat::Json => render_report(&report)?,
+                    OutputFormat::JsonLines => render_report_lines(&report)?,
+                };
+                println!("{output}");
                 eyre::Ok(())
             })
     }
 }
-
-impl ToArgs for ListResourceGroupsArgs {
-    fn to_args(&self) -> Vec<OsString> {
-        Vec::new()
-    }
-}
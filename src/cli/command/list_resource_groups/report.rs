@@ -0,0 +1,288 @@
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::RolePermissionAction;
+use cloud_terrastodon_azure::prelude::Scope;
+use serde::Serialize;
+
+/// One entry of the `list-resource-groups` output.
+///
+/// The output is a contract for downstream scripts and is described by
+/// `docs/list-resource-groups.schema.json`; change both together.
+/// Fields are declared alphabetically to match the key order of the printed JSON.
+#[derive(Serialize, Debug)]
+pub struct ResourceGroupReport<'a> {
+    /// Deny assignments in effect at the resource group, including inherited ones
+    pub deny_assignments: Vec<&'a DenyAssignment>,
+    /// PIM eligibilities scoped directly to the resource group, listed apart from active assignments
+    pub eligible_assignments: Vec<EligibleAssignmentReport<'a>>,
+    pub resource_group: &'a ResourceGroup,
+    pub role_assignments: Vec<RoleAssignmentReport<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoleAssignmentReport<'a> {
+    /// Whether the role grants `Microsoft.General/read` and no deny assignment blocks it
+    pub can_read: bool,
+    /// Whether the assignment carries an ABAC condition, which narrows what it grants.
+    /// Conditions only constrain data actions, so they do not affect `can_read`
+    pub conditional: bool,
+    pub role_assignment: &'a RoleAssignment,
+    pub role_definition: &'a RoleDefinition,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EligibleAssignmentReport<'a> {
+    pub eligible_assignment: &'a EligibleAssignment,
+    /// `None` when the role definition is not among those fetched
+    pub role_definition: Option<&'a RoleDefinition>,
+}
+
+/// Pair every resource group with the role assignments scoped directly to it.
+pub fn build_report<'a>(
+    resource_groups: &'a [ResourceGroup],
+    rbac: &'a RoleDefinitionsAndAssignments,
+    deny_assignments: &'a [DenyAssignment],
+    eligible_assignments: &'a [EligibleAssignment],
+) -> Vec<ResourceGroupReport<'a>> {
+    const READ: &str = "Microsoft.General/read";
+    let read_perm = [RolePermissionAction::new(READ)];
+    resource_groups
+        .iter()
+        .map(|rg| {
+            let scope = rg.id.expanded_form();
+            let denies = deny_assignments
+                .iter()
+                .filter(|deny| deny.applies_at(&scope))
+                .collect::<Vec<_>>();
+            ResourceGroupReport {
+                resource_group: rg,
+                role_assignments: rbac
+                    .iter_role_assignments()
+                    .filter(|(ra, _)| ra.scope == rg.id.as_scope_impl())
+                    .map(|(ra, rd)| {
+                        let principal_id = ra.principal_id.to_string();
+                        let denied = denies
+                            .iter()
+                            .any(|deny| deny.applies_to(&principal_id) && deny.denies_action(READ));
+                        RoleAssignmentReport {
+                            can_read: rd.satisfies(&read_perm, &[]) && !denied,
+                            conditional: ra.condition.is_some(),
+                            role_assignment: ra,
+                            role_definition: rd,
+                        }
+                    })
+                    .collect(),
+                deny_assignments: denies,
+                eligible_assignments: eligible_assignments
+                    .iter()
+                    .filter(|x| x.scope.eq_ignore_ascii_case(&scope))
+                    .map(|x| EligibleAssignmentReport {
+                        eligible_assignment: x,
+                        role_definition: x.role_definition(rbac),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Render the report the way `list-resource-groups` prints it.
+///
+/// Going through [`serde_json::Value`] sorts the keys of nested Azure objects,
+/// which keeps the output stable regardless of upstream field order.
+pub fn render_report(report: &[ResourceGroupReport]) -> eyre::Result<String> {
+    let value = serde_json::to_value(report)?;
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Render the report with one compact resource group per line, for the `json-lines` output format.
+pub fn render_report_lines(report: &[ResourceGroupReport]) -> eyre::Result<String> {
+    let mut lines = Vec::with_capacity(report.len());
+    for entry in report {
+        lines.push(serde_json::to_string(&serde_json::to_value(entry)?)?);
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use std::path::PathBuf;
+
+    /// Compare against `golden/<name>`; run with `UPDATE_GOLDEN=1` to rewrite the file.
+    fn assert_golden(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/cli/command/list_resource_groups/golden")
+            .join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, format!("{actual}\n")).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Reading {}: {e}", path.display()));
+        assert_eq!(
+            expected.replace("\r\n", "\n").trim_end(),
+            actual,
+            "{name} differs from golden file"
+        );
+    }
+
+    #[test]
+    fn empty_resource_groups() {
+        let alice = fixtures::user("Alice");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        // Assignments at other scopes are not listed under any resource group
+        let other = fixtures::resource_group("rg-other");
+        let rbac = fixtures::rbac(
+            [reader.clone()],
+            [fixtures::role_assignment(&other.id, &reader, alice.id)],
+        );
+        let resource_groups = [rg_app, rg_data];
+        let report = build_report(&resource_groups, &rbac, &[], &[]);
+        assert_golden("empty.json", &render_report(&report).unwrap());
+    }
+
+    #[test]
+    fn multiple_assignments() {
+        let alice = fixtures::user("Alice");
+        let bob = fixtures::user("Bob");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let rbac = fixtures::rbac(
+            [reader.clone(), owner.clone()],
+            [
+                fixtures::role_assignment(&rg_app.id, &reader, alice.id),
+                fixtures::role_assignment(&rg_app.id, &owner, alice.id),
+                fixtures::role_assignment(&rg_app.id, &reader, bob.id),
+                fixtures::role_assignment(&rg_data.id, &owner, bob.id),
+            ],
+        );
+        let resource_groups = [rg_app, rg_data];
+        let report = build_report(&resource_groups, &rbac, &[], &[]);
+        assert_eq!(report[0].role_assignments.len(), 3);
+        assert_eq!(report[1].role_assignments.len(), 1);
+        assert_golden(
+            "multiple_assignments.json",
+            &render_report(&report).unwrap(),
+        );
+    }
+
+    #[test]
+    fn can_read() {
+        let alice = fixtures::user("Alice");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let restarter = fixtures::built_in_role(
+            "VM Restarter",
+            &["Microsoft.Compute/virtualMachines/restart/action"],
+        );
+        let rg = fixtures::resource_group("rg-app");
+        let rbac = fixtures::rbac(
+            [reader.clone(), restarter.clone()],
+            [
+                fixtures::role_assignment(&rg.id, &reader, alice.id),
+                fixtures::role_assignment(&rg.id, &restarter, alice.id),
+            ],
+        );
+        let resource_groups = [rg];
+        let report = build_report(&resource_groups, &rbac, &[], &[]);
+        let can_read = report[0]
+            .role_assignments
+            .iter()
+            .map(|x| (x.role_definition.display_name.as_str(), x.can_read))
+            .collect::<Vec<_>>();
+        assert_eq!(can_read, [("Reader", true), ("VM Restarter", false)]);
+        assert_golden("can_read.json", &render_report(&report).unwrap());
+    }
+
+    #[test]
+    fn deny_assignments_block_read() {
+        let alice = fixtures::user("Alice");
+        let bob = fixtures::user("Bob");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let rg = fixtures::resource_group("rg-app");
+        let rbac = fixtures::rbac(
+            [reader.clone()],
+            [
+                fixtures::role_assignment(&rg.id, &reader, alice.id),
+                fixtures::role_assignment(&rg.id, &reader, bob.id),
+            ],
+        );
+        // Everyone but Bob is denied everything in the subscription
+        let deny = fixtures::deny_assignment(
+            &format!("/subscriptions/{}", fixtures::SUBSCRIPTION_ID),
+            &["*"],
+            &[&bob.id.to_string()],
+        );
+        let resource_groups = [rg];
+        let deny_assignments = [deny];
+        let report = build_report(&resource_groups, &rbac, &deny_assignments, &[]);
+        assert_eq!(report[0].deny_assignments.len(), 1);
+        let can_read = report[0]
+            .role_assignments
+            .iter()
+            .map(|x| x.can_read)
+            .collect::<Vec<_>>();
+        assert_eq!(can_read, [false, true]);
+        assert_golden("deny_assignments.json", &render_report(&report).unwrap());
+    }
+
+    #[test]
+    fn eligible_assignments() {
+        let alice = fixtures::user("Alice");
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let rbac = fixtures::rbac([owner.clone()], []);
+        let eligible = [fixtures::eligible_assignment(
+            &rg_app.id.expanded_form(),
+            &owner,
+            alice.id,
+        )];
+        let resource_groups = [rg_app, rg_data];
+        let report = build_report(&resource_groups, &rbac, &[], &eligible);
+        assert!(report[0].role_assignments.is_empty());
+        assert_eq!(report[0].eligible_assignments.len(), 1);
+        assert_eq!(
+            report[0].eligible_assignments[0]
+                .role_definition
+                .map(|x| x.display_name.as_str()),
+            Some("Owner")
+        );
+        assert!(report[1].eligible_assignments.is_empty());
+        assert_golden(
+            "eligible_assignments.json",
+            &render_report(&report).unwrap(),
+        );
+    }
+
+    #[test]
+    fn conditional_assignments() {
+        let alice = fixtures::user("Alice");
+        let reader = fixtures::built_in_role("Storage Blob Data Reader", &["*/read"]);
+        let rg = fixtures::resource_group("rg-app");
+        let mut conditional = fixtures::role_assignment(&rg.id, &reader, alice.id);
+        conditional.condition = Some(
+            "@Resource[Microsoft.Storage/storageAccounts/blobServices/containers/blobs/tags:project<$key_case_sensitive$>] StringEquals 'cascade'"
+                .to_string(),
+        );
+        conditional.condition_version = Some("2.0".to_string());
+        conditional.description = Some("Only blobs tagged for Cascade".to_string());
+        let rbac = fixtures::rbac([reader], [conditional]);
+        let resource_groups = [rg];
+        let report = build_report(&resource_groups, &rbac, &[], &[]);
+        assert!(report[0].role_assignments[0].conditional);
+        assert_golden(
+            "conditional_assignments.json",
+            &render_report(&report).unwrap(),
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use crate::cli::Cli;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::to_args::ToArgs;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::CommandFactory;
+use clap_mangen::Man;
+use eyre::Context;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Print the man page, or write one per subcommand to a directory
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct ManArgs {
+    /// Write a page for the binary and one for every subcommand into this directory instead
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub output_dir: Option<PathBuf>,
+}
+
+impl ManArgs {
+    pub fn invoke(self) -> eyre::Result<()> {
+        let command = Cli::command();
+        match self.output_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(&dir)
+                    .wrap_err_with(|| format!("Creating {}", dir.display()))?;
+                clap_mangen::generate_to(command, &dir)
+                    .wrap_err_with(|| format!("Writing man pages to {}", dir.display()))?;
+                info!("Wrote man pages to {}", dir.display());
+            }
+            None => Man::new(command).render(&mut std::io::stdout().lock())?,
+        }
+        Ok(())
+    }
+}
@@ -1,44 +1,68 @@
+pub mod access_matrix;
+pub mod completions;
+pub mod config;
+pub mod generate_tenant;
+pub mod html_report;
 pub mod list_resource_groups;
+pub mod man;
+pub mod principal_access;
 pub mod resource_group_tui;
+pub mod role_assignment;
 
+use crate::cli::command::access_matrix::AccessMatrixArgs;
+use crate::cli::command::completions::CompletionsArgs;
+use crate::cli::command::config::ConfigArgs;
+use crate::cli::command::generate_tenant::GenerateTenantArgs;
+use crate::cli::command::html_report::HtmlReportArgs;
 use crate::cli::command::list_resource_groups::ListResourceGroupsArgs;
+use crate::cli::command::man::ManArgs;
+use crate::cli::command::principal_access::PrincipalAccessArgs;
 use crate::cli::command::resource_group_tui::ResourceGroupTuiArgs;
+use crate::cli::command::role_assignment::RoleAssignmentArgs;
+use crate::cli::global_args::GlobalArgs;
 use crate::cli::to_args::ToArgs;
+use crate::config::Config;
 use arbitrary::Arbitrary;
 use clap::Subcommand;
-use std::ffi::OsString;
 
 /// A demonstration command line utility
-#[derive(Subcommand, Arbitrary, PartialEq, Debug)]
+#[derive(Subcommand, Arbitrary, PartialEq, Debug, ToArgs)]
 pub enum Command {
     /// List Azure resource groups
     ListResourceGroups(ListResourceGroupsArgs),
-    /// Launch a TUI for resource groups (stub)
+    /// Browse role assignments across the scope tree and act on them in bulk
     ResourceGroupTui(ResourceGroupTuiArgs),
+    /// Generate a synthetic tenant snapshot for tests and demos
+    GenerateTenant(GenerateTenantArgs),
+    /// Create or delete role assignments, optionally as a separate Azure CLI login
+    RoleAssignment(RoleAssignmentArgs),
+    /// Inspect the layered configuration
+    Config(ConfigArgs),
+    /// List every role a principal holds, directly or through its groups
+    PrincipalAccess(PrincipalAccessArgs),
+    /// Export a principal by resource group access matrix as CSV or XLSX
+    AccessMatrix(AccessMatrixArgs),
+    /// Write a self-contained HTML access review report
+    HtmlReport(HtmlReportArgs),
+    /// Print the script that registers shell completion
+    Completions(CompletionsArgs),
+    /// Print the man page, or write one per subcommand to a directory
+    Man(ManArgs),
 }
 
 impl Command {
-    pub fn invoke(self) -> eyre::Result<()> {
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
         match self {
-            Command::ListResourceGroups(args) => args.invoke(),
-            Command::ResourceGroupTui(args) => args.invoke(),
+            Command::ListResourceGroups(args) => args.invoke(global_args, config),
+            Command::ResourceGroupTui(args) => args.invoke(global_args, config),
+            Command::GenerateTenant(args) => args.invoke(),
+            Command::RoleAssignment(args) => args.invoke(global_args, config),
+            Command::Config(args) => args.invoke(config),
+            Command::PrincipalAccess(args) => args.invoke(global_args, config),
+            Command::AccessMatrix(args) => args.invoke(global_args, config),
+            Command::HtmlReport(args) => args.invoke(global_args, config),
+            Command::Completions(args) => args.invoke(),
+            Command::Man(args) => args.invoke(),
         }
     }
 }
-
-impl ToArgs for Command {
-    fn to_args(&self) -> Vec<OsString> {
-        let mut args = Vec::new();
-        match self {
-            Command::ListResourceGroups(lrg_args) => {
-                args.push("list-resource-groups".into());
-                args.extend(lrg_args.to_args());
-            }
-            Command::ResourceGroupTui(rg_tui_args) => {
-                args.push("resource-group-tui".into());
-                args.extend(rg_tui_args.to_args());
-            }
-        }
-        args
-    }
-}
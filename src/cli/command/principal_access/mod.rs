@@ -0,0 +1,103 @@
+pub mod report;
+
+use crate::azure::eligible_assignments::fetch_all_eligible_assignments;
+use crate::azure::hierarchy::fetch_scope_hierarchy;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::principals::fetch_transitive_group_ids;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::arbitrary_args::optional_value;
+use crate::cli::command::principal_access::report::build_report;
+use crate::cli::command::principal_access::report::render_report;
+use crate::cli::command::principal_access::report::render_report_lines;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::pick::ensure_can_prompt;
+use crate::cli::pick::pick_principal;
+use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use crate::config::OutputFormat;
+use crate::snapshot::TenantSnapshot;
+use arbitrary::Arbitrary;
+use clap::Args;
+use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments;
+use std::path::PathBuf;
+use tokio::runtime::Builder;
+use tokio::try_join;
+
+/// List every role a principal holds, directly or through its groups
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct PrincipalAccessArgs {
+    /// User principal name, application id, group name or object id; picked interactively
+    /// when omitted
+    #[arbitrary(with = optional_value)]
+    pub principal: Option<String>,
+
+    /// Read from a snapshot file instead of fetching from Azure
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub snapshot: Option<PathBuf>,
+}
+
+impl PrincipalAccessArgs {
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                let snapshot = match &self.snapshot {
+                    Some(path) => Some(TenantSnapshot::load(path)?),
+                    None => None,
+                };
+                let (principals, rbac, eligible_assignments) = match &snapshot {
+                    Some(snapshot) => (
+                        snapshot.principals(),
+                        snapshot.rbac.clone(),
+                        snapshot.eligible_assignments.clone(),
+                    ),
+                    None => {
+                        let cache = Cache::open(global_args, config).await?;
+                        let hierarchy = cache
+                            .get_or_fetch(FetchKind::Hierarchy, fetch_scope_hierarchy())
+                            .await?;
+                        try_join!(
+                            PrincipalDirectory::fetch_live(&cache),
+                            cache.get_or_fetch(
+                                FetchKind::RoleDefinitionsAndAssignments,
+                                fetch_all_role_definitions_and_assignments()
+                            ),
+                            cache.get_or_fetch(
+                                FetchKind::EligibleAssignments,
+                                fetch_all_eligible_assignments(&hierarchy)
+                            )
+                        )?
+                    }
+                };
+                let principal_id = match &self.principal {
+                    Some(query) => principals.find(query)?,
+                    None => {
+                        ensure_can_prompt(&["PRINCIPAL"])?;
+                        pick_principal(&principals)?
+                    }
+                };
+                // Memberships change too often to cache, and only this principal's are needed
+                let group_ids = match &snapshot {
+                    Some(snapshot) => snapshot.transitive_group_ids(&principal_id),
+                    None => fetch_transitive_group_ids(&principal_id).await?,
+                };
+                let report = build_report(
+                    &principals,
+                    principal_id,
+                    &group_ids,
+                    &rbac,
+                    &eligible_assignments,
+                );
+                let output = match config.output_format {
+                    OutputFormat::Json => render_report(&report)?,
+                    OutputFormat::JsonLines => render_report_lines(&report)?,
+                };
+                println!("{output}");
+                eyre::Ok(())
+            })
+    }
+}
@@ -0,0 +1,246 @@
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::principals::PrincipalDirectory;
+use cloud_terrastodon_azure::prelude::GroupId;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use itertools::Itertools;
+use serde::Serialize;
+
+/// The `principal-access` output: everything one principal can do and where.
+///
+/// Fields are declared alphabetically to match the key order of the printed JSON.
+#[derive(Serialize, Debug)]
+pub struct PrincipalAccessReport<'a> {
+    /// PIM eligibilities the principal can activate, directly or through a group
+    pub eligible_assignments: Vec<EligibleAccess<'a>>,
+    /// Groups the principal belongs to, directly or through nested groups
+    pub groups: Vec<PrincipalSummary>,
+    pub principal: PrincipalSummary,
+    pub role_assignments: Vec<RoleAccess<'a>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PrincipalSummary {
+    /// Name with a type prefix, as the TUI shows it
+    pub display: String,
+    pub id: PrincipalId,
+    /// `User`, `ServicePrincipal` or `Group`; `None` for principals missing from the directory
+    pub principal_type: Option<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoleAccess<'a> {
+    pub role_assignment: &'a RoleAssignment,
+    pub role_definition: &'a RoleDefinition,
+    pub scope: String,
+    /// The group the assignment reaches the principal through; `None` when assigned directly
+    pub via_group: Option<PrincipalSummary>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EligibleAccess<'a> {
+    pub eligible_assignment: &'a EligibleAssignment,
+    /// `None` when the role definition is not among those fetched
+    pub role_definition: Option<&'a RoleDefinition>,
+    pub scope: &'a str,
+    /// The group the eligibility reaches the principal through; `None` when made directly
+    pub via_group: Option<PrincipalSummary>,
+}
+
+fn summary(principals: &PrincipalDirectory, principal_id: &PrincipalId) -> PrincipalSummary {
+    PrincipalSummary {
+        display: principals.display(principal_id),
+        id: *principal_id,
+        principal_type: principals.kind(principal_id).map(|x| x.principal_type()),
+    }
+}
+
+/// Collect the assignments held by `principal_id` or any of `group_ids`, ordered by scope.
+pub fn build_report<'a>(
+    principals: &PrincipalDirectory,
+    principal_id: PrincipalId,
+    group_ids: &[GroupId],
+    rbac: &'a RoleDefinitionsAndAssignments,
+    eligible_assignments: &'a [EligibleAssignment],
+) -> PrincipalAccessReport<'a> {
+    let group_ids = group_ids
+        .iter()
+        .map(|x| PrincipalId::from(*x))
+        .collect_vec();
+    // `None` when the holder is not the principal or one of its groups
+    let via = |holder: &PrincipalId| -> Option<Option<PrincipalSummary>> {
+        if *holder == principal_id {
+            Some(None)
+        } else if group_ids.contains(holder) {
+            Some(Some(summary(principals, holder)))
+        } else {
+            None
+        }
+    };
+    PrincipalAccessReport {
+        eligible_assignments: eligible_assignments
+            .iter()
+            .filter_map(|x| {
+                Some(EligibleAccess {
+                    via_group: via(&x.principal_id)?,
+                    eligible_assignment: x,
+                    role_definition: x.role_definition(rbac),
+                    scope: &x.scope,
+                })
+            })
+            .sorted_by_key(|x| x.scope.to_lowercase())
+            .collect(),
+        groups: group_ids
+            .iter()
+            .map(|x| summary(principals, x))
+            .sorted_by(|a, b| a.display.cmp(&b.display))
+            .collect(),
+        principal: summary(principals, &principal_id),
+        role_assignments: rbac
+            .iter_role_assignments()
+            .filter_map(|(ra, rd)| {
+                Some(RoleAccess {
+                    via_group: via(&ra.principal_id)?,
+                    role_assignment: ra,
+                    role_definition: rd,
+                    scope: ra.scope.expanded_form(),
+                })
+            })
+            .sorted_by_key(|x| {
+                (
+                    x.scope.to_lowercase(),
+                    x.role_definition.display_name.clone(),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Render the report the way `principal-access` prints it.
+///
+/// Going through [`serde_json::Value`] sorts the keys of nested Azure objects,
+/// which keeps the output stable regardless of upstream field order.
+pub fn render_report(report: &PrincipalAccessReport) -> eyre::Result<String> {
+    let value = serde_json::to_value(report)?;
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Render one compact grant per line, active assignments first, for the `json-lines` output format.
+pub fn render_report_lines(report: &PrincipalAccessReport) -> eyre::Result<String> {
+    let mut lines = Vec::new();
+    for entry in &report.role_assignments {
+        lines.push(serde_json::to_string(&serde_json::to_value(entry)?)?);
+    }
+    for entry in &report.eligible_assignments {
+        lines.push(serde_json::to_string(&serde_json::to_value(entry)?)?);
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn lists_direct_and_group_assignments() {
+        let alice = fixtures::user("Alice");
+        let bob = fixtures::user("Bob");
+        let admins = fixtures::group("Admins");
+        let readers = fixtures::group("Readers");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let rbac = fixtures::rbac(
+            [reader.clone(), owner.clone()],
+            [
+                fixtures::role_assignment(&rg_data.id, &reader, alice.id),
+                fixtures::role_assignment(&rg_app.id, &owner, admins.id),
+                // Neither Alice nor one of her groups
+                fixtures::role_assignment(&rg_app.id, &reader, bob.id),
+                fixtures::role_assignment(&rg_app.id, &reader, readers.id),
+            ],
+        );
+        let eligible = [
+            fixtures::eligible_assignment(&rg_data.id.expanded_form(), &owner, admins.id),
+            fixtures::eligible_assignment(&rg_data.id.expanded_form(), &owner, bob.id),
+        ];
+        let mut principals = PrincipalDirectory::default();
+        principals.add_users(&[alice.clone(), bob]);
+        principals.add_groups(&[admins.clone(), readers]);
+
+        let report = build_report(&principals, alice.id.into(), &[admins.id], &rbac, &eligible);
+
+        assert_eq!(report.principal.display, "(User) Alice");
+        assert_eq!(report.principal.principal_type, Some("User"));
+        assert_eq!(
+            report
+                .groups
+                .iter()
+                .map(|x| x.display.as_str())
+                .collect_vec(),
+            ["(Group) Admins"]
+        );
+        let grants = report
+            .role_assignments
+            .iter()
+            .map(|x| {
+                (
+                    x.role_definition.display_name.as_str(),
+                    x.scope.rsplit('/').next().unwrap(),
+                    x.via_group.as_ref().map(|x| x.display.as_str()),
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            grants,
+            [
+                ("Owner", "rg-app", Some("(Group) Admins")),
+                ("Reader", "rg-data", None),
+            ]
+        );
+        assert_eq!(report.eligible_assignments.len(), 1);
+        assert_eq!(
+            report.eligible_assignments[0]
+                .via_group
+                .as_ref()
+                .map(|x| x.id),
+            Some(admins.id.into())
+        );
+        assert_eq!(render_report_lines(&report).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn keeps_group_assignments_of_principals_missing_from_the_directory() {
+        let missing = fixtures::user("Missing");
+        let admins = fixtures::group("Admins");
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let rg_app = fixtures::resource_group("rg-app");
+        let rbac = fixtures::rbac(
+            [owner.clone()],
+            [
+                fixtures::role_assignment(&rg_app.id, &owner, missing.id),
+                fixtures::role_assignment(&rg_app.id, &owner, admins.id),
+            ],
+        );
+        let mut principals = PrincipalDirectory::default();
+        principals.add_groups(std::slice::from_ref(&admins));
+
+        let report = build_report(&principals, missing.id.into(), &[admins.id], &rbac, &[]);
+
+        assert_eq!(report.principal.principal_type, None);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(
+            report
+                .role_assignments
+                .iter()
+                .map(|x| x.via_group.is_some())
+                .collect_vec(),
+            [false, true]
+        );
+    }
+}
@@ -0,0 +1,757 @@
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::principals::PrincipalKind;
+use crate::azure::resources::ResourceEntry;
+use crate::cache::FetchKind;
+use crate::cli::command::resource_group_tui::bulk;
+use crate::cli::command::resource_group_tui::bulk::BulkAction;
+use crate::cli::command::resource_group_tui::bulk::BulkResult;
+use crate::cli::command::resource_group_tui::clipboard;
+use crate::cli::command::resource_group_tui::clipboard::ClipboardOutput;
+use crate::cli::command::resource_group_tui::keymap::Action;
+use crate::cli::command::resource_group_tui::keymap::Keymap;
+use crate::cli::command::resource_group_tui::theme::Theme;
+use crate::cli::command::resource_group_tui::tree::ScopeNode;
+use crate::cli::command::resource_group_tui::tree::ScopeTree;
+use crate::cli::command::resource_group_tui::tree::VisibleRow;
+use crate::cli::command::role_assignment::identity::WriteIdentity;
+use crate::config::Config;
+use crate::logging::LogBuffer;
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleAssignmentId;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use cloud_terrastodon_azure::prelude::ServicePrincipal;
+use cloud_terrastodon_azure::prelude::User;
+use cloud_terrastodon_command::app_work::AppWorkState;
+use cloud_terrastodon_command::app_work::Loadable;
+use itertools::Itertools;
+use ratatui::crossterm::event::KeyEvent;
+use ratatui::crossterm::event::KeyEventKind;
+use ratatui::crossterm::event::MouseButton;
+use ratatui::crossterm::event::MouseEvent;
+use ratatui::crossterm::event::MouseEventKind;
+use ratatui::layout::Position;
+use ratatui::layout::Rect;
+use ratatui::widgets::ListState;
+use ratatui::widgets::TableState;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+#[derive(Default)]
+pub struct AppData {
+    pub resource_groups: Loadable<Vec<ResourceGroup>>,
+    pub hierarchy: Loadable<ScopeHierarchy>,
+    pub resources: Loadable<Vec<ResourceEntry>>,
+    pub rbac: Loadable<RoleDefinitionsAndAssignments>,
+    pub deny_assignments: Loadable<Vec<DenyAssignment>>,
+    pub eligible_assignments: Loadable<Vec<EligibleAssignment>>,
+    // Principals
+    pub users: Loadable<Vec<User>>,
+    pub service_principals: Loadable<Vec<ServicePrincipal>>,
+    pub security_groups: Loadable<Vec<Group>>,
+    /// Every principal loaded so far, for display strings with a type prefix
+    pub principals: PrincipalDirectory,
+    /// Per-assignment outcomes of bulk actions, oldest first
+    pub bulk_results: Vec<BulkResult>,
+    /// Cached copies still on screen because refreshing them failed
+    pub stale: Vec<StaleData>,
+    /// Bumped by every setter so derived state knows when to rebuild
+    pub revision: u64,
+}
+
+/// A cached copy left on screen because its background refresh failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleData {
+    pub kind: FetchKind,
+    /// How old the cached copy was when it was served
+    pub age: Duration,
+}
+
+impl std::fmt::Display for StaleData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.age.as_secs();
+        let age = match secs {
+            0..60 => format!("{secs}s"),
+            60..3600 => format!("{}m", secs / 60),
+            3600..86400 => format!("{}h", secs / 3600),
+            _ => format!("{}d", secs / 86400),
+        };
+        write!(
+            f,
+            "showing cached {} from {age} ago; refresh failed",
+            self.kind.label()
+        )
+    }
+}
+
+/// Where the panes were last drawn, for mapping mouse clicks back to rows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PaneAreas {
+    pub scopes: Rect,
+    pub scope_rows: Rect,
+    pub assignments: Rect,
+    /// Empty when no assignment table is shown
+    pub assignment_rows: Rect,
+}
+
+/// Which pane receives navigation keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Focus {
+    #[default]
+    Scopes,
+    Assignments,
+}
+
+/// A role assignment in effect at the selected scope.
+#[derive(Debug, Clone, Copy)]
+pub struct AssignmentRow<'a> {
+    pub role_assignment: &'a RoleAssignment,
+    pub role_definition: &'a RoleDefinition,
+    /// The enclosing scope the assignment was made at, or `None` if made at the selected scope
+    pub inherited_from: Option<&'a ScopeNode>,
+}
+
+impl AssignmentRow<'_> {
+    /// `direct`, or the enclosing scope the assignment is inherited from.
+    pub fn origin(&self) -> String {
+        match self.inherited_from {
+            Some(node) => format!("from {}", node.label),
+            None => "direct".to_string(),
+        }
+    }
+
+    /// Creation date, e.g. `2025-03-04`.
+    pub fn created(&self) -> String {
+        self.role_assignment
+            .created_on
+            .map(|x| x.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// A column of the role assignments table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignmentColumn {
+    Role,
+    Principal,
+    PrincipalType,
+    Origin,
+    Created,
+}
+
+impl AssignmentColumn {
+    /// Columns in display order; the number keys `1`-`5` sort by the matching entry.
+    pub const ALL: [AssignmentColumn; 5] = [
+        AssignmentColumn::Role,
+        AssignmentColumn::Principal,
+        AssignmentColumn::PrincipalType,
+        AssignmentColumn::Origin,
+        AssignmentColumn::Created,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            AssignmentColumn::Role => "Role",
+            AssignmentColumn::Principal => "Principal",
+            AssignmentColumn::PrincipalType => "Type",
+            AssignmentColumn::Origin => "Origin",
+            AssignmentColumn::Created => "Created",
+        }
+    }
+}
+
+/// How the role assignments table is ordered when the user has picked a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssignmentSort {
+    pub column: AssignmentColumn,
+    pub descending: bool,
+}
+
+#[derive(Default)]
+pub struct App {
+    pub data: AppData,
+    pub work: AppWorkState<AppData>,
+    pub scope_list_state: ListState,
+    /// Scopes the user has expanded or collapsed away from their default
+    pub toggled: HashSet<String>,
+    pub tree: ScopeTree,
+    pub rows: Vec<VisibleRow>,
+    tree_revision: Option<u64>,
+    pub focus: Focus,
+    /// Selection and scroll position in the role assignments table
+    pub assignment_table_state: TableState,
+    /// `None` keeps the natural order: direct assignments, then inherited ones nearest first
+    pub assignment_sort: Option<AssignmentSort>,
+    /// Assignments marked for a bulk action, in the order they were marked
+    pub marked: Vec<RoleAssignmentId>,
+    /// Set by the first delete key press; a second one deletes the marked assignments
+    pub confirm_delete: bool,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Settings that shape what is shown, such as the subscription filter
+    pub config: Config,
+    pub show_help: bool,
+    /// Log lines captured while the TUI owns the terminal
+    pub logs: LogBuffer,
+    /// The file those log lines are also written to
+    pub log_path: Option<PathBuf>,
+    pub show_logs: bool,
+    /// The login that deletes assignments; browsing always uses the current one
+    pub write_identity: WriteIdentity,
+    /// Where bulk exports are written; empty for the working directory
+    pub export_dir: PathBuf,
+    /// Where copies to the clipboard are written
+    pub clipboard: ClipboardOutput,
+    pub areas: PaneAreas,
+    /// Feedback from the last copy, shown under the scope list in place of any stale data warning
+    pub status: Option<String>,
+    pub should_quit: bool,
+}
+
+impl App {
+    /// The line under the scope list: the last copy's feedback, else the first stale data warning.
+    pub fn status_line(&self) -> String {
+        match (&self.status, self.data.stale.first()) {
+            (Some(status), _) => status.clone(),
+            (None, Some(stale)) => stale.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        self.refresh_tree();
+        if std::mem::take(&mut self.show_help) {
+            // Any key dismisses the help overlay
+            return;
+        }
+        let Some(action) = self.keymap.action(&key) else {
+            self.confirm_delete = false;
+            return;
+        };
+        if std::mem::take(&mut self.confirm_delete) && action == Action::Delete {
+            self.run_bulk(BulkAction::Delete);
+            return;
+        }
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::Help => self.show_help = true,
+            Action::ToggleLogs => self.show_logs = !self.show_logs,
+            Action::SortByRole => self.sort_by(AssignmentColumn::Role),
+            Action::SortByPrincipal => self.sort_by(AssignmentColumn::Principal),
+            Action::SortByType => self.sort_by(AssignmentColumn::PrincipalType),
+            Action::SortByOrigin => self.sort_by(AssignmentColumn::Origin),
+            Action::SortByCreated => self.sort_by(AssignmentColumn::Created),
+            Action::Delete if !self.marked.is_empty() => self.confirm_delete = true,
+            Action::ExportPlan if !self.marked.is_empty() => self.run_bulk(BulkAction::ExportPlan),
+            Action::CopyIds if !self.marked.is_empty() => self.run_bulk(BulkAction::CopyIds),
+            Action::ClearMarks => self.marked.clear(),
+            Action::CopyScopeId => {
+                if let Some(scope) = self.selected_node().map(|node| node.scope.clone()) {
+                    self.copy("scope id", &scope);
+                }
+            }
+            action => self.handle_pane_action(action),
+        }
+    }
+
+    /// Clicks select the row under the cursor and focus its pane; the wheel scrolls the focused pane.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        self.refresh_tree();
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.show_help = false;
+                self.confirm_delete = false;
+                if self.areas.scopes.contains(position) {
+                    self.focus = Focus::Scopes;
+                    if let Some(index) = row_at(
+                        self.areas.scope_rows,
+                        self.scope_list_state.offset(),
+                        position,
+                    )
+                    .filter(|x| *x < self.rows.len())
+                    {
+                        self.scope_list_state.select(Some(index));
+                    }
+                } else if self.areas.assignments.contains(position) {
+                    let count = self.assignment_rows().len();
+                    if count == 0 {
+                        return;
+                    }
+                    if self.focus != Focus::Assignments {
+                        self.focus = Focus::Assignments;
+                        self.assignment_table_state = TableState::default().with_selected(0);
+                    }
+                    if let Some(index) = row_at(
+                        self.areas.assignment_rows,
+                        self.assignment_table_state.offset(),
+                        position,
+                    )
+                    .filter(|x| *x < count)
+                    {
+                        self.assignment_table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.handle_pane_action(Action::Down),
+            MouseEventKind::ScrollUp => self.handle_pane_action(Action::Up),
+            _ => {}
+        }
+    }
+
+    fn handle_pane_action(&mut self, action: Action) {
+        match self.focus {
+            Focus::Scopes => self.handle_scopes_action(action),
+            Focus::Assignments => self.handle_assignments_action(action),
+        }
+    }
+
+    fn handle_scopes_action(&mut self, action: Action) {
+        match action {
+            Action::SwitchPane if !self.assignment_rows().is_empty() => {
+                self.focus = Focus::Assignments;
+                self.assignment_table_state = TableState::default().with_selected(0);
+            }
+            Action::Down => self.scope_list_state.select_next(),
+            Action::Up => self.scope_list_state.select_previous(),
+            Action::PageDown => {
+                for _ in 0..10 {
+                    self.scope_list_state.select_next();
+                }
+            }
+            Action::PageUp => {
+                for _ in 0..10 {
+                    self.scope_list_state.select_previous();
+                }
+            }
+            Action::First => self.scope_list_state.select_first(),
+            Action::Last => self.scope_list_state.select_last(),
+            Action::Expand => self.expand_or_descend(),
+            Action::Collapse => self.collapse_or_ascend(),
+            Action::Toggle => self.toggle_selected(),
+            _ => {}
+        }
+    }
+
+    fn handle_assignments_action(&mut self, action: Action) {
+        let last = self.assignment_rows().len().saturating_sub(1);
+        let state = &mut self.assignment_table_state;
+        match action {
+            Action::SwitchPane | Action::Collapse => self.focus = Focus::Scopes,
+            Action::Down => state.select(Some(state.selected().map_or(0, |x| x + 1).min(last))),
+            Action::Up => state.select_previous(),
+            Action::PageDown => {
+                state.select(Some(state.selected().map_or(0, |x| x + 10).min(last)))
+            }
+            Action::PageUp => state.scroll_up_by(10),
+            Action::First => state.select_first(),
+            Action::Last => state.select(Some(last)),
+            Action::Mark => self.toggle_mark(),
+            Action::CopyAssignmentId => {
+                if let Some(id) = self
+                    .selected_assignment()
+                    .map(|row| row.role_assignment.id.expanded_form())
+                {
+                    self.copy("assignment id", &id);
+                }
+            }
+            Action::CopyPrincipalId => {
+                if let Some(id) = self
+                    .selected_assignment()
+                    .map(|row| row.role_assignment.principal_id.to_string())
+                {
+                    self.copy("principal object id", &id);
+                }
+            }
+            Action::CopyCommand => {
+                if let Some(command) = self
+                    .selected_assignment()
+                    .map(|row| self.create_command(&row))
+                {
+                    self.copy("command", &command);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn copy(&mut self, what: &str, text: &str) {
+        self.status = Some(match clipboard::copy(&mut self.clipboard, text) {
+            Ok(()) => format!("Copied {what}"),
+            Err(error) => format!("Copy failed: {error}"),
+        });
+    }
+
+    /// An `az` command line that recreates the assignment, e.g. at another scope after editing.
+    pub fn create_command(&self, row: &AssignmentRow) -> String {
+        let ra = row.role_assignment;
+        let mut args = vec![
+            "az role assignment create".to_string(),
+            format!("--assignee-object-id {}", ra.principal_id),
+        ];
+        if let Some(kind) = self.principal_kind(&ra.principal_id) {
+            let kind = match kind {
+                PrincipalKind::User => "User",
+                PrincipalKind::ServicePrincipal => "ServicePrincipal",
+                PrincipalKind::Group => "Group",
+            };
+            args.push(format!("--assignee-principal-type {kind}"));
+        }
+        // The id, since a custom role can share a built-in role's name
+        args.push(format!(
+            "--role {}",
+            quote(&row.role_definition.id.expanded_form())
+        ));
+        args.push(format!("--scope {}", quote(&ra.scope.expanded_form())));
+        if let Some(condition) = &ra.condition {
+            args.push(format!("--condition {}", quote(condition)));
+        }
+        if let Some(version) = &ra.condition_version {
+            args.push(format!("--condition-version {}", quote(version)));
+        }
+        args.join(" ")
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(id) = self
+            .selected_assignment()
+            .map(|row| row.role_assignment.id.clone())
+        else {
+            return;
+        };
+        match self.marked.iter().position(|x| *x == id) {
+            Some(position) => {
+                self.marked.remove(position);
+            }
+            None => self.marked.push(id),
+        }
+    }
+
+    fn run_bulk(&mut self, action: BulkAction) {
+        if let Err(error) = bulk::enqueue(self, action) {
+            warn!("Failed to queue bulk {action}: {error:#}");
+        }
+    }
+
+    /// Whether the assignment is marked for a bulk action.
+    pub fn is_marked(&self, id: &RoleAssignmentId) -> bool {
+        self.marked.contains(id)
+    }
+
+    /// The marked assignments that still exist, in the order they were marked.
+    pub fn marked_rows(&self) -> Vec<(&RoleAssignment, &RoleDefinition)> {
+        let Loadable::Loaded { value: rbac, .. } = &self.data.rbac else {
+            return Vec::new();
+        };
+        let by_id = rbac
+            .iter_role_assignments()
+            .map(|(ra, rd)| (&ra.id, (ra, rd)))
+            .collect::<HashMap<_, _>>();
+        self.marked
+            .iter()
+            .filter_map(|id| by_id.get(id).copied())
+            .collect_vec()
+    }
+
+    /// Sort the role assignments table by `column`, reversing the order if it is already
+    /// sorted by that column. The selected assignment stays selected.
+    fn sort_by(&mut self, column: AssignmentColumn) {
+        let selected = self
+            .selected_assignment()
+            .map(|row| row.role_assignment.id.clone());
+        self.assignment_sort = Some(match self.assignment_sort {
+            Some(sort) if sort.column == column => AssignmentSort {
+                column,
+                descending: !sort.descending,
+            },
+            _ => AssignmentSort {
+                column,
+                descending: false,
+            },
+        });
+        if let Some(id) = selected {
+            let position = self
+                .assignment_rows()
+                .iter()
+                .position(|row| row.role_assignment.id == id);
+            self.assignment_table_state.select(position);
+        }
+    }
+
+    /// Rebuild the tree if any data has changed since it was last built,
+    /// keeping the same scope selected when it still exists.
+    pub fn refresh_tree(&mut self) {
+        if self.tree_revision == Some(self.data.revision) {
+            return;
+        }
+        let selected_scope = self.selected_node().map(|node| node.scope.clone());
+        let resource_groups = match &self.data.resource_groups {
+            Loadable::Loaded { value, .. } => value
+                .iter()
+                .filter(|rg| self.config.includes(rg))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        let hierarchy = match &self.data.hierarchy {
+            Loadable::Loaded { value, .. } => Some(value),
+            _ => None,
+        };
+        let resources = match &self.data.resources {
+            Loadable::Loaded { value, .. } => value.as_slice(),
+            _ => &[],
+        };
+        let rbac = match &self.data.rbac {
+            Loadable::Loaded { value, .. } => Some(value),
+            _ => None,
+        };
+        let deny_assignments = match &self.data.deny_assignments {
+            Loadable::Loaded { value, .. } => value.as_slice(),
+            _ => &[],
+        };
+        self.tree = ScopeTree::build(
+            hierarchy,
+            &resource_groups,
+            resources,
+            rbac,
+            deny_assignments,
+        );
+        if let Some(rbac) = rbac {
+            // Forget marks on assignments that have since been deleted
+            self.marked
+                .retain(|id| rbac.role_assignments.iter().any(|ra| ra.id == *id));
+        }
+        self.tree_revision = Some(self.data.revision);
+        self.refresh_rows();
+        if let Some(scope) = selected_scope {
+            let position = self
+                .rows
+                .iter()
+                .position(|row| self.tree.node(row.node).scope == scope);
+            if position.is_some() {
+                self.scope_list_state.select(position);
+            }
+        }
+    }
+
+    fn refresh_rows(&mut self) {
+        self.rows = self.tree.visible_rows(&self.toggled);
+    }
+
+    fn selected_row(&self) -> Option<(usize, VisibleRow)> {
+        let selected = self.scope_list_state.selected()?;
+        let idx = selected.min(self.rows.len().checked_sub(1)?);
+        Some((idx, self.rows[idx]))
+    }
+
+    /// The scope under the cursor.
+    pub fn selected_node(&self) -> Option<&ScopeNode> {
+        self.selected_index().map(|idx| self.tree.node(idx))
+    }
+
+    /// Index into the tree of the scope under the cursor.
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected_row().map(|(_, row)| row.node)
+    }
+
+    fn expand_or_descend(&mut self) {
+        let Some((_, row)) = self.selected_row() else {
+            return;
+        };
+        if self.tree.node(row.node).children.is_empty() {
+            return;
+        }
+        if self.tree.is_expanded(row.node, &self.toggled) {
+            self.scope_list_state.select_next();
+        } else {
+            self.toggle(row.node);
+        }
+    }
+
+    fn collapse_or_ascend(&mut self) {
+        let Some((_, row)) = self.selected_row() else {
+            return;
+        };
+        let node = self.tree.node(row.node);
+        if !node.children.is_empty() && self.tree.is_expanded(row.node, &self.toggled) {
+            self.toggle(row.node);
+        } else if let Some(parent) = node.parent {
+            let position = self.rows.iter().position(|row| row.node == parent);
+            self.scope_list_state.select(position);
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let Some((_, row)) = self.selected_row() else {
+            return;
+        };
+        if !self.tree.node(row.node).children.is_empty() {
+            self.toggle(row.node);
+        }
+    }
+
+    fn toggle(&mut self, idx: usize) {
+        let scope = &self.tree.node(idx).scope;
+        if !self.toggled.remove(scope) {
+            self.toggled.insert(scope.clone());
+        }
+        self.refresh_rows();
+    }
+
+    /// Role assignments made directly at the given scope.
+    pub fn assignments_at<'a>(
+        rbac: &'a RoleDefinitionsAndAssignments,
+        scope: &str,
+    ) -> Vec<(&'a RoleAssignment, &'a RoleDefinition)> {
+        rbac.iter_role_assignments()
+            .filter(|(ra, _)| ra.scope.expanded_form().eq_ignore_ascii_case(scope))
+            .collect_vec()
+    }
+
+    /// Active role assignments that apply at the selected scope: those made directly at it,
+    /// then those inherited from each enclosing scope, nearest first.
+    pub fn assignment_rows(&self) -> Vec<AssignmentRow<'_>> {
+        let (Some(idx), Loadable::Loaded { value: rbac, .. }) =
+            (self.selected_index(), &self.data.rbac)
+        else {
+            return Vec::new();
+        };
+        let mut rtn = Vec::new();
+        let mut current = Some(idx);
+        while let Some(current_idx) = current {
+            let node = self.tree.node(current_idx);
+            let inherited_from = (current_idx != idx).then_some(node);
+            rtn.extend(App::assignments_at(rbac, &node.scope).into_iter().map(
+                |(role_assignment, role_definition)| AssignmentRow {
+                    role_assignment,
+                    role_definition,
+                    inherited_from,
+                },
+            ));
+            current = node.parent;
+        }
+        if let Some(sort) = self.assignment_sort {
+            let key = |row: &AssignmentRow| match sort.column {
+                AssignmentColumn::Role => row.role_definition.display_name.to_lowercase(),
+                AssignmentColumn::Principal => self
+                    .principal_name(&row.role_assignment.principal_id)
+                    .to_lowercase(),
+                AssignmentColumn::PrincipalType => self
+                    .principal_kind(&row.role_assignment.principal_id)
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+                AssignmentColumn::Origin => row.origin(),
+                AssignmentColumn::Created => row.created(),
+            };
+            let mut keyed = rtn.into_iter().map(|row| (key(&row), row)).collect_vec();
+            // Stable in both directions, so ties keep the natural order
+            keyed.sort_by(|(a, _), (b, _)| if sort.descending { b.cmp(a) } else { a.cmp(b) });
+            rtn = keyed.into_iter().map(|(_, row)| row).collect();
+        }
+        rtn
+    }
+
+    /// The assignment under the cursor while the assignments pane has focus.
+    pub fn selected_assignment(&self) -> Option<AssignmentRow<'_>> {
+        if self.focus != Focus::Assignments {
+            return None;
+        }
+        let rows = self.assignment_rows();
+        let idx = self
+            .assignment_table_state
+            .selected()?
+            .min(rows.len().checked_sub(1)?);
+        rows.into_iter().nth(idx)
+    }
+
+    /// PIM eligibilities at `idx` or any enclosing scope, paired with the scope they were made at.
+    pub fn eligible_assignments_at(&self, idx: usize) -> Vec<(&ScopeNode, &EligibleAssignment)> {
+        let Loadable::Loaded { value, .. } = &self.data.eligible_assignments else {
+            return Vec::new();
+        };
+        let mut rtn = Vec::new();
+        let mut current = Some(idx);
+        while let Some(idx) = current {
+            let node = self.tree.node(idx);
+            rtn.extend(
+                value
+                    .iter()
+                    .filter(|x| x.scope.eq_ignore_ascii_case(&node.scope))
+                    .map(|x| (node, x)),
+            );
+            current = node.parent;
+        }
+        rtn
+    }
+
+    /// Deny assignments in effect at `scope`, including those inherited from above it.
+    pub fn deny_assignments_at(&self, scope: &str) -> Vec<&DenyAssignment> {
+        match &self.data.deny_assignments {
+            Loadable::Loaded { value, .. } => value
+                .iter()
+                .filter(|deny| deny.applies_at(scope))
+                .collect_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Display string for a principal, falling back to the raw ID if it is unknown yet.
+    pub fn principal_display(&self, principal_id: &PrincipalId) -> String {
+        self.data.principals.display(principal_id)
+    }
+
+    /// One-line summary of an assignment, e.g. `Reader · (User) Alice @ rg-app`.
+    pub fn assignment_label(&self, ra: &RoleAssignment, rd: &RoleDefinition) -> String {
+        let scope = ra.scope.expanded_form();
+        format!(
+            "{} · {} @ {}",
+            rd.display_name,
+            self.principal_display(&ra.principal_id),
+            scope.rsplit('/').next().unwrap_or_default()
+        )
+    }
+
+    /// Display name for a principal without the type prefix, falling back to the raw ID.
+    pub fn principal_name(&self, principal_id: &PrincipalId) -> String {
+        self.data.principals.name(principal_id)
+    }
+
+    pub fn principal_kind(&self, principal_id: &PrincipalId) -> Option<PrincipalKind> {
+        self.data.principals.kind(principal_id)
+    }
+}
+
+/// Single-quote a command line argument when it contains anything but plain id characters.
+///
+/// POSIX shells expand nothing inside single quotes, so `$`, backticks and `!` in conditions
+/// survive pasting; a `'` ends the quote, is escaped, and reopens it.
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/-_.:".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Index of the row under `position` in a list drawn in `area` and scrolled by `offset`.
+fn row_at(area: Rect, offset: usize, position: Position) -> Option<usize> {
+    area.contains(position)
+        .then(|| offset + (position.y - area.y) as usize)
+}
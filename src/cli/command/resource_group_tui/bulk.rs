@@ -0,0 +1,342 @@
+use crate::cli::command::resource_group_tui::app::App;
+use crate::cli::command::resource_group_tui::app::AppData;
+use crate::cli::command::resource_group_tui::clipboard;
+use crate::cli::command::role_assignment::DeleteRoleAssignmentArgs;
+use crate::cli::command::role_assignment::RoleAssignmentCommand;
+use crate::cli::command::role_assignment::WriteOutcome;
+use crate::cli::command::role_assignment::identity::WriteIdentity;
+use chrono::Utc;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::RoleAssignmentId;
+use cloud_terrastodon_azure::prelude::Scope;
+use cloud_terrastodon_command::app_work::Loadable;
+use cloud_terrastodon_command::app_work::LoadableWorkBuilder;
+use itertools::Itertools;
+use serde::Serialize;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// An action applied to every marked assignment at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    Delete,
+    ExportPlan,
+    CopyIds,
+}
+
+impl std::fmt::Display for BulkAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BulkAction::Delete => "delete",
+            BulkAction::ExportPlan => "export",
+            BulkAction::CopyIds => "copy",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkStatus {
+    Pending,
+    Done(String),
+    Failed(String),
+}
+
+/// The outcome of a bulk action for one assignment.
+#[derive(Debug, Clone)]
+pub struct BulkResult {
+    pub action: BulkAction,
+    /// e.g. `Reader · (User) Alice @ rg-app`
+    pub label: String,
+    pub status: BulkStatus,
+}
+
+/// One entry of an exported plan file.
+#[derive(Serialize)]
+struct PlannedRemoval {
+    role_assignment_id: RoleAssignmentId,
+    scope: String,
+    role: String,
+    principal_id: PrincipalId,
+    principal: String,
+}
+
+#[derive(Serialize)]
+struct Plan {
+    action: &'static str,
+    role_assignments: Vec<PlannedRemoval>,
+}
+
+/// Queue `action` for every marked assignment, recording a pending result for each.
+pub fn enqueue(app: &mut App, action: BulkAction) -> eyre::Result<()> {
+    let identity = app.write_identity.clone();
+    enqueue_with(app, action, move |command| {
+        let identity = identity.clone();
+        async move { identity.run(command).await }
+    })
+}
+
+/// [`enqueue`] with deletes performed by `delete` rather than the write identity, so tests can
+/// stand in for Azure.
+fn enqueue_with<F, Fut>(app: &mut App, action: BulkAction, delete: F) -> eyre::Result<()>
+where
+    F: Fn(RoleAssignmentCommand) -> Fut,
+    Fut: Future<Output = eyre::Result<WriteOutcome>> + Send + 'static,
+{
+    let (labels, items): (Vec<_>, Vec<_>) = app
+        .marked_rows()
+        .into_iter()
+        .map(|(ra, rd)| {
+            let label = app.assignment_label(ra, rd);
+            let item = PlannedRemoval {
+                role_assignment_id: ra.id.clone(),
+                scope: ra.scope.expanded_form(),
+                role: rd.display_name.clone(),
+                principal_id: ra.principal_id,
+                principal: app.principal_display(&ra.principal_id),
+            };
+            (label, item)
+        })
+        .unzip();
+    let first = app.data.bulk_results.len();
+    app.data
+        .bulk_results
+        .extend(labels.into_iter().map(|label| BulkResult {
+            action,
+            label,
+            status: BulkStatus::Pending,
+        }));
+    let all = first..app.data.bulk_results.len();
+
+    match action {
+        BulkAction::Delete => {
+            // One work item per assignment so each succeeds or fails on its own
+            for (index, item) in all.zip(items) {
+                let id = item.role_assignment_id;
+                let command = RoleAssignmentCommand::Delete(DeleteRoleAssignmentArgs {
+                    id: id.expanded_form(),
+                });
+                let done = match &app.write_identity {
+                    WriteIdentity::Current => "deleted".to_string(),
+                    identity => format!("deleted as {identity}"),
+                };
+                LoadableWorkBuilder::<AppData, WriteOutcome>::new()
+                    .description("delete_role_assignment")
+                    .setter(
+                        move |state: &mut AppData, loadable: Loadable<WriteOutcome>| {
+                            if let Loadable::Loaded { .. } = loadable
+                                && let Loadable::Loaded { value: rbac, .. } = &mut state.rbac
+                            {
+                                rbac.role_assignments.retain(|ra| ra.id != id);
+                                state.revision += 1;
+                            }
+                            record(state, index..index + 1, loadable, |_| done.clone());
+                        },
+                    )
+                    .work(delete(command))
+                    .build()?
+                    .enqueue(&app.work, &mut app.data)?;
+            }
+        }
+        BulkAction::ExportPlan => {
+            let path = app.export_dir.join(format!(
+                "role-assignment-plan-{}.json",
+                Utc::now().format("%Y%m%dT%H%M%S")
+            ));
+            let plan = Plan {
+                action: "delete",
+                role_assignments: items,
+            };
+            LoadableWorkBuilder::<AppData, PathBuf>::new()
+                .description("export_plan")
+                .setter(move |state: &mut AppData, loadable: Loadable<PathBuf>| {
+                    record(state, all.clone(), loadable, |path| {
+                        format!("exported to {}", path.display())
+                    });
+                })
+                .work(async move {
+                    tokio::fs::write(&path, serde_json::to_vec_pretty(&plan)?).await?;
+                    Ok(path)
+                })
+                .build()?
+                .enqueue(&app.work, &mut app.data)?;
+        }
+        BulkAction::CopyIds => {
+            let ids = items
+                .iter()
+                .map(|item| item.role_assignment_id.expanded_form())
+                .join("\n");
+            // On the UI thread, as background work could write the escape in the middle of a frame
+            let status = match clipboard::copy(&mut app.clipboard, &ids) {
+                Ok(()) => BulkStatus::Done("copied".to_string()),
+                Err(error) => BulkStatus::Failed(format!("{error:#}")),
+            };
+            for result in &mut app.data.bulk_results[all] {
+                result.status = status.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Update the results in `range` from the state of the work item behind them.
+fn record<T>(
+    state: &mut AppData,
+    range: Range<usize>,
+    loadable: Loadable<T>,
+    done: impl Fn(&T) -> String,
+) {
+    let status = match &loadable {
+        Loadable::Loaded { value, .. } => BulkStatus::Done(done(value)),
+        Loadable::Failed { error, .. } => BulkStatus::Failed(format!("{error:#}")),
+        _ => BulkStatus::Pending,
+    };
+    for result in &mut state.bulk_results[range] {
+        result.status = status.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::command::resource_group_tui::clipboard::last_copied;
+    use crate::cli::command::resource_group_tui::ui::tests::populated_app;
+    use crate::cli::command::resource_group_tui::ui::tests::press;
+    use ratatui::crossterm::event::KeyCode;
+    use std::time::Duration;
+
+    /// The app with the Reader assignment on rg-app marked.
+    async fn marked_app() -> App {
+        let mut app = populated_app().await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.marked.len(), 1);
+        app
+    }
+
+    /// Let queued work finish and deliver its results.
+    async fn settle(app: &mut App) {
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            app.work.handle_messages(&mut app.data).unwrap();
+        }
+    }
+
+    fn statuses(app: &App) -> Vec<(BulkAction, BulkStatus)> {
+        app.data
+            .bulk_results
+            .iter()
+            .map(|x| (x.action, x.status.clone()))
+            .collect()
+    }
+
+    fn assignment_ids(app: &App) -> Vec<RoleAssignmentId> {
+        let Loadable::Loaded { value: rbac, .. } = &app.data.rbac else {
+            panic!("rbac not loaded");
+        };
+        rbac.role_assignments.iter().map(|x| x.id.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn exports_marked_assignments_to_a_plan_file() {
+        let mut app = marked_app().await;
+        let dir = std::env::temp_dir().join(format!("bulk-export-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        app.export_dir = dir.clone();
+        press(&mut app, KeyCode::Char('e'));
+        settle(&mut app).await;
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .collect_vec();
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("role-assignment-plan-") && name.ends_with(".json"));
+        let plan: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+        assert_eq!(plan["action"], "delete");
+        let removals = plan["role_assignments"].as_array().unwrap();
+        assert_eq!(removals.len(), 1);
+        assert_eq!(removals[0]["role"], "Reader");
+        assert_eq!(
+            removals[0]["role_assignment_id"],
+            app.marked[0].expanded_form()
+        );
+        assert_eq!(
+            statuses(&app),
+            [(
+                BulkAction::ExportPlan,
+                BulkStatus::Done(format!("exported to {}", files[0].display()))
+            )]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn deletes_marked_assignments() {
+        let mut app = marked_app().await;
+        let id = app.marked[0].clone();
+        assert!(assignment_ids(&app).contains(&id));
+        enqueue_with(&mut app, BulkAction::Delete, |command| async move {
+            let RoleAssignmentCommand::Delete(args) = command else {
+                eyre::bail!("expected a delete");
+            };
+            Ok(WriteOutcome::Deleted {
+                role_assignment_id: args.id,
+            })
+        })
+        .unwrap();
+        settle(&mut app).await;
+
+        assert!(!assignment_ids(&app).contains(&id));
+        assert_eq!(assignment_ids(&app).len(), 2);
+        assert_eq!(
+            statuses(&app),
+            [(BulkAction::Delete, BulkStatus::Done("deleted".to_string()))]
+        );
+        assert!(app.marked_rows().is_empty());
+    }
+
+    #[tokio::test]
+    async fn records_a_failed_delete() {
+        let mut app = marked_app().await;
+        enqueue_with(&mut app, BulkAction::Delete, |_| async {
+            eyre::bail!("AuthorizationFailed")
+        })
+        .unwrap();
+        settle(&mut app).await;
+
+        assert_eq!(assignment_ids(&app).len(), 3);
+        assert_eq!(
+            statuses(&app),
+            [(
+                BulkAction::Delete,
+                BulkStatus::Failed("AuthorizationFailed".to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn copies_marked_ids() {
+        let mut app = marked_app().await;
+        // Mark the Owner assignment below the Reader one too
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.marked.len(), 2);
+        press(&mut app, KeyCode::Char('y'));
+        // Copying is synchronous, so there is nothing to wait for
+        assert_eq!(
+            statuses(&app),
+            [
+                (BulkAction::CopyIds, BulkStatus::Done("copied".to_string())),
+                (BulkAction::CopyIds, BulkStatus::Done("copied".to_string())),
+            ]
+        );
+        let expected = app.marked.iter().map(|x| x.expanded_form()).join("\n");
+        assert_eq!(last_copied(&app.clipboard), Some(expected));
+    }
+}
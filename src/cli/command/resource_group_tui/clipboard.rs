@@ -0,0 +1,119 @@
+use std::io::Write;
+
+/// Put `text` on the clipboard by writing the OSC 52 terminal escape to `terminal`.
+///
+/// The terminal does the copying, so this works over SSH without a local clipboard daemon.
+pub fn copy(terminal: &mut impl Write, text: &str) -> eyre::Result<()> {
+    write!(terminal, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    terminal.flush()?;
+    Ok(())
+}
+
+/// Where the copy escape is written.
+#[derive(Debug, Default)]
+pub enum ClipboardOutput {
+    /// The terminal the TUI is drawn on
+    #[default]
+    Stdout,
+    /// Kept in memory, for tests
+    #[cfg(test)]
+    Buffer(Vec<u8>),
+}
+
+impl Write for ClipboardOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ClipboardOutput::Stdout => std::io::stdout().write(buf),
+            #[cfg(test)]
+            ClipboardOutput::Buffer(buffer) => buffer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ClipboardOutput::Stdout => std::io::stdout().flush(),
+            #[cfg(test)]
+            ClipboardOutput::Buffer(_) => Ok(()),
+        }
+    }
+}
+
+/// The text of the last copy written to `output`, decoded from its escape.
+#[cfg(test)]
+pub fn last_copied(output: &ClipboardOutput) -> Option<String> {
+    let ClipboardOutput::Buffer(buffer) = output else {
+        return None;
+    };
+    let escape = std::str::from_utf8(buffer).ok()?;
+    let encoded = escape.rsplit("\x1b]52;c;").next()?.strip_suffix('\x07')?;
+    String::from_utf8(unbase64(encoded)).ok()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut rtn = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                rtn.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                rtn.push('=');
+            }
+        }
+    }
+    rtn
+}
+
+#[cfg(test)]
+fn unbase64(text: &str) -> Vec<u8> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let sextets = text
+        .bytes()
+        .take_while(|b| *b != b'=')
+        .filter_map(|b| ALPHABET.iter().position(|x| *x == b))
+        .collect::<Vec<_>>();
+    let mut rtn = Vec::with_capacity(sextets.len() * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, s)| n | ((*s as u32) << (18 - 6 * i)));
+        for i in 0..chunk.len().saturating_sub(1) {
+            rtn.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64_with_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"/subscriptions/abc"), "L3N1YnNjcmlwdGlvbnMvYWJj");
+    }
+
+    #[test]
+    fn copies_through_the_given_output() {
+        let mut output = ClipboardOutput::Buffer(Vec::new());
+        for text in [
+            "",
+            "f",
+            "fo",
+            "foo",
+            "/subscriptions/abc\n/subscriptions/def",
+        ] {
+            copy(&mut output, text).unwrap();
+            assert_eq!(last_copied(&output).as_deref(), Some(text));
+        }
+    }
+}
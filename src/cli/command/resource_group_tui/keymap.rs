@@ -0,0 +1,419 @@
+use crate::config::Config;
+use eyre::Context;
+use itertools::Itertools;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::crossterm::event::KeyEvent;
+use ratatui::crossterm::event::KeyModifiers;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something the user can do in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Help,
+    ToggleLogs,
+    SwitchPane,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Expand,
+    Collapse,
+    Toggle,
+    SortByRole,
+    SortByPrincipal,
+    SortByType,
+    SortByOrigin,
+    SortByCreated,
+    Mark,
+    ClearMarks,
+    Delete,
+    ExportPlan,
+    CopyIds,
+    CopyScopeId,
+    CopyAssignmentId,
+    CopyPrincipalId,
+    CopyCommand,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them.
+    pub const ALL: [Action; 27] = [
+        Action::Quit,
+        Action::Help,
+        Action::ToggleLogs,
+        Action::SwitchPane,
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::First,
+        Action::Last,
+        Action::Expand,
+        Action::Collapse,
+        Action::Toggle,
+        Action::SortByRole,
+        Action::SortByPrincipal,
+        Action::SortByType,
+        Action::SortByOrigin,
+        Action::SortByCreated,
+        Action::Mark,
+        Action::ClearMarks,
+        Action::Delete,
+        Action::ExportPlan,
+        Action::CopyIds,
+        Action::CopyScopeId,
+        Action::CopyAssignmentId,
+        Action::CopyPrincipalId,
+        Action::CopyCommand,
+    ];
+
+    /// The name used for the action in the `[keys]` config table.
+    pub fn name(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|x| x.as_str().map(String::from))
+            .expect("actions serialize as strings")
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide this help",
+            Action::ToggleLogs => "Show or hide recent log lines",
+            Action::SwitchPane => "Switch between scopes and assignments",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::PageUp => "Move up a page",
+            Action::PageDown => "Move down a page",
+            Action::First => "Jump to the first row",
+            Action::Last => "Jump to the last row",
+            Action::Expand => "Expand the scope, or move into it",
+            Action::Collapse => "Collapse the scope, or move to its parent",
+            Action::Toggle => "Expand or collapse the scope",
+            Action::SortByRole => "Sort assignments by role",
+            Action::SortByPrincipal => "Sort assignments by principal",
+            Action::SortByType => "Sort assignments by principal type",
+            Action::SortByOrigin => "Sort assignments by origin",
+            Action::SortByCreated => "Sort assignments by created date",
+            Action::Mark => "Mark or unmark the assignment",
+            Action::ClearMarks => "Clear all marks",
+            Action::Delete => "Delete marked assignments (press twice)",
+            Action::ExportPlan => "Export marked assignments to a plan file",
+            Action::CopyIds => "Copy marked assignment ids",
+            Action::CopyScopeId => "Copy the scope id",
+            Action::CopyAssignmentId => "Copy the assignment id",
+            Action::CopyPrincipalId => "Copy the principal object id",
+            Action::CopyCommand => "Copy an az command that recreates the assignment",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "Esc"],
+            Action::Help => &["?"],
+            Action::ToggleLogs => &["L"],
+            Action::SwitchPane => &["Tab"],
+            Action::Up => &["Up", "k"],
+            Action::Down => &["Down", "j"],
+            Action::PageUp => &["PageUp", "Ctrl+u"],
+            Action::PageDown => &["PageDown", "Ctrl+d"],
+            Action::First => &["Home", "g"],
+            Action::Last => &["End", "G"],
+            Action::Expand => &["Right", "l"],
+            Action::Collapse => &["Left", "h"],
+            Action::Toggle => &["Enter"],
+            Action::SortByRole => &["1"],
+            Action::SortByPrincipal => &["2"],
+            Action::SortByType => &["3"],
+            Action::SortByOrigin => &["4"],
+            Action::SortByCreated => &["5"],
+            Action::Mark => &["Space"],
+            Action::ClearMarks => &["u"],
+            Action::Delete => &["d"],
+            Action::ExportPlan => &["e"],
+            Action::CopyIds => &["y"],
+            Action::CopyScopeId => &["c"],
+            Action::CopyAssignmentId => &["a"],
+            Action::CopyPrincipalId => &["p"],
+            Action::CopyCommand => &["C"],
+        }
+    }
+}
+
+/// A key with the modifiers that matter for matching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    /// Only Ctrl and Alt; Shift is already reflected in the character
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn relevant(modifiers: KeyModifiers) -> KeyModifiers {
+        modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        self.code == key.code && self.modifiers == KeyBinding::relevant(key.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            if let Some(x) = rest.strip_prefix("Ctrl+") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = x;
+            } else if let Some(x) = rest.strip_prefix("Alt+") {
+                modifiers |= KeyModifiers::ALT;
+                rest = x;
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest {
+                "Esc" => KeyCode::Esc,
+                "Tab" => KeyCode::Tab,
+                "BackTab" => KeyCode::BackTab,
+                "Enter" => KeyCode::Enter,
+                "Space" => KeyCode::Char(' '),
+                "Backspace" => KeyCode::Backspace,
+                "Delete" => KeyCode::Delete,
+                "Insert" => KeyCode::Insert,
+                "Up" => KeyCode::Up,
+                "Down" => KeyCode::Down,
+                "Left" => KeyCode::Left,
+                "Right" => KeyCode::Right,
+                "PageUp" => KeyCode::PageUp,
+                "PageDown" => KeyCode::PageDown,
+                "Home" => KeyCode::Home,
+                "End" => KeyCode::End,
+                _ => match rest.strip_prefix('F').and_then(|x| x.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => eyre::bail!("Unknown key {s:?}"),
+                },
+            },
+        };
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Which keys trigger which actions.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: Action::ALL
+                .iter()
+                .map(|action| {
+                    let keys = action
+                        .default_keys()
+                        .iter()
+                        .map(|x| x.parse().expect("default key bindings parse"))
+                        .collect();
+                    (*action, keys)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default keymap with the configured `[keys]` overrides applied.
+    pub fn from_config(config: &Config) -> eyre::Result<Keymap> {
+        Keymap::from_overrides(&config.keys).wrap_err_with(|| {
+            let sources = config
+                .keys
+                .keys()
+                .map(|action| config.source(&format!("keys.{action}")).to_string())
+                .unique()
+                .join(", ");
+            format!("Reading [keys] from {sources}")
+        })
+    }
+
+    /// Apply overrides like `down = ["Down", "n"]`; each listed action replaces its defaults.
+    ///
+    /// Fails when a key ends up bound to two actions, since only one of them could ever run.
+    pub fn from_overrides(overrides: &BTreeMap<String, Vec<String>>) -> eyre::Result<Keymap> {
+        let mut rtn = Keymap::default();
+        for (name, replacement) in overrides {
+            let action: Action = serde_json::from_value(serde_json::Value::from(name.as_str()))
+                .map_err(|_| eyre::eyre!("Unknown action {name:?}"))?;
+            let keys = rtn
+                .bindings
+                .iter_mut()
+                .find_map(|(x, keys)| (*x == action).then_some(keys))
+                .expect("every action has bindings");
+            *keys = replacement
+                .iter()
+                .map(|x| x.parse())
+                .collect::<eyre::Result<_>>()?;
+        }
+        for (i, (action, keys)) in rtn.bindings.iter().enumerate() {
+            for (other, other_keys) in &rtn.bindings[i + 1..] {
+                if let Some(key) = keys.iter().find(|x| other_keys.contains(x)) {
+                    eyre::bail!(
+                        "{key} is bound to both {} and {}; rebind one of them",
+                        action.name(),
+                        other.name()
+                    );
+                }
+            }
+        }
+        Ok(rtn)
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|x| x.matches(key)))
+            .map(|(action, _)| *action)
+    }
+
+    /// The first key bound to `action`, for hints, or `-` if it is unbound.
+    pub fn hint(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(x, _)| *x == action)
+            .and_then(|(_, keys)| keys.first())
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Every action with the keys bound to it, in help order.
+    pub fn bindings(&self) -> &[(Action, Vec<KeyBinding>)] {
+        &self.bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays_keys() {
+        for key in [
+            "q",
+            "Esc",
+            "Space",
+            "Ctrl+d",
+            "Alt+Ctrl+x",
+            "PageDown",
+            "F5",
+            "G",
+        ] {
+            let binding: KeyBinding = key.parse().unwrap();
+            let reparsed: KeyBinding = binding.to_string().parse().unwrap();
+            assert_eq!(binding, reparsed);
+        }
+        assert!("Hyper+x".parse::<KeyBinding>().is_err());
+        assert!("F13".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn home_selects_first_and_end_selects_last() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(&KeyEvent::from(KeyCode::Home)),
+            Some(Action::First)
+        );
+        assert_eq!(
+            keymap.action(&KeyEvent::from(KeyCode::End)),
+            Some(Action::Last)
+        );
+    }
+
+    fn overrides(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|x| x.to_string()).collect();
+                (action.to_string(), keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn overrides_replace_defaults_per_action() {
+        let keymap =
+            Keymap::from_overrides(&overrides(&[("down", &["n"]), ("quit", &["Ctrl+c"])])).unwrap();
+        assert_eq!(
+            keymap.action(&KeyEvent::from(KeyCode::Char('n'))),
+            Some(Action::Down)
+        );
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('j'))), None);
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('q'))), None);
+        assert_eq!(
+            keymap.action(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        // Shift is carried by the character itself
+        assert_eq!(
+            keymap.action(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Last)
+        );
+        assert!(Keymap::from_overrides(&overrides(&[("down", &["Nope"])])).is_err());
+        assert!(Keymap::from_overrides(&overrides(&[("dance", &["x"])])).is_err());
+    }
+
+    #[test]
+    fn rejects_a_key_bound_to_two_actions() {
+        let error = Keymap::from_overrides(&overrides(&[("down", &["Down", "g"])])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "g is bound to both down and first; rebind one of them"
+        );
+        // Moving the other action off the key resolves it
+        Keymap::from_overrides(&overrides(&[
+            ("down", &["Down", "g"]),
+            ("first", &["Home"]),
+        ]))
+        .unwrap();
+        // So does unbinding it
+        Keymap::from_overrides(&overrides(&[("down", &["Down", "g"]), ("first", &[])])).unwrap();
+    }
+
+    #[test]
+    fn names_actions_as_in_the_config() {
+        assert_eq!(Action::PageDown.name(), "page_down");
+        assert_eq!(Action::CopyIds.name(), "copy_ids");
+    }
+}
@@ -1,11 +1,38 @@
+mod app;
+mod bulk;
+mod clipboard;
+mod keymap;
+mod theme;
+mod tree;
+mod ui;
+
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::deny_assignments::fetch_all_deny_assignments;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::eligible_assignments::fetch_all_eligible_assignments;
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::hierarchy::fetch_scope_hierarchy;
+use crate::azure::resources::ResourceEntry;
+use crate::azure::resources::fetch_all_resources;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::command::resource_group_tui::app::App;
+use crate::cli::command::resource_group_tui::app::AppData;
+use crate::cli::command::resource_group_tui::app::StaleData;
+use crate::cli::command::resource_group_tui::keymap::Keymap;
+use crate::cli::command::resource_group_tui::theme::Theme;
+use crate::cli::command::role_assignment::identity::WriteIdentityArgs;
+use crate::cli::global_args::GlobalArgs;
 use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use crate::logging::capture_for_tui;
+use crate::snapshot::TenantSnapshot;
 use arbitrary::Arbitrary;
 use clap::Args;
 use cloud_terrastodon_azure::prelude::Group;
-use cloud_terrastodon_azure::prelude::PrincipalId;
 use cloud_terrastodon_azure::prelude::ResourceGroup;
 use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
-use cloud_terrastodon_azure::prelude::Scope;
 use cloud_terrastodon_azure::prelude::ServicePrincipal;
 use cloud_terrastodon_azure::prelude::User;
 use cloud_terrastodon_azure::prelude::fetch_all_resource_groups;
@@ -13,322 +40,331 @@ use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments
 use cloud_terrastodon_azure::prelude::fetch_all_security_groups;
 use cloud_terrastodon_azure::prelude::fetch_all_service_principals;
 use cloud_terrastodon_azure::prelude::fetch_all_users;
-use cloud_terrastodon_command::app_work::AppWorkState;
 use cloud_terrastodon_command::app_work::Loadable;
 use cloud_terrastodon_command::app_work::LoadableWorkBuilder;
-use itertools::Itertools;
+use ratatui::crossterm::event::DisableMouseCapture;
+use ratatui::crossterm::event::EnableMouseCapture;
 use ratatui::crossterm::event::Event;
-use ratatui::crossterm::event::KeyCode;
-use ratatui::crossterm::event::KeyEventKind;
 use ratatui::crossterm::event::{self};
-use ratatui::layout::Constraint;
-use ratatui::layout::Layout;
-use ratatui::prelude::*;
-use ratatui::widgets::Block;
-use ratatui::widgets::Borders;
-use ratatui::widgets::List;
-use ratatui::widgets::ListItem;
-use ratatui::widgets::ListState;
-use ratatui::widgets::Paragraph;
-use ratatui::widgets::Wrap;
-use std::collections::HashMap;
-use std::ffi::OsString;
+use ratatui::crossterm::execute;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Builder;
+use tokio::sync::OnceCell;
 use tracing::info;
+use tracing::warn;
 
-/// Launch a TUI for managing/inspecting resource groups (stub)
-#[derive(Args, Clone, Arbitrary, PartialEq, Debug)]
+/// Browse role assignments across the scope tree and delete, export or copy them in bulk
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
 pub struct ResourceGroupTuiArgs {
-    // In the future: flags such as --subscription, --tenant, filters, etc.
+    /// Browse a snapshot file instead of fetching from Azure
+    #[clap(long)]
+    #[arbitrary(with = optional_path)]
+    pub snapshot: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub write_identity: WriteIdentityArgs,
 }
 
 impl ResourceGroupTuiArgs {
-    pub fn invoke(self) -> eyre::Result<()> {
-        #[derive(Default)]
-        struct AppData {
-            resource_groups: Loadable<Vec<ResourceGroup>>,
-            rbac: Loadable<RoleDefinitionsAndAssignments>,
-            // Principals
-            users: Loadable<Vec<User>>,
-            service_principals: Loadable<Vec<ServicePrincipal>>,
-            security_groups: Loadable<Vec<Group>>,
-            // Lookup map from principal UUID -> display string with type prefix
-            principal_display: HashMap<PrincipalId, String>,
-        }
-
-        #[derive(Default)]
-        struct App {
-            data: AppData,
-            work: AppWorkState<AppData>,
-            rg_list_state: ListState,
-        }
-
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
         Builder::new_current_thread()
             .enable_all()
             .build()?
             .block_on(async {
                 // Set up app state
                 let mut app = App::default();
+                app.keymap = Keymap::from_config(config)?;
+                app.theme = Theme::named(config.theme);
+                app.config = config.clone();
+                app.write_identity = self.write_identity.identity(global_args)?;
+                match &self.snapshot {
+                    Some(path) => {
+                        let snapshot = TenantSnapshot::load(path)?;
+                        enqueue_value(&mut app, set_hierarchy, snapshot.hierarchy())?;
+                        enqueue_value(&mut app, set_resource_groups, snapshot.resource_groups)?;
+                        enqueue_value(&mut app, set_resources, snapshot.resources)?;
+                        enqueue_value(&mut app, set_rbac, snapshot.rbac)?;
+                        enqueue_value(&mut app, set_deny_assignments, snapshot.deny_assignments)?;
+                        enqueue_value(
+                            &mut app,
+                            set_eligible_assignments,
+                            snapshot.eligible_assignments,
+                        )?;
+                        enqueue_value(
+                            &mut app,
+                            set_service_principals,
+                            snapshot.service_principals,
+                        )?;
+                        enqueue_value(&mut app, set_users, snapshot.users)?;
+                        enqueue_value(&mut app, set_security_groups, snapshot.security_groups)?;
+                    }
+                    None => {
+                        let cache = Cache::open(global_args, config).await?;
+                        let hierarchy = SharedHierarchy::new(&cache);
 
-                // Queue background work for resource groups
-                LoadableWorkBuilder::<AppData, Vec<ResourceGroup>>::new()
-                    .description("fetch_all_resource_groups")
-                    .setter(|state, value| state.resource_groups = value)
-                    .work(async { fetch_all_resource_groups().await })
-                    .build()?
-                    .enqueue(&app.work, &mut app.data)?;
-
-                // Queue background work for RBAC
-                LoadableWorkBuilder::<AppData, RoleDefinitionsAndAssignments>::new()
-                    .description("fetch_all_role_definitions_and_assignments")
-                    .setter(|state, value| state.rbac = value)
-                    .work(async { fetch_all_role_definitions_and_assignments().await })
-                    .build()?
-                    .enqueue(&app.work, &mut app.data)?;
-
-                // Queue background work for principals: service principals, users, security groups
-                LoadableWorkBuilder::<AppData, Vec<ServicePrincipal>>::new()
-                    .description("fetch_all_service_principals")
-                    .setter(
-                        |state: &mut AppData, loadable: Loadable<Vec<ServicePrincipal>>| {
-                            // When transitioned to Loaded, update principal_display
-                            if let Loadable::Loaded { value, .. } = &loadable {
-                                for sp in value.iter() {
-                                    // Assuming `sp.id` implements AsRef<Uuid> and `display_name` exists
-                                    state.principal_display.insert(
-                                        sp.id.into(),
-                                        format!("(Service Principal) {}", sp.display_name),
-                                    );
+                        // Queue background work, serving cached copies first where we have them
+                        enqueue_cached(&mut app, &cache, FetchKind::Hierarchy, set_hierarchy, {
+                            let hierarchy = hierarchy.clone();
+                            async move { hierarchy.get().await }
+                        })?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::ResourceGroups,
+                            set_resource_groups,
+                            fetch_all_resource_groups(),
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::Resources,
+                            set_resources,
+                            fetch_all_resources(),
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::RoleDefinitionsAndAssignments,
+                            set_rbac,
+                            fetch_all_role_definitions_and_assignments(),
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::DenyAssignments,
+                            set_deny_assignments,
+                            {
+                                let hierarchy = hierarchy.clone();
+                                async move { fetch_all_deny_assignments(&hierarchy.get().await?).await }
+                            },
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::EligibleAssignments,
+                            set_eligible_assignments,
+                            {
+                                let hierarchy = hierarchy.clone();
+                                async move {
+                                    fetch_all_eligible_assignments(&hierarchy.get().await?).await
                                 }
-                            }
-                            state.service_principals = loadable;
-                        },
-                    )
-                    .work(async { fetch_all_service_principals().await })
-                    .build()?
-                    .enqueue(&app.work, &mut app.data)?;
-
-                LoadableWorkBuilder::<AppData, Vec<User>>::new()
-                    .description("fetch_all_users")
-                    .setter(|state: &mut AppData, loadable: Loadable<Vec<User>>| {
-                        if let Loadable::Loaded { value, .. } = &loadable {
-                            for user in value.iter() {
-                                state.principal_display.insert(
-                                    user.id.into(),
-                                    format!("(User) {}", user.display_name),
-                                );
-                            }
-                        }
-                        state.users = loadable;
-                    })
-                    .work(async { fetch_all_users().await })
-                    .build()?
-                    .enqueue(&app.work, &mut app.data)?;
-
-                LoadableWorkBuilder::<AppData, Vec<Group>>::new()
-                    .description("fetch_all_security_groups")
-                    .setter(|state: &mut AppData, loadable: Loadable<Vec<Group>>| {
-                        if let Loadable::Loaded { value, .. } = &loadable {
-                            for sg in value.iter() {
-                                state
-                                    .principal_display
-                                    .insert(sg.id.into(), format!("(Group) {}", sg.display_name));
-                            }
-                        }
-                        state.security_groups = loadable;
-                    })
-                    .work(async { fetch_all_security_groups().await })
-                    .build()?
-                    .enqueue(&app.work, &mut app.data)?;
+                            },
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::ServicePrincipals,
+                            set_service_principals,
+                            fetch_all_service_principals(),
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::Users,
+                            set_users,
+                            fetch_all_users(),
+                        )?;
+                        enqueue_cached(
+                            &mut app,
+                            &cache,
+                            FetchKind::SecurityGroups,
+                            set_security_groups,
+                            fetch_all_security_groups(),
+                        )?;
+                    }
+                }
 
+                // Logs would draw over the TUI, so send them to a file and the log viewer instead
+                let logs = capture_for_tui()?;
+                app.logs = logs.buffer.clone();
+                app.log_path = logs.path.clone();
                 let mut terminal = ratatui::init();
+                let terminal_guard = TerminalGuard;
                 terminal.clear()?;
+                execute!(std::io::stdout(), EnableMouseCapture)?;
 
-                'outer: loop {
+                while !app.should_quit {
                     app.work.handle_messages(&mut app.data)?;
+                    app.refresh_tree();
 
                     // Keyboard handling
                     while event::poll(Duration::from_millis(0))? {
-                        if let Event::Key(key) = event::read()? {
-                            if key.kind != KeyEventKind::Press {
-                                continue;
-                            }
-                            match key.code {
-                                KeyCode::Esc | KeyCode::Char('q') => break 'outer,
-                                KeyCode::Down => {
-                                    let this = &mut app;
-                                    this.rg_list_state.select_next();
-                                }
-                                KeyCode::Up => {
-                                    let this = &mut app;
-                                    this.rg_list_state.select_previous();
-                                }
-                                KeyCode::PageDown => {
-                                    let this = &mut app;
-                                    for _ in 0..10 {
-                                        this.rg_list_state.select_next();
-                                    }
-                                }
-                                KeyCode::PageUp => {
-                                    let this = &mut app;
-                                    for _ in 0..10 {
-                                        this.rg_list_state.select_previous();
-                                    }
-                                }
-                                // Per request: Home -> select_last, End -> select_first
-                                KeyCode::Home => {
-                                    let this = &mut app;
-                                    this.rg_list_state.select_last();
-                                }
-                                KeyCode::End => {
-                                    let this = &mut app;
-                                    this.rg_list_state.select_first();
-                                }
-                                _ => {}
-                            }
+                        match event::read()? {
+                            Event::Key(key) => app.handle_key(key),
+                            Event::Mouse(mouse) => app.handle_mouse(mouse),
+                            _ => {}
                         }
                     }
 
-                    terminal.draw(|f| {
-                        let area = f.area();
-                        let [left, right] = Layout::horizontal([
-                            Constraint::Percentage(40),
-                            Constraint::Percentage(60),
-                        ])
-                        .areas(area);
-
-                        // Left: Resource Groups List
-                        let rg_items: Vec<ListItem> = match &app.data.resource_groups {
-                            Loadable::Loaded { value, .. } => value
-                                .iter()
-                                .map(|rg| ListItem::new(rg.name.to_string()))
-                                .collect(),
-                            Loadable::Loading { .. } => {
-                                vec![ListItem::new("Loading resource groups...")]
-                            }
-                            Loadable::Failed { error, .. } => {
-                                vec![ListItem::new(format!("Error: {error}"))]
-                            }
-                            Loadable::NotLoaded => vec![ListItem::new("Not loaded")],
-                        };
-                        ratatui::widgets::StatefulWidget::render(
-                            List::new(rg_items)
-                                .block(
-                                    Block::default()
-                                        .title("Resource Groups")
-                                        .borders(Borders::ALL),
-                                )
-                                .highlight_symbol("> ")
-                                .highlight_style(Style::default().add_modifier(Modifier::BOLD)),
-                            left,
-                            f.buffer_mut(),
-                            &mut app.rg_list_state,
-                        );
-
-                        // Right: Role Assignments for selected RG
-                        let right_widget: Paragraph =
-                            match (&app.data.resource_groups, &app.data.rbac) {
-                                (
-                                    Loadable::Loaded { value: rgs, .. },
-                                    Loadable::Loaded { value: rbac, .. },
-                                ) => {
-                                    if let Some(idx) = app.rg_list_state.selected() {
-                                        if let Some(rg) = rgs.get(idx) {
-                                            let assignments = rbac
-                                                .iter_role_assignments()
-                                                .filter_map(|(ra, rd)| {
-                                                    if ra.scope == rg.id.as_scope_impl() {
-                                                        Some((ra, rd))
-                                                    } else {
-                                                        None
-                                                    }
-                                                })
-                                                .collect_vec();
-                                            if assignments.is_empty() {
-                                                Paragraph::new("No role assignments.").block(
-                                                    Block::default()
-                                                        .title("Role Assignments")
-                                                        .borders(Borders::ALL),
-                                                )
-                                            } else {
-                                                let items = assignments
-                                                    .iter()
-                                                    .map(|(ra, rd)| {
-                                                        // Look up principal display; fall back to the raw ID if unknown yet
-                                                        let principal = app
-                                                            .data
-                                                            .principal_display
-                                                            .get(&ra.principal_id)
-                                                            .cloned()
-                                                            .unwrap_or_else(|| {
-                                                                format!("{}", ra.principal_id)
-                                                            });
-                                                        format!(
-                                                            "{}: {}",
-                                                            rd.display_name, principal
-                                                        )
-                                                    })
-                                                    .collect::<Vec<_>>()
-                                                    .join("\n");
-                                                Paragraph::new(items)
-                                                    .block(
-                                                        Block::default()
-                                                            .title("Role Assignments")
-                                                            .borders(Borders::ALL),
-                                                    )
-                                                    .wrap(Wrap { trim: false })
-                                            }
-                                        } else {
-                                            Paragraph::new("No resource group selected.").block(
-                                                Block::default()
-                                                    .title("Role Assignments")
-                                                    .borders(Borders::ALL),
-                                            )
-                                        }
-                                    } else {
-                                        Paragraph::new("No resource group selected.").block(
-                                            Block::default()
-                                                .title("Role Assignments")
-                                                .borders(Borders::ALL),
-                                        )
-                                    }
-                                }
-                                (Loadable::Loading { .. }, _) | (_, Loadable::Loading { .. }) => {
-                                    Paragraph::new("Loading...").block(
-                                        Block::default()
-                                            .title("Role Assignments")
-                                            .borders(Borders::ALL),
-                                    )
-                                }
-                                (Loadable::Failed { error, .. }, _)
-                                | (_, Loadable::Failed { error, .. }) => {
-                                    Paragraph::new(format!("Error: {error}")).block(
-                                        Block::default()
-                                            .title("Role Assignments")
-                                            .borders(Borders::ALL),
-                                    )
-                                }
-                                _ => Paragraph::new("Not loaded.").block(
-                                    Block::default()
-                                        .title("Role Assignments")
-                                        .borders(Borders::ALL),
-                                ),
-                            };
-                        right_widget.render(right, f.buffer_mut());
-                    })?;
+                    terminal.draw(|f| ui::render(f, &mut app))?;
 
                     tokio::time::sleep(Duration::from_millis(50)).await;
                 }
-                ratatui::restore();
+                drop(terminal_guard);
+                drop(logs);
                 info!("Exited resource-group TUI");
                 eyre::Ok(())
             })
     }
 }
 
-impl ToArgs for ResourceGroupTuiArgs {
-    fn to_args(&self) -> Vec<OsString> {
-        Vec::new()
+/// Hands the terminal back on drop, so an error or panic in the event loop does not leave it
+/// capturing the mouse; `ratatui::init`'s panic hook only restores the screen and raw mode.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), DisableMouseCapture);
+        ratatui::restore();
+    }
+}
+
+fn set_hierarchy(state: &mut AppData, loadable: Loadable<ScopeHierarchy>) {
+    state.hierarchy = loadable;
+    state.revision += 1;
+}
+
+fn set_resource_groups(state: &mut AppData, loadable: Loadable<Vec<ResourceGroup>>) {
+    state.resource_groups = loadable;
+    state.revision += 1;
+}
+
+fn set_resources(state: &mut AppData, loadable: Loadable<Vec<ResourceEntry>>) {
+    state.resources = loadable;
+    state.revision += 1;
+}
+
+fn set_rbac(state: &mut AppData, loadable: Loadable<RoleDefinitionsAndAssignments>) {
+    state.rbac = loadable;
+    state.revision += 1;
+}
+
+fn set_deny_assignments(state: &mut AppData, loadable: Loadable<Vec<DenyAssignment>>) {
+    state.deny_assignments = loadable;
+    state.revision += 1;
+}
+
+fn set_eligible_assignments(state: &mut AppData, loadable: Loadable<Vec<EligibleAssignment>>) {
+    state.eligible_assignments = loadable;
+    state.revision += 1;
+}
+
+fn set_service_principals(state: &mut AppData, loadable: Loadable<Vec<ServicePrincipal>>) {
+    // When transitioned to Loaded, update the principal lookup
+    if let Loadable::Loaded { value, .. } = &loadable {
+        state.principals.add_service_principals(value);
+    }
+    state.service_principals = loadable;
+    state.revision += 1;
+}
+
+fn set_users(state: &mut AppData, loadable: Loadable<Vec<User>>) {
+    if let Loadable::Loaded { value, .. } = &loadable {
+        state.principals.add_users(value);
+    }
+    state.users = loadable;
+    state.revision += 1;
+}
+
+fn set_security_groups(state: &mut AppData, loadable: Loadable<Vec<Group>>) {
+    if let Loadable::Loaded { value, .. } = &loadable {
+        state.principals.add_groups(value);
+    }
+    state.security_groups = loadable;
+    state.revision += 1;
+}
+
+/// The scope hierarchy shared by the fetches that walk every subscription, fetched at most once.
+///
+/// A fresh cached copy is used as is, so a warm start does not fetch it at all.
+#[derive(Clone, Default)]
+struct SharedHierarchy(Arc<OnceCell<ScopeHierarchy>>);
+
+impl SharedHierarchy {
+    fn new(cache: &Cache) -> Self {
+        let rtn = Self::default();
+        if let Some(entry) = cache.read::<ScopeHierarchy>(FetchKind::Hierarchy)
+            && cache.is_fresh(&entry)
+        {
+            let _ = rtn.0.set(entry.value);
+        }
+        rtn
+    }
+
+    async fn get(&self) -> eyre::Result<ScopeHierarchy> {
+        self.0.get_or_try_init(fetch_scope_hierarchy).await.cloned()
+    }
+}
+
+/// Queue an already-available value through the same setter path as a fetch.
+fn enqueue_value<T: Send + Sync + 'static>(
+    app: &mut App,
+    setter: fn(&mut AppData, Loadable<T>),
+    value: T,
+) -> eyre::Result<()> {
+    LoadableWorkBuilder::<AppData, T>::new()
+        .description("load_value")
+        .setter(setter)
+        .work(async move { Ok(value) })
+        .build()?
+        .enqueue(&app.work, &mut app.data)
+}
+
+/// Queue the fetch for `kind`, serving the cached copy first when one exists.
+///
+/// A missing or stale entry is refetched in the background and written back to the cache.
+/// While a cached copy is on screen, the refresh only replaces it once it succeeds; if it fails
+/// the copy stays and the status line says so.
+fn enqueue_cached<T, Fut>(
+    app: &mut App,
+    cache: &Cache,
+    kind: FetchKind,
+    setter: fn(&mut AppData, Loadable<T>),
+    fetch: Fut,
+) -> eyre::Result<()>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    Fut: Future<Output = eyre::Result<T>> + Send + 'static,
+{
+    let cached = cache.read::<T>(kind);
+    let cached_age = cached.as_ref().map(|entry| entry.age());
+    let needs_refresh = cached.as_ref().is_none_or(|entry| !cache.is_fresh(entry));
+
+    if let Some(entry) = cached {
+        enqueue_value(app, setter, entry.value)?;
+    }
+
+    if needs_refresh {
+        let cache = cache.clone();
+        LoadableWorkBuilder::<AppData, T>::new()
+            .description(kind.description())
+            .setter(move |state: &mut AppData, loadable: Loadable<T>| {
+                match (loadable, cached_age) {
+                    (Loadable::Failed { error, .. }, Some(age)) => {
+                        let stale = StaleData { kind, age };
+                        warn!("Refreshing {} failed, {stale}: {error:?}", kind.label());
+                        state.stale.push(stale);
+                        state.revision += 1;
+                    }
+                    (loadable @ Loadable::Loaded { .. }, _) | (loadable, None) => {
+                        state.stale.retain(|x| x.kind != kind);
+                        setter(state, loadable);
+                    }
+                    // Keep the cached copy on screen while the refresh is in flight
+                    (Loadable::NotLoaded | Loadable::Loading { .. }, Some(_)) => {}
+                }
+            })
+            .work(async move {
+                let value = fetch.await?;
+                cache.write(kind, &value);
+                Ok(value)
+            })
+            .build()?
+            .enqueue(&app.work, &mut app.data)?;
     }
+    Ok(())
 }
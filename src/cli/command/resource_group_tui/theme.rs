@@ -0,0 +1,51 @@
+use crate::config::ThemeName;
+use ratatui::style::Color;
+
+/// Colours by meaning, so the `theme` setting can swap them all at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Keys, subscriptions and PIM eligibilities
+    pub accent: Color,
+    /// Management groups
+    pub secondary: Color,
+    /// Resources, conditions and pending work
+    pub warning: Color,
+    /// Deny assignments, failures and destructive prompts
+    pub danger: Color,
+    /// Marks and finished work
+    pub success: Color,
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Theme {
+        match name {
+            ThemeName::Default => Theme {
+                accent: Color::Cyan,
+                secondary: Color::Magenta,
+                warning: Color::Yellow,
+                danger: Color::Red,
+                success: Color::Green,
+            },
+            ThemeName::Light => Theme {
+                accent: Color::Blue,
+                secondary: Color::Magenta,
+                warning: Color::Rgb(160, 100, 0),
+                danger: Color::Red,
+                success: Color::Rgb(0, 120, 0),
+            },
+            ThemeName::Monochrome => Theme {
+                accent: Color::Reset,
+                secondary: Color::Reset,
+                warning: Color::Reset,
+                danger: Color::Reset,
+                success: Color::Reset,
+            },
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::named(ThemeName::Default)
+    }
+}
@@ -0,0 +1,323 @@
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::resources::ResourceEntry;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::Scope;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeKind {
+    ManagementGroup,
+    Subscription,
+    ResourceGroup,
+    Resource,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScopeNode {
+    pub kind: NodeKind,
+    /// Fully expanded scope, e.g. `/subscriptions/<id>`
+    pub scope: String,
+    pub label: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Assignments made directly at this scope
+    pub assignment_count: usize,
+    /// Deny assignments made directly at this scope
+    pub deny_count: usize,
+}
+
+/// A node as it appears in the flattened, partially collapsed tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisibleRow {
+    pub node: usize,
+    pub depth: usize,
+}
+
+/// Management groups → subscriptions → resource groups → resources, the way Azure structures RBAC.
+#[derive(Debug, Default)]
+pub struct ScopeTree {
+    nodes: Vec<ScopeNode>,
+    roots: Vec<usize>,
+}
+
+impl ScopeTree {
+    /// Build the tree from whatever has loaded so far.
+    ///
+    /// Without a hierarchy, subscriptions are inferred from resource group ids and shown as roots.
+    pub fn build(
+        hierarchy: Option<&ScopeHierarchy>,
+        resource_groups: &[ResourceGroup],
+        resources: &[ResourceEntry],
+        rbac: Option<&RoleDefinitionsAndAssignments>,
+        deny_assignments: &[DenyAssignment],
+    ) -> Self {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        if let Some(rbac) = rbac {
+            for (ra, _) in rbac.iter_role_assignments() {
+                *counts
+                    .entry(ra.scope.expanded_form().to_lowercase())
+                    .or_default() += 1;
+            }
+        }
+        let mut deny_counts: HashMap<String, usize> = HashMap::new();
+        for deny in deny_assignments {
+            *deny_counts.entry(deny.scope.to_lowercase()).or_default() += 1;
+        }
+
+        let mut tree = ScopeTree::default();
+        let mut management_groups: HashMap<String, usize> = HashMap::new();
+        let mut subscriptions: HashMap<String, usize> = HashMap::new();
+        let mut resource_group_nodes: HashMap<String, usize> = HashMap::new();
+
+        if let Some(hierarchy) = hierarchy {
+            for mg in &hierarchy.management_groups {
+                let idx = tree.push(NodeKind::ManagementGroup, mg.id(), mg.display_name.clone());
+                management_groups.insert(mg.name.to_lowercase(), idx);
+            }
+            for mg in &hierarchy.management_groups {
+                let idx = management_groups[&mg.name.to_lowercase()];
+                let parent = mg
+                    .parent_name
+                    .as_ref()
+                    .and_then(|name| management_groups.get(&name.to_lowercase()))
+                    .copied();
+                tree.attach(idx, parent);
+            }
+            for sub in &hierarchy.subscriptions {
+                let idx = tree.push(NodeKind::Subscription, sub.id(), sub.display_name.clone());
+                subscriptions.insert(sub.subscription_id.to_lowercase(), idx);
+                let parent = sub
+                    .management_group_name
+                    .as_ref()
+                    .and_then(|name| management_groups.get(&name.to_lowercase()))
+                    .copied();
+                tree.attach(idx, parent);
+            }
+        }
+
+        for rg in resource_groups {
+            let scope = rg.id.expanded_form();
+            let parent = subscription_id_of(&scope).map(|subscription_id| {
+                *subscriptions
+                    .entry(subscription_id.to_lowercase())
+                    .or_insert_with(|| {
+                        let idx = tree.push(
+                            NodeKind::Subscription,
+                            format!("/subscriptions/{subscription_id}"),
+                            subscription_id.to_string(),
+                        );
+                        tree.attach(idx, None);
+                        idx
+                    })
+            });
+            let idx = tree.push(NodeKind::ResourceGroup, scope.clone(), rg.name.to_string());
+            resource_group_nodes.insert(scope.to_lowercase(), idx);
+            tree.attach(idx, parent);
+        }
+
+        // Resources whose group has not loaded are left out rather than shown as roots
+        for resource in resources {
+            let Some(parent) = resource
+                .resource_group_id()
+                .and_then(|id| resource_group_nodes.get(&id.to_lowercase()))
+                .copied()
+            else {
+                continue;
+            };
+            let idx = tree.push(
+                NodeKind::Resource,
+                resource.id.clone(),
+                format!("{} [{}]", resource.name, resource.short_kind()),
+            );
+            tree.attach(idx, Some(parent));
+        }
+
+        for node in tree.nodes.iter_mut() {
+            let scope = node.scope.to_lowercase();
+            node.assignment_count = counts.get(&scope).copied().unwrap_or_default();
+            node.deny_count = deny_counts.get(&scope).copied().unwrap_or_default();
+        }
+        tree.sort();
+        tree
+    }
+
+    pub fn node(&self, idx: usize) -> &ScopeNode {
+        &self.nodes[idx]
+    }
+
+    /// Whether the children of `idx` are shown.
+    ///
+    /// Resource groups start collapsed since they can hold many resources; everything else
+    /// starts expanded. `toggled` holds the scopes the user has flipped.
+    pub fn is_expanded(&self, idx: usize, toggled: &HashSet<String>) -> bool {
+        let node = &self.nodes[idx];
+        (node.kind != NodeKind::ResourceGroup) != toggled.contains(&node.scope)
+    }
+
+    /// Depth-first rows, skipping the descendants of collapsed scopes.
+    pub fn visible_rows(&self, toggled: &HashSet<String>) -> Vec<VisibleRow> {
+        let mut rows = Vec::new();
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|&node| VisibleRow { node, depth: 0 })
+            .collect::<Vec<_>>();
+        while let Some(row) = stack.pop() {
+            rows.push(row);
+            let node = &self.nodes[row.node];
+            if self.is_expanded(row.node, toggled) {
+                stack.extend(node.children.iter().rev().map(|&child| VisibleRow {
+                    node: child,
+                    depth: row.depth + 1,
+                }));
+            }
+        }
+        rows
+    }
+
+    fn push(&mut self, kind: NodeKind, scope: String, label: String) -> usize {
+        self.nodes.push(ScopeNode {
+            kind,
+            scope,
+            label,
+            parent: None,
+            children: Vec::new(),
+            assignment_count: 0,
+            deny_count: 0,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn attach(&mut self, idx: usize, parent: Option<usize>) {
+        match parent {
+            Some(parent) => {
+                self.nodes[idx].parent = Some(parent);
+                self.nodes[parent].children.push(idx);
+            }
+            None => self.roots.push(idx),
+        }
+    }
+
+    /// Order siblings management groups first, then subscriptions, then resource groups, by name.
+    fn sort(&mut self) {
+        let keys = self
+            .nodes
+            .iter()
+            .map(|node| (node.kind, node.label.to_lowercase()))
+            .collect::<Vec<_>>();
+        self.roots.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        for node in self.nodes.iter_mut() {
+            node.children.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        }
+    }
+}
+
+/// The subscription id segment of a scope like `/subscriptions/<id>/resourceGroups/<name>`.
+pub fn subscription_id_of(scope: &str) -> Option<&str> {
+    let mut segments = scope.split('/').filter(|x| !x.is_empty());
+    match segments.next() {
+        Some(first) if first.eq_ignore_ascii_case("subscriptions") => segments.next(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::SyntheticTenantOptions;
+    use crate::synthetic::generate;
+
+    #[test]
+    fn every_generated_scope_is_placed() {
+        let tenant = generate(&SyntheticTenantOptions::small(5)).unwrap();
+        let tree = ScopeTree::build(
+            Some(&tenant.hierarchy()),
+            &tenant.resource_groups,
+            &tenant.resources,
+            Some(&tenant.rbac),
+            &tenant.deny_assignments,
+        );
+        assert_eq!(
+            tree.nodes.iter().map(|x| x.deny_count).sum::<usize>(),
+            tenant.deny_assignments.len()
+        );
+        let resource_groups = tree
+            .nodes
+            .iter()
+            .filter(|x| x.kind == NodeKind::ResourceGroup)
+            .map(|x| x.scope.clone())
+            .collect();
+        let rows = tree.visible_rows(&resource_groups);
+        assert_eq!(rows.len(), tree.nodes.len());
+        assert_eq!(
+            tree.nodes.iter().map(|x| x.assignment_count).sum::<usize>(),
+            tenant.rbac.role_assignments.len()
+        );
+        // Only the tenant root group has no parent
+        assert_eq!(tree.roots.len(), 1);
+    }
+
+    #[test]
+    fn collapsed_scopes_hide_descendants() {
+        let tenant = generate(&SyntheticTenantOptions::small(5)).unwrap();
+        let tree = ScopeTree::build(
+            Some(&tenant.hierarchy()),
+            &tenant.resource_groups,
+            &tenant.resources,
+            None,
+            &[],
+        );
+        let root = tree.node(tree.roots[0]).scope.clone();
+        let rows = tree.visible_rows(&HashSet::from([root]));
+        assert_eq!(
+            rows,
+            [VisibleRow {
+                node: tree.roots[0],
+                depth: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn infers_subscriptions_without_hierarchy() {
+        let tenant = generate(&SyntheticTenantOptions::small(5)).unwrap();
+        let tree = ScopeTree::build(None, &tenant.resource_groups, &[], None, &[]);
+        assert_eq!(tree.roots.len(), tenant.subscriptions.len());
+        assert!(
+            tree.roots
+                .iter()
+                .all(|&idx| tree.node(idx).kind == NodeKind::Subscription)
+        );
+    }
+
+    #[test]
+    fn resource_groups_start_collapsed() {
+        let tenant = generate(&SyntheticTenantOptions::small(5)).unwrap();
+        let tree = ScopeTree::build(
+            Some(&tenant.hierarchy()),
+            &tenant.resource_groups,
+            &tenant.resources,
+            None,
+            &[],
+        );
+        let rows = tree.visible_rows(&HashSet::new());
+        assert!(
+            rows.iter()
+                .all(|row| tree.node(row.node).kind != NodeKind::Resource)
+        );
+
+        let rg = rows
+            .iter()
+            .find(|row| tree.node(row.node).kind == NodeKind::ResourceGroup)
+            .unwrap();
+        let expanded = tree.visible_rows(&HashSet::from([tree.node(rg.node).scope.clone()]));
+        assert_eq!(
+            expanded.len(),
+            rows.len() + tenant.resources.len() / tenant.resource_groups.len()
+        );
+    }
+}
@@ -0,0 +1,1102 @@
+use crate::cli::command::resource_group_tui::app::App;
+use crate::cli::command::resource_group_tui::app::AssignmentColumn;
+use crate::cli::command::resource_group_tui::app::AssignmentRow;
+use crate::cli::command::resource_group_tui::app::Focus;
+use crate::cli::command::resource_group_tui::bulk::BulkStatus;
+use crate::cli::command::resource_group_tui::keymap::Action;
+use crate::cli::command::resource_group_tui::tree::NodeKind;
+use chrono::DateTime;
+use chrono::Utc;
+use cloud_terrastodon_azure::prelude::Scope;
+use cloud_terrastodon_command::app_work::Loadable;
+use itertools::Itertools;
+use ratatui::layout::Constraint;
+use ratatui::layout::Layout;
+use ratatui::layout::Margin;
+use ratatui::prelude::*;
+use ratatui::widgets::Block;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Cell;
+use ratatui::widgets::Clear;
+use ratatui::widgets::List;
+use ratatui::widgets::ListItem;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Row;
+use ratatui::widgets::Table;
+use ratatui::widgets::Wrap;
+
+pub fn render(frame: &mut Frame, app: &mut App) {
+    app.refresh_tree();
+    let area = frame.area();
+    let area = if app.marked.is_empty() && app.data.bulk_results.is_empty() {
+        area
+    } else {
+        let [main, tray] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(8)]).areas(area);
+        render_tray(frame, tray, app);
+        main
+    };
+    let area = if app.show_logs {
+        let [main, logs] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(10)]).areas(area);
+        render_logs(frame, logs, app);
+        main
+    } else {
+        area
+    };
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(area);
+    render_scopes(frame, left, app);
+    render_role_assignments(frame, right, app);
+    if app.show_help {
+        render_help(frame, app);
+    }
+}
+
+/// The most recent log lines that fit, newest at the bottom.
+fn render_logs(frame: &mut Frame, area: Rect, app: &App) {
+    let title = match &app.log_path {
+        Some(path) => format!("Logs ({})", path.display()),
+        None => "Logs".to_string(),
+    };
+    let lines = app.logs.tail(area.height.saturating_sub(2) as usize);
+    let items = if lines.is_empty() {
+        vec![ListItem::new("No log lines yet")]
+    } else {
+        lines.into_iter().map(ListItem::new).collect()
+    };
+    frame.render_widget(
+        List::new(items).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+/// Every action and its keys, generated from the keymap.
+fn render_help(frame: &mut Frame, app: &App) {
+    let rows = app
+        .keymap
+        .bindings()
+        .iter()
+        .map(|(action, keys)| {
+            Row::new([
+                Cell::from(keys.iter().map(|x| x.to_string()).join(", "))
+                    .style(Style::default().fg(app.theme.accent)),
+                Cell::from(action.description()),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let area = frame.area();
+    let width = area.width.min(80);
+    let height = area.height.min(rows.len() as u16 + 2);
+    let area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Table::new(rows, [Constraint::Length(16), Constraint::Fill(1)]).block(
+            Block::default()
+                .title("Keys (any key to close)")
+                .borders(Borders::ALL),
+        ),
+        area,
+    );
+}
+
+/// Marked assignments on the left, outcomes of bulk actions on the right.
+fn render_tray(frame: &mut Frame, area: Rect, app: &App) {
+    let [marked, results] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
+    let keymap = &app.keymap;
+    let title = if app.confirm_delete {
+        Line::styled(
+            format!(
+                "Press {} again to delete {} assignments",
+                keymap.hint(Action::Delete),
+                app.marked_rows().len()
+            ),
+            Style::default()
+                .fg(app.theme.danger)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Line::from(format!(
+            "Marked ({}) {} delete · {} export plan · {} copy ids · {} clear",
+            app.marked.len(),
+            keymap.hint(Action::Delete),
+            keymap.hint(Action::ExportPlan),
+            keymap.hint(Action::CopyIds),
+            keymap.hint(Action::ClearMarks),
+        ))
+    };
+    let items = app
+        .marked_rows()
+        .into_iter()
+        .map(|(ra, rd)| ListItem::new(app.assignment_label(ra, rd)))
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        List::new(items).block(Block::default().title(title).borders(Borders::ALL)),
+        marked,
+    );
+
+    // Newest last, showing as many as fit
+    let visible = results.height.saturating_sub(2) as usize;
+    let skip = app.data.bulk_results.len().saturating_sub(visible);
+    let items = app.data.bulk_results[skip..]
+        .iter()
+        .map(|result| {
+            let (status, style) = match &result.status {
+                BulkStatus::Pending => (
+                    "pending".to_string(),
+                    Style::default().fg(app.theme.warning),
+                ),
+                BulkStatus::Done(message) => {
+                    (message.clone(), Style::default().fg(app.theme.success))
+                }
+                BulkStatus::Failed(error) => (error.clone(), Style::default().fg(app.theme.danger)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{} {}: ", result.action, result.label)),
+                Span::styled(status, style),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        List::new(items).block(Block::default().title("Results").borders(Borders::ALL)),
+        results,
+    );
+}
+
+fn render_scopes(frame: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = if app.rows.is_empty() {
+        match &app.data.resource_groups {
+            Loadable::Loaded { .. } => vec![ListItem::new("No scopes.")],
+            Loadable::Loading { .. } => vec![ListItem::new("Loading resource groups...")],
+            Loadable::Failed { error, .. } => vec![ListItem::new(format!("Error: {error}"))],
+            Loadable::NotLoaded => vec![ListItem::new("Not loaded")],
+        }
+    } else {
+        app.rows
+            .iter()
+            .map(|row| {
+                let node = app.tree.node(row.node);
+                let marker = if node.children.is_empty() {
+                    " "
+                } else if app.tree.is_expanded(row.node, &app.toggled) {
+                    "▾"
+                } else {
+                    "▸"
+                };
+                let style = match node.kind {
+                    NodeKind::ManagementGroup => Style::default().fg(app.theme.secondary),
+                    NodeKind::Subscription => Style::default().fg(app.theme.accent),
+                    NodeKind::ResourceGroup => Style::default(),
+                    NodeKind::Resource => Style::default().fg(app.theme.warning),
+                };
+                let mut spans = vec![
+                    Span::raw(format!("{}{marker} ", "  ".repeat(row.depth))),
+                    Span::styled(node.label.clone(), style),
+                    Span::raw(format!(" ({})", node.assignment_count)),
+                ];
+                if node.deny_count > 0 {
+                    spans.push(Span::styled(
+                        format!(" ⊘{}", node.deny_count),
+                        Style::default().fg(app.theme.danger),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect()
+    };
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .title("Scopes")
+                    .title_bottom(app.status_line())
+                    .borders(Borders::ALL),
+            )
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD)),
+        area,
+        &mut app.scope_list_state,
+    );
+    app.areas.scopes = area;
+    app.areas.scope_rows = area.inner(Margin::new(1, 1));
+}
+
+fn render_role_assignments(frame: &mut Frame, area: Rect, app: &mut App) {
+    app.areas.assignments = area;
+    app.areas.assignment_rows = Rect::default();
+    let area = match app.selected_assignment() {
+        Some(row) => {
+            let [list, details] =
+                Layout::vertical([Constraint::Percentage(30), Constraint::Percentage(70)])
+                    .areas(area);
+            render_assignment_details(frame, details, app, &row);
+            list
+        }
+        None => area,
+    };
+    let block = Block::default()
+        .title("Role Assignments")
+        .borders(Borders::ALL);
+    let message = match (&app.data.resource_groups, &app.data.rbac) {
+        (Loadable::Loaded { .. }, Loadable::Loaded { value: rbac, .. }) => {
+            match app.selected_index() {
+                Some(idx) => {
+                    let node = app.tree.node(idx);
+                    let table = assignment_table(app);
+                    let mut lines = Vec::new();
+                    let eligible = app.eligible_assignments_at(idx);
+                    if !eligible.is_empty() {
+                        lines.push(Line::styled(
+                            "Eligible (PIM):",
+                            Style::default().fg(app.theme.accent),
+                        ));
+                        lines.extend(eligible.iter().map(|(scope, x)| {
+                            let role = x
+                                .role_definition(rbac)
+                                .map(|rd| rd.display_name.as_str())
+                                .unwrap_or(x.role_definition_id.as_str());
+                            let mut line = format!(
+                                "  {role}: {}, {}",
+                                app.principal_display(&x.principal_id),
+                                x.window()
+                            );
+                            if scope.scope != node.scope {
+                                line.push_str(&format!(" (from {})", scope.label));
+                            }
+                            Line::styled(line, Style::default().fg(app.theme.accent))
+                        }));
+                    }
+                    let denies = app.deny_assignments_at(&node.scope);
+                    if !denies.is_empty() {
+                        lines.push(Line::styled(
+                            "Deny assignments:",
+                            Style::default().fg(app.theme.danger),
+                        ));
+                        lines.extend(denies.iter().map(|deny| {
+                            Line::from(format!("  {}: {}", deny.name, deny.actions.join(", ")))
+                        }));
+                    }
+                    match table {
+                        None if lines.is_empty() => "No role assignments.".to_string(),
+                        table => {
+                            let inner = block.inner(area);
+                            frame.render_widget(block, area);
+                            // Eligible and deny assignments get up to half the pane below the table
+                            let extra_height = match table {
+                                Some(_) => (lines.len() as u16).min(inner.height / 2),
+                                None => inner.height,
+                            };
+                            let [table_area, extra_area] = Layout::vertical([
+                                Constraint::Min(0),
+                                Constraint::Length(extra_height),
+                            ])
+                            .areas(inner);
+                            if let Some(table) = table {
+                                // Below the header row
+                                app.areas.assignment_rows = Rect {
+                                    y: table_area.y + 1,
+                                    height: table_area.height.saturating_sub(1),
+                                    ..table_area
+                                };
+                                frame.render_stateful_widget(
+                                    table,
+                                    table_area,
+                                    &mut app.assignment_table_state,
+                                );
+                            }
+                            frame.render_widget(
+                                Paragraph::new(lines).wrap(Wrap { trim: false }),
+                                extra_area,
+                            );
+                            return;
+                        }
+                    }
+                }
+                None => "No scope selected.".to_string(),
+            }
+        }
+        (Loadable::Loading { .. }, _) | (_, Loadable::Loading { .. }) => "Loading...".to_string(),
+        (Loadable::Failed { error, .. }, _) | (_, Loadable::Failed { error, .. }) => {
+            format!("Error: {error}")
+        }
+        _ => "Not loaded.".to_string(),
+    };
+    frame.render_widget(Paragraph::new(message).block(block), area);
+}
+
+/// The active role assignments at the selected scope, or `None` if there are none.
+fn assignment_table(app: &App) -> Option<Table<'static>> {
+    let rows = app.assignment_rows();
+    if rows.is_empty() {
+        return None;
+    }
+    let header = AssignmentColumn::ALL
+        .iter()
+        .map(|column| match app.assignment_sort {
+            Some(sort) if sort.column == *column => {
+                let arrow = if sort.descending { "▼" } else { "▲" };
+                format!("{} {arrow}", column.title())
+            }
+            _ => column.title().to_string(),
+        })
+        .collect::<Row>()
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = rows.iter().map(|row| {
+        let principal_id = &row.role_assignment.principal_id;
+        let mut role = Vec::new();
+        if app.is_marked(&row.role_assignment.id) {
+            role.push(Span::styled("✓ ", Style::default().fg(app.theme.success)));
+        }
+        role.push(Span::raw(row.role_definition.display_name.clone()));
+        if row.role_assignment.condition.is_some() {
+            role.push(Span::styled(
+                " (cond.)",
+                Style::default().fg(app.theme.warning),
+            ));
+        }
+        let origin_style = match row.inherited_from {
+            Some(_) => Style::default().add_modifier(Modifier::DIM),
+            None => Style::default(),
+        };
+        Row::new([
+            Cell::from(Line::from(role)),
+            Cell::from(app.principal_name(principal_id)),
+            Cell::from(
+                app.principal_kind(principal_id)
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::from(Span::styled(row.origin(), origin_style)),
+            Cell::from(row.created()),
+        ])
+    });
+    let highlight = match app.focus {
+        Focus::Assignments => Style::default().add_modifier(Modifier::REVERSED),
+        Focus::Scopes => Style::default(),
+    };
+    Some(
+        Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Fill(3),
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(highlight),
+    )
+}
+
+fn render_assignment_details(frame: &mut Frame, area: Rect, app: &App, row: &AssignmentRow) {
+    let ra = row.role_assignment;
+    let field = |name: &str, value: Option<String>| {
+        Line::from(vec![
+            Span::styled(
+                format!("{name}: "),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(value.unwrap_or_else(|| "-".to_string())),
+        ])
+    };
+    let when_and_who = |on: Option<DateTime<Utc>>, by: &Option<String>| match (
+        on.map(|x| x.format("%Y-%m-%d %H:%M UTC").to_string()),
+        by,
+    ) {
+        (Some(on), Some(by)) => Some(format!("{on} by {by}")),
+        (Some(on), None) => Some(on),
+        (None, Some(by)) => Some(format!("by {by}")),
+        (None, None) => None,
+    };
+    let lines = vec![
+        field("Role", Some(row.role_definition.display_name.clone())),
+        field("Principal", Some(app.principal_display(&ra.principal_id))),
+        field(
+            "Principal type",
+            app.principal_kind(&ra.principal_id).map(|x| x.to_string()),
+        ),
+        field("Object id", Some(ra.principal_id.to_string())),
+        field("Scope", Some(ra.scope.expanded_form())),
+        field("Assignment id", Some(ra.id.expanded_form())),
+        field(
+            "Role definition id",
+            Some(ra.role_definition_id.expanded_form()),
+        ),
+        field("Created", when_and_who(ra.created_on, &ra.created_by)),
+        field("Updated", when_and_who(ra.updated_on, &ra.updated_by)),
+        field("Description", ra.description.clone()),
+        field("Condition", ra.condition.clone()),
+        field("Condition version", ra.condition_version.clone()),
+        field(
+            "Delegated identity",
+            ra.delegated_managed_identity_resource_id.clone(),
+        ),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title("Details").borders(Borders::ALL)),
+        area,
+    );
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::cache::CacheMode;
+    use crate::cache::FetchKind;
+    use crate::cli::command::resource_group_tui::app::AppData;
+    use crate::cli::command::resource_group_tui::clipboard::ClipboardOutput;
+    use crate::cli::command::resource_group_tui::enqueue_cached;
+    use crate::cli::command::resource_group_tui::set_deny_assignments;
+    use crate::cli::command::resource_group_tui::set_eligible_assignments;
+    use crate::cli::command::resource_group_tui::set_hierarchy;
+    use crate::cli::command::resource_group_tui::set_rbac;
+    use crate::cli::command::resource_group_tui::set_resource_groups;
+    use crate::cli::command::resource_group_tui::set_resources;
+    use crate::cli::command::resource_group_tui::set_users;
+    use crate::fixtures;
+    use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+    use cloud_terrastodon_azure::prelude::Scope;
+    use cloud_terrastodon_command::app_work::LoadableWorkBuilder;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyCode;
+    use ratatui::crossterm::event::KeyEvent;
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui::crossterm::event::MouseButton;
+    use ratatui::crossterm::event::MouseEvent;
+    use ratatui::crossterm::event::MouseEventKind;
+    use std::time::Duration;
+
+    async fn load<T: Send + Sync + 'static>(
+        app: &mut App,
+        setter: fn(&mut AppData, Loadable<T>),
+        work: impl Future<Output = eyre::Result<T>> + Send + 'static,
+    ) {
+        LoadableWorkBuilder::<AppData, T>::new()
+            .description("test")
+            .setter(setter)
+            .work(work)
+            .build()
+            .unwrap()
+            .enqueue(&app.work, &mut app.data)
+            .unwrap();
+        // Let the spawned work finish and deliver its result
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            app.work.handle_messages(&mut app.data).unwrap();
+        }
+    }
+
+    fn draw(app: &mut App) -> Vec<String> {
+        draw_sized(app, 60, 6)
+    }
+
+    fn draw_sized(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| render(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    pub(in crate::cli::command::resource_group_tui) fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::from(code));
+    }
+
+    pub(in crate::cli::command::resource_group_tui) async fn populated_app() -> App {
+        let mut app = App::default();
+        // Keep copies off the real terminal, which test output capture does not cover
+        app.clipboard = ClipboardOutput::Buffer(Vec::new());
+        let alice = fixtures::user("Alice");
+        let rg_app = fixtures::resource_group("rg-app");
+        let rg_data = fixtures::resource_group("rg-data");
+        let reader = fixtures::built_in_role("Reader", &["*/read"]);
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let secrets_user = fixtures::built_in_role("Secrets User", &[]);
+        let vault = fixtures::resource(&rg_app, "kv-app", "Microsoft.KeyVault/vaults");
+        let rbac = fixtures::rbac(
+            [reader.clone(), owner.clone(), secrets_user.clone()],
+            [
+                fixtures::role_assignment(&rg_app.id, &reader, alice.id),
+                fixtures::role_assignment(&rg_app.id, &owner, alice.id),
+                fixtures::role_assignment_at(&vault.id, &secrets_user, alice.id),
+            ],
+        );
+        load(&mut app, set_hierarchy, async { Ok(fixtures::hierarchy()) }).await;
+        load(&mut app, set_users, async move { Ok(vec![alice]) }).await;
+        load(&mut app, set_rbac, async move { Ok(rbac) }).await;
+        load(&mut app, set_resource_groups, async move {
+            Ok(vec![rg_app, rg_data])
+        })
+        .await;
+        load(&mut app, set_resources, async move { Ok(vec![vault]) }).await;
+        app
+    }
+
+    #[tokio::test]
+    async fn renders_not_loaded() {
+        let mut app = App::default();
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│Not loaded            ││Not loaded.                       │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_loading() {
+        let mut app = App::default();
+        load(&mut app, set_resource_groups, std::future::pending()).await;
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│Loading resource group││Loading...                        │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_failed() {
+        let mut app = App::default();
+        load(&mut app, set_resource_groups, async {
+            Err(eyre::eyre!("boom"))
+        })
+        .await;
+        load(&mut app, set_rbac, async {
+            Ok(RoleDefinitionsAndAssignments::default())
+        })
+        .await;
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│Error: boom           ││Error: boom                       │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_a_cached_copy_when_its_refresh_fails() {
+        let dir = std::env::temp_dir().join(format!("ui-stale-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = Cache::at(dir.clone(), Duration::ZERO, CacheMode::Enabled);
+        cache.write(
+            FetchKind::ResourceGroups,
+            &vec![fixtures::resource_group("rg-app")],
+        );
+        let mut app = App::default();
+        enqueue_cached(
+            &mut app,
+            &cache,
+            FetchKind::ResourceGroups,
+            set_resource_groups,
+            async { Err(eyre::eyre!("offline")) },
+        )
+        .unwrap();
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            app.work.handle_messages(&mut app.data).unwrap();
+        }
+
+        let Loadable::Loaded { value, .. } = &app.data.resource_groups else {
+            panic!(
+                "expected the cached copy, got {:?}",
+                app.data.resource_groups
+            );
+        };
+        assert_eq!(value.len(), 1);
+        assert_eq!(app.data.stale.len(), 1);
+        assert_eq!(app.data.stale[0].kind, FetchKind::ResourceGroups);
+        let status = app.status_line();
+        assert!(status.starts_with("showing cached resource groups from "));
+        assert!(status.ends_with(" ago; refresh failed"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn renders_empty_selection() {
+        let mut app = populated_app().await;
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│  ▾ Tenant Root Group ││No role assignments.              │",
+                "│    ▾ sub-demo (0)    ││                                  │",
+                "│      ▸ rg-app (2)    ││                                  │",
+                "│>       rg-data (0)   ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_populated() {
+        let mut app = populated_app().await;
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│▾ Tenant Root Group (0││No scope selected.                │",
+                "│  ▾ sub-demo (0)      ││                                  │",
+                "│    ▸ rg-app (2)      ││                                  │",
+                "│      rg-data (0)     ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(
+            draw_sized(&mut app, 100, 6),
+            [
+                "┌Scopes────────────────────────────────┐┌Role Assignments──────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)             ││Role         Principal    Type     Origin       Created   │",
+                "│    ▾ sub-demo (0)                    ││Reader       Alice        User     direct       -         │",
+                "│>     ▸ rg-app (2)                    ││Owner        Alice        User     direct       -         │",
+                "│        rg-data (0)                   ││                                                          │",
+                "└──────────────────────────────────────┘└──────────────────────────────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn collapses_and_expands_scopes() {
+        let mut app = populated_app().await;
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Left);
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│  ▾ Tenant Root Group ││No role assignments.              │",
+                "│>   ▸ sub-demo (0)    ││                                  │",
+                "│                      ││                                  │",
+                "│                      ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+
+        // A second Left moves to the parent, Enter toggles it
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.rows.len(), 1);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.rows.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn shows_resource_and_inherited_assignments() {
+        let mut app = populated_app().await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(
+            draw_sized(&mut app, 100, 6),
+            [
+                "┌Scopes────────────────────────────────┐┌Role Assignments──────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)             ││Role         Principal    Type     Origin       Created   │",
+                "│    ▾ sub-demo (0)                    ││Secrets User Alice        User     direct       -         │",
+                "│      ▾ rg-app (2)                    ││Reader       Alice        User     from rg-app  -         │",
+                "│>         kv-app [vaults] (1)         ││Owner        Alice        User     from rg-app  -         │",
+                "└──────────────────────────────────────┘└──────────────────────────────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sorts_assignments_by_column() {
+        let mut app = populated_app().await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            app.selected_assignment()
+                .map(|row| row.role_definition.display_name.as_str()),
+            Some("Owner")
+        );
+
+        // Sorting by role moves Owner first and keeps it selected
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(app.assignment_table_state.selected(), Some(0));
+
+        // Pressing the same column again reverses the order
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(app.assignment_table_state.selected(), Some(1));
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(
+            draw_sized(&mut app, 100, 6),
+            [
+                "┌Scopes────────────────────────────────┐┌Role Assignments──────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)             ││Role ▼       Principal    Type     Origin       Created   │",
+                "│    ▾ sub-demo (0)                    ││Reader       Alice        User     direct       -         │",
+                "│>     ▸ rg-app (2)                    ││Owner        Alice        User     direct       -         │",
+                "│        rg-data (0)                   ││                                                          │",
+                "└──────────────────────────────────────┘└──────────────────────────────────────────────────────────┘",
+            ]
+        );
+
+        // Sort keys also work from the scopes pane
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(
+            draw_sized(&mut app, 100, 6),
+            [
+                "┌Scopes────────────────────────────────┐┌Role Assignments──────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)             ││Role ▲       Principal    Type     Origin       Created   │",
+                "│    ▾ sub-demo (0)                    ││Owner        Alice        User     direct       -         │",
+                "│>     ▸ rg-app (2)                    ││Reader       Alice        User     direct       -         │",
+                "│        rg-data (0)                   ││                                                          │",
+                "└──────────────────────────────────────┘└──────────────────────────────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn marks_assignments_across_scopes() {
+        let mut app = populated_app().await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.marked.len(), 2);
+        assert_eq!(
+            draw_sized(&mut app, 100, 14),
+            [
+                "┌Scopes────────────────────────────────┐┌Role Assignments──────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)             ││Role         Principal    Type     Origin       Created   │",
+                "│    ▾ sub-demo (0)                    ││✓ Secrets Us Alice        User     direct       -         │",
+                "│      ▾ rg-app (2)                    ││✓ Reader     Alice        User     from rg-app  -         │",
+                "│>         kv-app [vaults] (1)         ││Owner        Alice        User     from rg-app  -         │",
+                "└──────────────────────────────────────┘└──────────────────────────────────────────────────────────┘",
+                "┌Marked (2) d delete · e export plan · y copy ids┐┌Results─────────────────────────────────────────┐",
+                "│Reader · (User) Alice @ rg-app                  ││                                                │",
+                "│Secrets User · (User) Alice @ kv-app            ││                                                │",
+                "│                                                ││                                                │",
+                "│                                                ││                                                │",
+                "│                                                ││                                                │",
+                "│                                                ││                                                │",
+                "└────────────────────────────────────────────────┘└────────────────────────────────────────────────┘",
+            ]
+        );
+
+        // Delete asks for confirmation, and any other key cancels it
+        press(&mut app, KeyCode::Char('d'));
+        assert!(app.confirm_delete);
+        press(&mut app, KeyCode::Down);
+        assert!(!app.confirm_delete);
+        assert!(app.data.bulk_results.is_empty());
+
+        press(&mut app, KeyCode::Char('u'));
+        assert!(app.marked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn builds_create_command_for_selected_assignment() {
+        let mut app = populated_app().await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        let row = app.selected_assignment().unwrap();
+        assert_eq!(
+            app.create_command(&row),
+            "az role assignment create \
+             --assignee-object-id afea254c-f343-41c1-8afe-254cf34341c1 \
+             --assignee-principal-type User \
+             --role /providers/Microsoft.Authorization/roleDefinitions/de849dc6-bc2a-4b1d-8de8-9dc6bc2a1b1d \
+             --scope /subscriptions/00000000-0000-0000-0000-00000000a001/resourceGroups/rg-app"
+        );
+    }
+
+    #[tokio::test]
+    async fn shows_deny_assignments() {
+        let mut app = populated_app().await;
+        let rg_data = fixtures::resource_group("rg-data");
+        let deny = fixtures::deny_assignment(&rg_data.id.expanded_form(), &["*/delete"], &[]);
+        load(
+            &mut app,
+            set_deny_assignments,
+            async move { Ok(vec![deny]) },
+        )
+        .await;
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│  ▾ Tenant Root Group ││Deny assignments:                 │",
+                "│    ▾ sub-demo (0)    ││  deny-fixture: */delete          │",
+                "│      ▸ rg-app (2)    ││                                  │",
+                "│>       rg-data (0) ⊘1││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn shows_eligible_assignments() {
+        let mut app = populated_app().await;
+        let bob = fixtures::user("Bob");
+        let owner = fixtures::built_in_role("Owner", &["*"]);
+        let eligible = fixtures::eligible_assignment(
+            &fixtures::resource_group("rg-data").id.expanded_form(),
+            &owner,
+            bob.id,
+        );
+        load(&mut app, set_users, async move { Ok(vec![bob]) }).await;
+        load(&mut app, set_eligible_assignments, async move {
+            Ok(vec![eligible])
+        })
+        .await;
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(
+            draw(&mut app),
+            [
+                "┌Scopes────────────────┐┌Role Assignments──────────────────┐",
+                "│  ▾ Tenant Root Group ││Eligible (PIM):                   │",
+                "│    ▾ sub-demo (0)    ││  Owner: (User) Bob, 2026-01-01 → │",
+                "│      ▸ rg-app (2)    ││2026-06-30                        │",
+                "│>       rg-data (0)   ││                                  │",
+                "└──────────────────────┘└──────────────────────────────────┘",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_conditional_assignments_and_shows_details() {
+        let mut app = populated_app().await;
+        let alice = fixtures::user("Alice");
+        let rg_app = fixtures::resource_group("rg-app");
+        let reader = fixtures::built_in_role("Storage Reader", &["*/read"]);
+        let mut conditional = fixtures::role_assignment(&rg_app.id, &reader, alice.id);
+        conditional.condition = Some("@Resource[tag] == 'x'".to_string());
+        conditional.condition_version = Some("2.0".to_string());
+        conditional.created_on = "2025-03-04T05:06:00Z".parse().ok();
+        conditional.created_by = Some("bob@example.com".to_string());
+        let rbac = fixtures::rbac([reader], [conditional]);
+        load(&mut app, set_rbac, async move { Ok(rbac) }).await;
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(
+            draw_sized(&mut app, 160, 27),
+            [
+                "┌Scopes────────────────────────────────────────────────────────┐┌Role Assignments──────────────────────────────────────────────────────────────────────────────┐",
+                "│  ▾ Tenant Root Group (0)                                     ││Role                   Principal              Type           Origin                 Created   │",
+                "│    ▾ sub-demo (0)                                            ││Storage Reader (cond.) Alice                  User           direct                 2025-03-04│",
+                "│>     ▸ rg-app (1)                                            ││                                                                                              │",
+                "│        rg-data (0)                                           ││                                                                                              │",
+                "│                                                              ││                                                                                              │",
+                "│                                                              ││                                                                                              │",
+                "│                                                              │└──────────────────────────────────────────────────────────────────────────────────────────────┘",
+                "│                                                              │┌Details───────────────────────────────────────────────────────────────────────────────────────┐",
+                "│                                                              ││Role: Storage Reader                                                                          │",
+                "│                                                              ││Principal: (User) Alice                                                                       │",
+                "│                                                              ││Principal type: User                                                                          │",
+                "│                                                              ││Object id: afea254c-f343-41c1-8afe-254cf34341c1                                               │",
+                "│                                                              ││Scope: /subscriptions/00000000-0000-0000-0000-00000000a001/resourceGroups/rg-app              │",
+                "│                                                              ││Assignment id:                                                                                │",
+                "│                                                              ││/subscriptions/00000000-0000-0000-0000-00000000a001/resourceGroups/rg-app/providers/Microsoft.│",
+                "│                                                              ││Authorization/roleAssignments/85579dff-ed98-4814-8855-9dffed989814                            │",
+                "│                                                              ││Role definition id:                                                                           │",
+                "│                                                              ││/providers/Microsoft.Authorization/roleDefinitions/1f6619df-af7a-4296-81f6-19dfaf7a8296       │",
+                "│                                                              ││Created: 2025-03-04 05:06 UTC by bob@example.com                                              │",
+                "│                                                              ││Updated: -                                                                                    │",
+                "│                                                              ││Description: -                                                                                │",
+                "│                                                              ││Condition: @Resource[tag] == 'x'                                                              │",
+                "│                                                              ││Condition version: 2.0                                                                        │",
+                "│                                                              ││Delegated identity: -                                                                         │",
+                "│                                                              ││                                                                                              │",
+                "└──────────────────────────────────────────────────────────────┘└──────────────────────────────────────────────────────────────────────────────────────────────┘",
+            ]
+        );
+
+        let row = app.selected_assignment().unwrap();
+        assert!(app.create_command(&row).ends_with(
+            "--role /providers/Microsoft.Authorization/roleDefinitions/1f6619df-af7a-4296-81f6-19dfaf7a8296 \
+             --scope /subscriptions/00000000-0000-0000-0000-00000000a001/resourceGroups/rg-app \
+             --condition '@Resource[tag] == '\\''x'\\''' --condition-version 2.0"
+        ));
+
+        press(&mut app, KeyCode::Tab);
+        assert!(app.selected_assignment().is_none());
+    }
+
+    #[tokio::test]
+    async fn home_and_end_jump_to_first_and_last_scope() {
+        let mut app = populated_app().await;
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.selected_node().unwrap().label, "rg-data");
+        press(&mut app, KeyCode::Home);
+        assert_eq!(app.selected_node().unwrap().label, "Tenant Root Group");
+        press(&mut app, KeyCode::Char('G'));
+        assert_eq!(app.selected_node().unwrap().label, "rg-data");
+    }
+
+    #[tokio::test]
+    async fn shows_help_from_keymap() {
+        let mut app = App::default();
+        press(&mut app, KeyCode::Char('?'));
+        assert_eq!(
+            draw_sized(&mut app, 70, 30),
+            [
+                "┌Keys (any key to close)─────────────────────────────────────────────┐",
+                "│q, Esc           Quit                                               │",
+                "│?                Show or hide this help                             │",
+                "│L                Show or hide recent log lines                      │",
+                "│Tab              Switch between scopes and assignments              │",
+                "│Up, k            Move up                                            │",
+                "│Down, j          Move down                                          │",
+                "│PageUp, Ctrl+u   Move up a page                                     │",
+                "│PageDown, Ctrl+d Move down a page                                   │",
+                "│Home, g          Jump to the first row                              │",
+                "│End, G           Jump to the last row                               │",
+                "│Right, l         Expand the scope, or move into it                  │",
+                "│Left, h          Collapse the scope, or move to its parent          │",
+                "│Enter            Expand or collapse the scope                       │",
+                "│1                Sort assignments by role                           │",
+                "│2                Sort assignments by principal                      │",
+                "│3                Sort assignments by principal type                 │",
+                "│4                Sort assignments by origin                         │",
+                "│5                Sort assignments by created date                   │",
+                "│Space            Mark or unmark the assignment                      │",
+                "│u                Clear all marks                                    │",
+                "│d                Delete marked assignments (press twice)            │",
+                "│e                Export marked assignments to a plan file           │",
+                "│y                Copy marked assignment ids                         │",
+                "│c                Copy the scope id                                  │",
+                "│a                Copy the assignment id                             │",
+                "│p                Copy the principal object id                       │",
+                "│C                Copy an az command that recreates the assignment   │",
+                "└────────────────────────────────────────────────────────────────────┘",
+                "└──────────────────────────┘└────────────────────────────────────────┘",
+            ]
+        );
+
+        // Any key closes it without acting
+        press(&mut app, KeyCode::Char('q'));
+        assert!(!app.show_help);
+        assert!(!app.should_quit);
+    }
+
+    #[tokio::test]
+    async fn shows_recent_logs_in_a_pane() {
+        let mut app = App::default();
+        app.log_path = Some("tui.log".into());
+        for i in 0..12 {
+            app.logs.push(format!(" INFO fetched page {i}"));
+        }
+        press(&mut app, KeyCode::Char('L'));
+        let lines = draw_sized(&mut app, 60, 14);
+        assert_eq!(
+            lines[4..],
+            [
+                "┌Logs (tui.log)────────────────────────────────────────────┐",
+                "│ INFO fetched page 4                                      │",
+                "│ INFO fetched page 5                                      │",
+                "│ INFO fetched page 6                                      │",
+                "│ INFO fetched page 7                                      │",
+                "│ INFO fetched page 8                                      │",
+                "│ INFO fetched page 9                                      │",
+                "│ INFO fetched page 10                                     │",
+                "│ INFO fetched page 11                                     │",
+                "└──────────────────────────────────────────────────────────┘",
+            ]
+        );
+
+        press(&mut app, KeyCode::Char('L'));
+        assert!(
+            !draw_sized(&mut app, 60, 14)
+                .iter()
+                .any(|x| x.contains("Logs"))
+        );
+    }
+
+    #[tokio::test]
+    async fn selects_and_scrolls_with_the_mouse() {
+        let mut app = populated_app().await;
+        let mouse = |kind, column, row| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        draw_sized(&mut app, 100, 6);
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 5, 3));
+        assert_eq!(app.selected_node().unwrap().label, "rg-app");
+
+        // Second row of the assignments table, below its header
+        draw_sized(&mut app, 100, 6);
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 50, 3));
+        assert_eq!(app.focus, Focus::Assignments);
+        let role = |app: &App| {
+            app.selected_assignment()
+                .map(|row| row.role_definition.display_name.clone())
+        };
+        assert_eq!(role(&app).as_deref(), Some("Owner"));
+        app.handle_mouse(mouse(MouseEventKind::ScrollUp, 50, 3));
+        assert_eq!(role(&app).as_deref(), Some("Reader"));
+
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 5, 4));
+        assert_eq!(app.focus, Focus::Scopes);
+        assert_eq!(app.selected_node().unwrap().label, "rg-data");
+    }
+
+    #[tokio::test]
+    async fn quits_on_escape() {
+        let mut app = App::default();
+        press(&mut app, KeyCode::Esc);
+        assert!(app.should_quit);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::cache::cache_root;
+use crate::cli::Cli;
+use crate::cli::arbitrary_args::optional_path;
+use crate::cli::command::Command;
+use crate::cli::command::role_assignment::RoleAssignmentArgs;
+use crate::cli::command::role_assignment::RoleAssignmentCommand;
+use crate::cli::command::role_assignment::WriteOutcome;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::relaunch;
+use crate::cli::to_args::Invocable;
+use crate::cli::to_args::ToArgs;
+use arbitrary::Arbitrary;
+use clap::Args;
+use eyre::Context;
+use eyre::OptionExt;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+/// Which Azure CLI login creates and deletes role assignments
+#[derive(Args, Clone, Default, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct WriteIdentityArgs {
+    /// Azure CLI config dir of a separate `az login` to create and delete role assignments with
+    #[clap(long, value_name = "DIR")]
+    #[arbitrary(with = optional_path)]
+    pub write_config_dir: Option<PathBuf>,
+
+    /// Create and delete role assignments as the service principal in AZURE_CLIENT_ID,
+    /// AZURE_CLIENT_SECRET and AZURE_TENANT_ID
+    #[clap(long)]
+    pub write_as_service_principal: bool,
+}
+
+/// The login that performs writes.
+///
+/// Anything other than the current login runs the write in a relaunched copy of this program
+/// with its own `AZURE_CONFIG_DIR`, so the caller never holds the privileged credentials.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum WriteIdentity {
+    #[default]
+    Current,
+    Relaunched {
+        config_dir: PathBuf,
+        /// Log in as the service principal from the environment before writing
+        service_principal: bool,
+        /// Passed on to the relaunched process so it logs and caches like this one
+        global_args: GlobalArgs,
+    },
+}
+
+impl std::fmt::Display for WriteIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteIdentity::Current => f.write_str("current login"),
+            WriteIdentity::Relaunched {
+                service_principal: true,
+                ..
+            } => f.write_str("service principal"),
+            WriteIdentity::Relaunched { config_dir, .. } => {
+                write!(f, "login in {}", config_dir.display())
+            }
+        }
+    }
+}
+
+impl WriteIdentityArgs {
+    pub fn identity(&self, global_args: &GlobalArgs) -> eyre::Result<WriteIdentity> {
+        let config_dir = match (&self.write_config_dir, self.write_as_service_principal) {
+            (None, false) => return Ok(WriteIdentity::Current),
+            (Some(dir), _) => dir.clone(),
+            // Keep the service principal's tokens away from the user's own login
+            (None, true) => cache_root()?.join("az-service-principal"),
+        };
+        Ok(WriteIdentity::Relaunched {
+            config_dir,
+            service_principal: self.write_as_service_principal,
+            global_args: GlobalArgs {
+                // The relaunched process has no console of its own to attach to
+                console_pid: None,
+                ..global_args.clone()
+            },
+        })
+    }
+}
+
+/// Held while a relaunched write runs.
+///
+/// Relaunched writes share a config dir and may each `az login` into it, so running them at the
+/// same time would race on its token cache.
+static RELAUNCH_LOCK: Mutex<()> = Mutex::const_new(());
+
+impl WriteIdentity {
+    /// Perform `command` as this identity, relaunching if it is not the current login.
+    ///
+    /// Relaunched writes run one at a time, however many are started together.
+    pub async fn run(&self, command: RoleAssignmentCommand) -> eyre::Result<WriteOutcome> {
+        let WriteIdentity::Relaunched {
+            config_dir,
+            service_principal,
+            global_args,
+        } = self
+        else {
+            return command.execute().await;
+        };
+        let _lock = RELAUNCH_LOCK.lock().await;
+        let invocation = WriteInvocation {
+            cli: Cli {
+                global_args: global_args.clone(),
+                command: Command::RoleAssignment(RoleAssignmentArgs {
+                    identity: WriteIdentityArgs::default(),
+                    login_service_principal: *service_principal,
+                    command,
+                }),
+            },
+            config_dir: config_dir.clone(),
+        };
+        let output = tokio::task::spawn_blocking(move || relaunch::output(&invocation)).await??;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            eyre::bail!(
+                "Write as {self} failed with {}: {}",
+                output.status,
+                stderr.trim()
+            );
+        }
+        parse_outcome(&output.stdout)
+            .wrap_err_with(|| format!("Reading the result of the write as {self}"))
+    }
+}
+
+/// A `role-assignment` command for a relaunched copy of this program.
+struct WriteInvocation {
+    cli: Cli,
+    config_dir: PathBuf,
+}
+
+impl Invocable for WriteInvocation {
+    fn executable(&self) -> PathBuf {
+        std::env::current_exe().expect("Failed to get current executable path")
+    }
+
+    fn args(&self) -> Vec<OsString> {
+        self.cli.to_args()
+    }
+
+    fn envs(&self) -> Vec<(OsString, OsString)> {
+        vec![("AZURE_CONFIG_DIR".into(), self.config_dir.clone().into())]
+    }
+}
+
+/// The outcome a relaunched write printed, which is its last line of output.
+///
+/// Only that line is read so anything else the child prints cannot break the result.
+fn parse_outcome(stdout: &[u8]) -> eyre::Result<WriteOutcome> {
+    let stdout = String::from_utf8_lossy(stdout);
+    let line = stdout
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .ok_or_eyre("The relaunched write printed nothing")?;
+    Ok(serde_json::from_str(line)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_write_identity_from_args() {
+        let global_args = GlobalArgs {
+            debug: true,
+            log_file: Some("/tmp/ratui.log".into()),
+            console_pid: Some(42),
+            ..Default::default()
+        };
+        assert_eq!(
+            WriteIdentityArgs::default().identity(&global_args).unwrap(),
+            WriteIdentity::Current
+        );
+        let args = WriteIdentityArgs {
+            write_config_dir: Some("/tmp/az-admin".into()),
+            write_as_service_principal: true,
+        };
+        assert_eq!(
+            args.identity(&global_args).unwrap(),
+            WriteIdentity::Relaunched {
+                config_dir: "/tmp/az-admin".into(),
+                service_principal: true,
+                global_args: GlobalArgs {
+                    console_pid: None,
+                    ..global_args.clone()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn parses_outcome_after_log_lines() {
+        let stdout = b"2025-01-01T00:00:00Z  INFO logged in\n{\"outcome\":\"deleted\",\"role_assignment_id\":\"/x\"}\n\n";
+        assert_eq!(
+            parse_outcome(stdout).unwrap(),
+            WriteOutcome::Deleted {
+                role_assignment_id: "/x".into()
+            }
+        );
+        assert!(parse_outcome(b"").is_err());
+    }
+}
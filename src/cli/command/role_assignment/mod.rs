@@ -0,0 +1,199 @@
+pub mod identity;
+
+use crate::azure::login::login_service_principal;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::role_assignments::NewRoleAssignment;
+use crate::azure::role_assignments::create_role_assignment;
+use crate::azure::role_assignments::delete_role_assignment;
+use crate::cache::Cache;
+use crate::cli::arbitrary_args::optional_value;
+use crate::cli::arbitrary_args::value;
+use crate::cli::command::role_assignment::identity::WriteIdentityArgs;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::pick::ensure_can_prompt;
+use crate::cli::pick::pick_principal;
+use crate::cli::pick::pick_resource_group;
+use crate::cli::pick::pick_role;
+use crate::cli::to_args::ToArgs;
+use crate::config::Config;
+use arbitrary::Arbitrary;
+use clap::Args;
+use clap::Subcommand;
+use cloud_terrastodon_azure::prelude::Scope;
+use eyre::OptionExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::runtime::Builder;
+
+/// Create or delete role assignments, optionally as a separate Azure CLI login
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct RoleAssignmentArgs {
+    #[clap(flatten)]
+    pub identity: WriteIdentityArgs,
+
+    /// Log in as the service principal from the environment first; passed when relaunching
+    #[clap(long, hide = true)]
+    pub login_service_principal: bool,
+
+    #[clap(subcommand)]
+    pub command: RoleAssignmentCommand,
+}
+
+#[derive(Subcommand, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub enum RoleAssignmentCommand {
+    /// Assign a role to a principal at a scope
+    Create(CreateRoleAssignmentArgs),
+    /// Remove a role assignment
+    Delete(DeleteRoleAssignmentArgs),
+}
+
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct CreateRoleAssignmentArgs {
+    /// Object id of the user, group or service principal; picked interactively when omitted
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub assignee_object_id: Option<String>,
+
+    /// `User`, `ServicePrincipal` or `Group`
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub assignee_principal_type: Option<String>,
+
+    /// Role name or role definition id; picked interactively when omitted
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub role: Option<String>,
+
+    /// Scope to assign at; a resource group is picked interactively when omitted
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub scope: Option<String>,
+
+    /// ABAC condition limiting the assignment
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub condition: Option<String>,
+
+    #[clap(long)]
+    #[arbitrary(with = optional_value)]
+    pub condition_version: Option<String>,
+}
+
+#[derive(Args, Clone, Arbitrary, PartialEq, Debug, ToArgs)]
+pub struct DeleteRoleAssignmentArgs {
+    /// Full role assignment id
+    #[clap(long)]
+    #[arbitrary(with = value)]
+    pub id: String,
+}
+
+/// What a write did, printed as JSON so a relaunching parent can read it back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum WriteOutcome {
+    Created { role_assignment_id: String },
+    Deleted { role_assignment_id: String },
+}
+
+impl std::fmt::Display for WriteOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteOutcome::Created { role_assignment_id } => {
+                write!(f, "created {role_assignment_id}")
+            }
+            WriteOutcome::Deleted { role_assignment_id } => {
+                write!(f, "deleted {role_assignment_id}")
+            }
+        }
+    }
+}
+
+impl RoleAssignmentArgs {
+    pub fn invoke(self, global_args: &GlobalArgs, config: &Config) -> eyre::Result<()> {
+        let outcome = Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                if self.login_service_principal {
+                    login_service_principal().await?;
+                }
+                // Prompt before relaunching, since the relaunched process has no stdin
+                let command = self.command.prompt_for_missing(global_args, config).await?;
+                self.identity.identity(global_args)?.run(command).await
+            })?;
+        println!("{}", serde_json::to_string(&outcome)?);
+        Ok(())
+    }
+}
+
+impl RoleAssignmentCommand {
+    /// Fill in arguments left off the command line by asking the user to pick them.
+    pub async fn prompt_for_missing(
+        self,
+        global_args: &GlobalArgs,
+        config: &Config,
+    ) -> eyre::Result<Self> {
+        match self {
+            RoleAssignmentCommand::Create(mut args) => {
+                let missing = [
+                    ("--assignee-object-id", args.assignee_object_id.is_none()),
+                    ("--role", args.role.is_none()),
+                    ("--scope", args.scope.is_none()),
+                ]
+                .into_iter()
+                .filter_map(|(flag, missing)| missing.then_some(flag))
+                .collect::<Vec<_>>();
+                if missing.is_empty() {
+                    return Ok(RoleAssignmentCommand::Create(args));
+                }
+                ensure_can_prompt(&missing)?;
+                let cache = Cache::open(global_args, config).await?;
+                if args.assignee_object_id.is_none() {
+                    let principals = PrincipalDirectory::fetch(&cache).await?;
+                    let principal_id = pick_principal(&principals)?;
+                    args.assignee_object_id = Some(principal_id.to_string());
+                    if let Some(kind) = principals.kind(&principal_id) {
+                        args.assignee_principal_type
+                            .get_or_insert_with(|| kind.principal_type().to_owned());
+                    }
+                }
+                if args.role.is_none() {
+                    // The id, since a custom role can share a built-in role's name
+                    args.role = Some(pick_role(&cache).await?.id.expanded_form());
+                }
+                if args.scope.is_none() {
+                    let resource_group = pick_resource_group(&cache, config).await?;
+                    args.scope = Some(resource_group.id.expanded_form());
+                }
+                Ok(RoleAssignmentCommand::Create(args))
+            }
+            RoleAssignmentCommand::Delete(_) => Ok(self),
+        }
+    }
+
+    /// Perform the write with the Azure CLI login of this process.
+    pub async fn execute(self) -> eyre::Result<WriteOutcome> {
+        match self {
+            RoleAssignmentCommand::Create(args) => {
+                let role_assignment_id = create_role_assignment(&NewRoleAssignment {
+                    assignee_object_id: args
+                        .assignee_object_id
+                        .ok_or_eyre("--assignee-object-id is required")?,
+                    assignee_principal_type: args.assignee_principal_type,
+                    role: args.role.ok_or_eyre("--role is required")?,
+                    scope: args.scope.ok_or_eyre("--scope is required")?,
+                    condition: args.condition,
+                    condition_version: args.condition_version,
+                })
+                .await?;
+                Ok(WriteOutcome::Created { role_assignment_id })
+            }
+            RoleAssignmentCommand::Delete(args) => {
+                delete_role_assignment(&args.id).await?;
+                Ok(WriteOutcome::Deleted {
+                    role_assignment_id: args.id,
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Dynamic shell completion of subscription ids and resource group names.
+//!
+//! Completion runs on every keypress, so it never calls Azure: values come from the
+//! `--snapshot` already on the command line, otherwise from every tenant in the local cache.
+//! Any failure yields no candidates rather than an error, which the shell would print.
+
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::hierarchy::SubscriptionEntry;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::cache::cache_root;
+use crate::snapshot::TenantSnapshot;
+use clap_complete::engine::CompletionCandidate;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use itertools::Itertools;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The environment variable that asks the binary for completions instead of running a command.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Candidates for `--subscription`, described by the subscription's display name.
+pub fn subscription_candidates() -> Vec<CompletionCandidate> {
+    Known::load().subscription_candidates()
+}
+
+/// Candidates for `--resource-group`, described by the subscription each one is in.
+pub fn resource_group_candidates() -> Vec<CompletionCandidate> {
+    Known::load().resource_group_candidates()
+}
+
+/// Subscriptions and resource groups that can be listed without calling Azure.
+#[derive(Default, Debug)]
+struct Known {
+    subscriptions: Vec<SubscriptionEntry>,
+    resource_groups: Vec<ResourceGroup>,
+}
+
+impl Known {
+    fn load() -> Self {
+        match snapshot_arg(std::env::args_os()) {
+            Some(path) => match TenantSnapshot::load(&path) {
+                Ok(snapshot) => Self::from(snapshot),
+                Err(_) => Self::default(),
+            },
+            None => Self::from_cache().unwrap_or_default(),
+        }
+    }
+
+    fn from_cache() -> eyre::Result<Self> {
+        let mut rtn = Self::default();
+        for cache in Cache::tenants_in(&cache_root()?)? {
+            if let Some(entry) = cache.read::<ScopeHierarchy>(FetchKind::Hierarchy) {
+                rtn.subscriptions.extend(entry.value.subscriptions);
+            }
+            if let Some(entry) = cache.read::<Vec<ResourceGroup>>(FetchKind::ResourceGroups) {
+                rtn.resource_groups.extend(entry.value);
+            }
+        }
+        Ok(rtn)
+    }
+
+    fn subscription_name(&self, subscription_id: &str) -> Option<&str> {
+        self.subscriptions
+            .iter()
+            .find(|x| x.subscription_id.eq_ignore_ascii_case(subscription_id))
+            .map(|x| x.display_name.as_str())
+    }
+
+    fn subscription_candidates(&self) -> Vec<CompletionCandidate> {
+        // Subscriptions missing from the hierarchy still show up through their resource groups
+        let ids = self
+            .subscriptions
+            .iter()
+            .map(|x| x.subscription_id.clone())
+            .chain(
+                self.resource_groups
+                    .iter()
+                    .map(|x| x.subscription_id.to_string()),
+            );
+        ids.unique_by(|x| x.to_lowercase())
+            .sorted()
+            .map(|id| {
+                let help = self.subscription_name(&id).map(|x| x.to_owned().into());
+                CompletionCandidate::new(id).help(help)
+            })
+            .collect()
+    }
+
+    fn resource_group_candidates(&self) -> Vec<CompletionCandidate> {
+        self.resource_groups
+            .iter()
+            .unique_by(|x| x.name.to_lowercase())
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .map(|rg| {
+                let subscription_id = rg.subscription_id.to_string();
+                let help = self
+                    .subscription_name(&subscription_id)
+                    .unwrap_or(&subscription_id)
+                    .to_owned();
+                CompletionCandidate::new(&rg.name).help(Some(help.into()))
+            })
+            .collect()
+    }
+}
+
+impl From<TenantSnapshot> for Known {
+    fn from(snapshot: TenantSnapshot) -> Self {
+        Self {
+            subscriptions: snapshot.subscriptions,
+            resource_groups: snapshot.resource_groups,
+        }
+    }
+}
+
+/// The last `--snapshot` value on a command line, in either `--snapshot PATH` or
+/// `--snapshot=PATH` form.
+fn snapshot_arg(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut rtn = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--snapshot" {
+            rtn = args.next().map(PathBuf::from);
+        } else if let Some(path) = arg.to_str().and_then(|x| x.strip_prefix("--snapshot=")) {
+            rtn = Some(PathBuf::from(path));
+        }
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::SyntheticTenantOptions;
+    use crate::synthetic::generate;
+
+    fn values(candidates: &[CompletionCandidate]) -> Vec<String> {
+        candidates
+            .iter()
+            .map(|x| x.get_value().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_snapshot_on_the_command_line() {
+        let args = |args: &[&str]| snapshot_arg(args.iter().map(OsString::from));
+        assert_eq!(
+            args(&[
+                "bin",
+                "--",
+                "bin",
+                "list-resource-groups",
+                "--snapshot",
+                "a.json"
+            ]),
+            Some(PathBuf::from("a.json"))
+        );
+        assert_eq!(
+            args(&["bin", "--snapshot=b.json", "--resource-group", ""]),
+            Some(PathBuf::from("b.json"))
+        );
+        assert_eq!(args(&["bin", "list-resource-groups"]), None);
+    }
+
+    #[test]
+    fn completes_every_subscription_and_resource_group_in_a_snapshot() {
+        let snapshot = generate(&SyntheticTenantOptions::small(7)).unwrap();
+        let subscription_ids = snapshot
+            .subscriptions
+            .iter()
+            .map(|x| x.subscription_id.clone())
+            .sorted()
+            .collect_vec();
+        let resource_group_names = snapshot
+            .resource_groups
+            .iter()
+            .map(|x| x.name.clone())
+            .sorted()
+            .dedup()
+            .collect_vec();
+        let known = Known::from(snapshot);
+
+        let subscriptions = known.subscription_candidates();
+        assert_eq!(values(&subscriptions), subscription_ids);
+        assert!(subscriptions.iter().all(|x| x.get_help().is_some()));
+        assert_eq!(
+            values(&known.resource_group_candidates()),
+            resource_group_names
+        );
+    }
+}
@@ -1,14 +1,52 @@
 use arbitrary::Arbitrary;
+use arbitrary::Unstructured;
 use clap::Args;
-use std::ffi::OsString;
+use clap::ValueEnum;
+use std::path::PathBuf;
 
+use crate::cache::CacheMode;
+use crate::cli::arbitrary_args::optional_path;
 use crate::cli::to_args::ToArgs;
 
-#[derive(Args, Default, Arbitrary, PartialEq, Debug)]
+#[derive(Args, Clone, Default, Arbitrary, PartialEq, Eq, Debug, ToArgs)]
 pub struct GlobalArgs {
-    /// Enable debug logging
+    /// Enable debug logging; `RUST_LOG` directives refine it per module
     #[clap(long, global = true)]
     pub debug: bool,
+
+    /// Append logs to this file instead of writing them to stderr
+    #[clap(long, global = true, value_name = "PATH")]
+    #[arbitrary(with = optional_path)]
+    pub log_file: Option<PathBuf>,
+
+    /// How log lines are formatted
+    #[clap(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Bypass the on-disk cache entirely; always fetch and never write
+    #[clap(long, global = true, conflicts_with = "refresh")]
+    pub no_cache: bool,
+
+    /// Ignore cached entries but write freshly fetched data back to the cache
+    #[clap(long, global = true)]
+    pub refresh: bool,
+
+    /// How long cached Azure data is considered fresh, in seconds; overrides the `cache_ttl` setting
+    #[clap(long, global = true, value_name = "SECONDS")]
+    pub cache_ttl: Option<u64>,
+
+    /// Console to attach to, passed when this program relaunches itself
+    #[clap(long, global = true, hide = true, value_name = "PID")]
+    pub console_pid: Option<u32>,
+}
+
+/// Arbitrary global args that clap accepts, for fuzzing.
+///
+/// The derived impl can set both `--no-cache` and `--refresh`, which conflict.
+pub fn valid_global_args(u: &mut Unstructured) -> arbitrary::Result<GlobalArgs> {
+    let mut rtn = GlobalArgs::arbitrary(u)?;
+    rtn.refresh &= !rtn.no_cache;
+    Ok(rtn)
 }
 
 impl GlobalArgs {
@@ -19,14 +57,27 @@ impl GlobalArgs {
             tracing::Level::INFO
         }
     }
-}
 
-impl ToArgs for GlobalArgs {
-    fn to_args(&self) -> Vec<OsString> {
-        let mut args = Vec::new();
-        if self.debug {
-            args.push("--debug".into());
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format.unwrap_or_default()
+    }
+
+    pub fn cache_mode(&self) -> CacheMode {
+        if self.no_cache {
+            CacheMode::Disabled
+        } else if self.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Enabled
         }
-        args
     }
 }
+
+#[derive(ValueEnum, Clone, Copy, Default, Arbitrary, PartialEq, Eq, Debug)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log shippers
+    Json,
+}
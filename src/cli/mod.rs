@@ -1,18 +1,25 @@
+pub mod arbitrary_args;
 pub mod command;
+pub mod completion;
 pub mod global_args;
+pub mod pick;
+pub mod relaunch;
 pub mod to_args;
 use crate::cli::command::Command;
 use crate::cli::global_args::GlobalArgs;
+use crate::cli::global_args::valid_global_args;
+use crate::config::Config;
 use arbitrary::Arbitrary;
 use clap::Parser;
 use std::ffi::OsString;
 use to_args::Invocable;
 use to_args::ToArgs;
 
-#[derive(Parser, Arbitrary, PartialEq, Debug)]
+#[derive(Parser, Arbitrary, PartialEq, Debug, ToArgs)]
 #[clap(version)]
 pub struct Cli {
     #[clap(flatten)]
+    #[arbitrary(with = valid_global_args)]
     pub global_args: GlobalArgs,
     #[clap(subcommand)]
     pub command: Command,
@@ -20,16 +27,8 @@ pub struct Cli {
 
 impl Cli {
     pub fn invoke(self) -> eyre::Result<()> {
-        self.command.invoke()
-    }
-}
-
-impl ToArgs for Cli {
-    fn to_args(&self) -> Vec<OsString> {
-        let mut args = Vec::new();
-        args.extend(self.global_args.to_args());
-        args.extend(self.command.to_args());
-        args
+        let config = Config::load(&self.global_args)?;
+        self.command.invoke(&self.global_args, &config)
     }
 }
 
@@ -95,6 +94,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fuzz_every_command_args_roundtrip() {
+        // Generate each command explicitly so none is left to chance
+        for seed in 0..50u8 {
+            let data = vec![seed; 1024];
+            let mut rng = arbitrary::Unstructured::new(&data);
+            let commands = [
+                Command::ListResourceGroups(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::ResourceGroupTui(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::GenerateTenant(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::RoleAssignment(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::Config(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::PrincipalAccess(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::AccessMatrix(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::HtmlReport(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::Completions(Arbitrary::arbitrary(&mut rng).unwrap()),
+                Command::Man(Arbitrary::arbitrary(&mut rng).unwrap()),
+            ];
+            for command in commands {
+                // Fails to compile when a command is added without being listed above
+                match &command {
+                    Command::ListResourceGroups(_)
+                    | Command::ResourceGroupTui(_)
+                    | Command::GenerateTenant(_)
+                    | Command::RoleAssignment(_)
+                    | Command::Config(_)
+                    | Command::PrincipalAccess(_)
+                    | Command::AccessMatrix(_)
+                    | Command::HtmlReport(_)
+                    | Command::Completions(_)
+                    | Command::Man(_) => {}
+                }
+                let cli = Cli {
+                    global_args: valid_global_args(&mut rng).unwrap(),
+                    command,
+                };
+                let mut full_args = vec!["test-exe".into()];
+                full_args.extend(cli.to_args());
+                let parsed = Cli::try_parse_from(&full_args)
+                    .unwrap_or_else(|e| panic!("Failed to parse {full_args:?} for {cli:?}: {e}"));
+                assert_eq!(cli, parsed, "Args: {full_args:?}");
+            }
+        }
+    }
+
     #[test]
     fn fuzz_cli_args_consistency() {
         // Test that the same CLI instance always produces the same args
@@ -121,4 +165,24 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn parses_console_pid_appended_by_relaunch() {
+        // `SameInvocationSameConsole` appends the option after the subcommand's own arguments
+        let cli = Cli::try_parse_from(["test-exe", "generate-tenant", "--seed", "3"]).unwrap();
+        let mut args = vec!["test-exe".into()];
+        args.extend(cli.to_args());
+        args.extend(["--console-pid".into(), "4242".into()]);
+        let relaunched = Cli::try_parse_from(args).unwrap();
+        assert_eq!(relaunched.global_args.console_pid, Some(4242));
+        assert_eq!(relaunched.command, cli.command);
+    }
+
+    #[test]
+    fn rejects_no_cache_with_refresh() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args);
+        assert!(parse(&["test-exe", "--no-cache", "--refresh", "generate-tenant"]).is_err());
+        assert!(parse(&["test-exe", "generate-tenant", "--refresh", "--no-cache"]).is_err());
+        assert!(parse(&["test-exe", "--refresh", "generate-tenant"]).is_ok());
+    }
 }
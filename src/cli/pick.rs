@@ -0,0 +1,87 @@
+//! Interactive fallbacks for arguments left off the command line.
+//!
+//! Pickers only open when stdin is a terminal, so scripts and relaunched processes get an error
+//! naming the missing flags instead of waiting on input that never comes.
+
+use crate::azure::principals::PrincipalDirectory;
+use crate::cache::Cache;
+use crate::cache::FetchKind;
+use crate::config::Config;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::fetch_all_resource_groups;
+use cloud_terrastodon_azure::prelude::fetch_all_role_definitions_and_assignments;
+use cloud_terrastodon_user_input::Choice;
+use cloud_terrastodon_user_input::PickerTui;
+use itertools::Itertools;
+use std::io::IsTerminal;
+
+/// Fail with the `missing` flags unless the user can be prompted for them instead.
+pub fn ensure_can_prompt(missing: &[&str]) -> eyre::Result<()> {
+    if std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+    eyre::bail!(
+        "Missing {}; pass {} or run from a terminal to pick interactively",
+        missing.join(", "),
+        if missing.len() == 1 { "it" } else { "them" }
+    )
+}
+
+pub fn pick_principal(principals: &PrincipalDirectory) -> eyre::Result<PrincipalId> {
+    let choices = principals
+        .iter()
+        .map(|(id, entry)| {
+            let display = principals.display(id);
+            Choice {
+                key: match &entry.alias {
+                    Some(alias) => format!("{display} <{alias}>"),
+                    None => display,
+                },
+                value: *id,
+            }
+        })
+        .collect_vec();
+    PickerTui::new(choices)
+        .set_header("Pick a principal")
+        .pick_one()
+}
+
+pub async fn pick_role(cache: &Cache) -> eyre::Result<RoleDefinition> {
+    let rbac = cache
+        .get_or_fetch(
+            FetchKind::RoleDefinitionsAndAssignments,
+            fetch_all_role_definitions_and_assignments(),
+        )
+        .await?;
+    let choices = rbac
+        .role_definitions
+        .into_values()
+        .sorted_by(|a, b| a.display_name.cmp(&b.display_name))
+        .map(|x| Choice {
+            key: format!("{} - {}", x.display_name, x.description),
+            value: x,
+        })
+        .collect_vec();
+    PickerTui::new(choices).set_header("Pick a role").pick_one()
+}
+
+/// Pick from the resource groups in the configured subscriptions.
+pub async fn pick_resource_group(cache: &Cache, config: &Config) -> eyre::Result<ResourceGroup> {
+    let resource_groups = cache
+        .get_or_fetch(FetchKind::ResourceGroups, fetch_all_resource_groups())
+        .await?;
+    let choices = resource_groups
+        .into_iter()
+        .filter(|x| config.includes(x))
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .map(|x| Choice {
+            key: format!("{} ({})", x.name, x.subscription_id),
+            value: x,
+        })
+        .collect_vec();
+    PickerTui::new(choices)
+        .set_header("Pick a resource group")
+        .pick_one()
+}
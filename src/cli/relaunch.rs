@@ -0,0 +1,78 @@
+use crate::cli::to_args::Invocable;
+use eyre::Context;
+use std::convert::Infallible;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::process::Stdio;
+use tracing::debug;
+
+/// Launch `invocable` sharing this process's console and wait for it to finish.
+pub fn run(invocable: &impl Invocable) -> eyre::Result<ExitStatus> {
+    command(invocable)
+        .status()
+        .wrap_err_with(|| format!("Failed to launch {}", invocable.executable().display()))
+}
+
+/// Launch `invocable` without a console, capturing its output, and wait for it to finish.
+///
+/// Used while the TUI owns the terminal.
+pub fn output(invocable: &impl Invocable) -> eyre::Result<Output> {
+    command(invocable)
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err_with(|| format!("Failed to launch {}", invocable.executable().display()))
+}
+
+fn command(invocable: &impl Invocable) -> std::process::Command {
+    let executable = invocable.executable();
+    let args = invocable.args();
+    let envs = invocable.envs();
+    debug!(
+        "Relaunching {} with {:?} and environment {:?}",
+        executable.display(),
+        args,
+        envs.iter().map(|(key, _)| key).collect::<Vec<_>>()
+    );
+    let mut command = std::process::Command::new(&executable);
+    command.args(&args).envs(envs);
+    command
+}
+
+/// Launch `invocable`, then exit with its exit code once it finishes.
+///
+/// This process outlives the child so the console is only handed back to the shell at the end.
+pub fn relaunch(invocable: &impl Invocable) -> eyre::Result<Infallible> {
+    let status = run(invocable)?;
+    std::process::exit(exit_code(status))
+}
+
+/// The exit code that reports `status` to our own caller; 1 if the child was killed by a signal.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+/// Attach to the console of the process that relaunched us, given by `--console-pid`.
+///
+/// Only Windows gives a relaunched process a console of its own; elsewhere the standard streams
+/// are inherited and there is nothing to do.
+pub fn attach_console(pid: u32) -> eyre::Result<()> {
+    #[cfg(windows)]
+    {
+        unsafe extern "system" {
+            fn FreeConsole() -> i32;
+            fn AttachConsole(process_id: u32) -> i32;
+        }
+        // SAFETY: neither call touches memory we own; failure is reported through the return value
+        let attached = unsafe {
+            FreeConsole();
+            AttachConsole(pid)
+        };
+        if attached == 0 {
+            return Err(std::io::Error::last_os_error())
+                .wrap_err_with(|| format!("Failed to attach to the console of process {pid}"));
+        }
+    }
+    #[cfg(not(windows))]
+    debug!("Console of process {pid} is already inherited");
+    Ok(())
+}
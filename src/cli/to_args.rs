@@ -1,13 +1,51 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
 
+pub use role_assignment_tui_macros::ToArgs;
+
 /// Trait for converting CLI structures to command line arguments
+///
+/// Derive it alongside clap's derives rather than implementing it by hand, so the emitted
+/// arguments always follow the clap attributes.
 pub trait ToArgs {
     fn to_args(&self) -> Vec<OsString> {
         Vec::new()
     }
 }
 
+/// Conversion of a single option or positional value to a command line argument
+pub trait ToArg {
+    fn to_arg(&self) -> OsString;
+}
+
+impl ToArg for OsString {
+    fn to_arg(&self) -> OsString {
+        self.clone()
+    }
+}
+
+impl ToArg for PathBuf {
+    fn to_arg(&self) -> OsString {
+        self.clone().into_os_string()
+    }
+}
+
+macro_rules! to_arg_via_display {
+    ($($ty:ty),*) => {
+        $(
+            impl ToArg for $ty {
+                fn to_arg(&self) -> OsString {
+                    self.to_string().into()
+                }
+            }
+        )*
+    };
+}
+
+to_arg_via_display!(
+    String, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
 // Blanket implementation for references
 impl<T: ToArgs> ToArgs for &T {
     fn to_args(&self) -> Vec<OsString> {
@@ -19,6 +57,11 @@ impl<T: ToArgs> ToArgs for &T {
 pub trait Invocable {
     fn executable(&self) -> PathBuf;
     fn args(&self) -> Vec<OsString>;
+
+    /// Environment variables set for the launched process on top of the inherited ones
+    fn envs(&self) -> Vec<(OsString, OsString)> {
+        Vec::new()
+    }
 }
 
 /// Unit struct representing the current invocation's arguments
@@ -77,3 +120,138 @@ impl Invocable for SameInvocationSameConsole {
         self.to_args()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgAction;
+    use clap::Args;
+    use clap::Parser;
+    use clap::Subcommand;
+    use clap::ValueEnum;
+
+    #[derive(Parser, PartialEq, Debug, ToArgs)]
+    struct Example {
+        #[clap(short, action = ArgAction::Count)]
+        verbose: u8,
+        #[clap(long = "tag", short = 't')]
+        tags: Vec<String>,
+        #[clap(long, value_enum)]
+        mode: Option<Mode>,
+        #[clap(short = 'n')]
+        name: Option<String>,
+        #[clap(skip)]
+        ignored: u32,
+        file: Option<PathBuf>,
+        #[clap(subcommand)]
+        action: Option<Action>,
+    }
+
+    #[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+    enum Mode {
+        DryRun,
+        Apply,
+    }
+
+    #[derive(Subcommand, PartialEq, Debug, ToArgs)]
+    enum Action {
+        #[command(name = "go")]
+        Run(RunArgs),
+        StopAll {
+            #[clap(long)]
+            force: bool,
+        },
+        HTMLReport {
+            #[clap(long)]
+            title: Option<String>,
+        },
+    }
+
+    #[derive(Args, PartialEq, Debug, ToArgs)]
+    struct RunArgs {
+        #[clap(long)]
+        retries: u64,
+    }
+
+    fn roundtrip(example: Example, expected: &[&str]) {
+        let args = example.to_args();
+        assert_eq!(
+            args,
+            expected.iter().map(OsString::from).collect::<Vec<_>>()
+        );
+        let parsed = Example::try_parse_from(std::iter::once("test-exe".into()).chain(args));
+        assert_eq!(parsed.unwrap(), example);
+    }
+
+    #[test]
+    fn derived_to_args_roundtrip() {
+        roundtrip(
+            Example {
+                verbose: 2,
+                tags: vec!["a".into(), "b c".into()],
+                mode: Some(Mode::DryRun),
+                name: Some("x".into()),
+                ignored: 0,
+                file: Some("x.json".into()),
+                action: Some(Action::Run(RunArgs { retries: 3 })),
+            },
+            &[
+                "-v",
+                "-v",
+                "--tag=a",
+                "--tag=b c",
+                "--mode=dry-run",
+                "-n=x",
+                "x.json",
+                "go",
+                "--retries=3",
+            ],
+        );
+        roundtrip(
+            Example {
+                verbose: 0,
+                tags: Vec::new(),
+                mode: None,
+                name: None,
+                ignored: 0,
+                file: None,
+                action: Some(Action::StopAll { force: true }),
+            },
+            &["stop-all", "--force"],
+        );
+    }
+
+    #[test]
+    fn values_starting_with_a_hyphen_roundtrip() {
+        roundtrip(
+            Example {
+                verbose: 0,
+                tags: vec!["-a".into(), "--b".into()],
+                mode: None,
+                name: Some("-x".into()),
+                ignored: 0,
+                file: Some("-x.json".into()),
+                action: None,
+            },
+            &["--tag=-a", "--tag=--b", "-n=-x", "--", "-x.json"],
+        );
+    }
+
+    #[test]
+    fn names_acronym_variants_as_clap_does() {
+        roundtrip(
+            Example {
+                verbose: 0,
+                tags: Vec::new(),
+                mode: None,
+                name: None,
+                ignored: 0,
+                file: None,
+                action: Some(Action::HTMLReport {
+                    title: Some("Q3".into()),
+                }),
+            },
+            &["html-report", "--title=Q3"],
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Layered configuration.
+//!
+//! Later layers override earlier ones: built-in defaults, the user's `config.toml`, the nearest
+//! project-local `.role-assignment-tui.toml`, `RATUI_*` environment variables and finally
+//! command line flags.
+
+use crate::cache::DEFAULT_CACHE_TTL;
+use crate::cli::global_args::GlobalArgs;
+use clap::ValueEnum;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use eyre::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Name of the project-local config file, looked up from the working directory upwards.
+pub const PROJECT_FILE_NAME: &str = ".role-assignment-tui.toml";
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// One pretty-printed JSON array
+    #[default]
+    Json,
+    /// One compact JSON object per line
+    JsonLines,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Darker colours that stay readable on a light background
+    Light,
+    /// No colours, for terminals or screen readers that do not want them
+    Monochrome,
+}
+
+/// The settings from one source; unset fields fall through to earlier layers.
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub expected_tenant: Option<String>,
+    pub subscriptions: Option<Vec<String>>,
+    pub output_format: Option<OutputFormat>,
+    pub theme: Option<ThemeName>,
+    /// Seconds
+    pub cache_ttl: Option<u64>,
+    /// The `[keys]` table: TUI action names to the keys that trigger them
+    pub keys: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// Where an effective setting came from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ConfigSource {
+    Default,
+    UserFile(PathBuf),
+    ProjectFile(PathBuf),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => f.write_str("default"),
+            ConfigSource::UserFile(path) => write!(f, "user config {}", path.display()),
+            ConfigSource::ProjectFile(path) => write!(f, "project config {}", path.display()),
+            ConfigSource::Env(name) => write!(f, "environment {name}"),
+            ConfigSource::Flag(name) => write!(f, "flag {name}"),
+        }
+    }
+}
+
+/// The effective configuration after merging every layer.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Config {
+    /// Tenant the Azure CLI must be logged in to; `None` accepts whichever it is
+    pub expected_tenant: Option<String>,
+    /// Subscription ids to limit resource groups to; empty means all of them
+    pub subscriptions: Vec<String>,
+    pub output_format: OutputFormat,
+    pub theme: ThemeName,
+    /// Seconds
+    pub cache_ttl: u64,
+    /// Key overrides by action, merged action by action; unlisted actions keep their defaults
+    pub keys: BTreeMap<String, Vec<String>>,
+    /// Keyed by setting name, or `keys.<action>` for key overrides; settings missing here are
+    /// defaults
+    sources: HashMap<String, ConfigSource>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            expected_tenant: None,
+            subscriptions: Vec::new(),
+            output_format: OutputFormat::default(),
+            theme: ThemeName::default(),
+            cache_ttl: DEFAULT_CACHE_TTL.as_secs(),
+            keys: BTreeMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+}
+
+/// Environment variables for each setting, in display order.
+const ENV_VARS: [(&str, &str); 5] = [
+    ("expected_tenant", "RATUI_EXPECTED_TENANT"),
+    ("subscriptions", "RATUI_SUBSCRIPTIONS"),
+    ("output_format", "RATUI_OUTPUT_FORMAT"),
+    ("theme", "RATUI_THEME"),
+    ("cache_ttl", "RATUI_CACHE_TTL"),
+];
+
+impl Config {
+    /// Merge every layer for this invocation.
+    pub fn load(global_args: &GlobalArgs) -> eyre::Result<Config> {
+        let mut layers = Vec::new();
+        let user_file = config_root()?.join("config.toml");
+        if let Some(layer) = read_layer(&user_file)? {
+            layers.push((ConfigSource::UserFile(user_file), layer));
+        }
+        if let Some(project_file) = find_project_file(&std::env::current_dir()?) {
+            let layer = read_layer(&project_file)?.unwrap_or_default();
+            layers.push((ConfigSource::ProjectFile(project_file), layer));
+        }
+        layers.extend(env_layers(|name| std::env::var(name).ok())?);
+        layers.extend(flag_layers(global_args));
+        Ok(Config::from_layers(layers))
+    }
+
+    pub fn from_layers(layers: impl IntoIterator<Item = (ConfigSource, ConfigLayer)>) -> Config {
+        let mut rtn = Config::default();
+        for (source, layer) in layers {
+            rtn.apply(layer, &source);
+        }
+        rtn
+    }
+
+    fn apply(&mut self, layer: ConfigLayer, source: &ConfigSource) {
+        macro_rules! set {
+            ($field:ident, $convert:expr) => {
+                if let Some(value) = layer.$field {
+                    self.$field = $convert(value);
+                    self.sources
+                        .insert(stringify!($field).to_string(), source.clone());
+                }
+            };
+        }
+        set!(expected_tenant, Some);
+        set!(subscriptions, std::convert::identity);
+        set!(output_format, std::convert::identity);
+        set!(theme, std::convert::identity);
+        set!(cache_ttl, std::convert::identity);
+        for (action, keys) in layer.keys.into_iter().flatten() {
+            self.sources
+                .insert(format!("keys.{action}"), source.clone());
+            self.keys.insert(action, keys);
+        }
+    }
+
+    pub fn source(&self, setting: &str) -> &ConfigSource {
+        self.sources.get(setting).unwrap_or(&ConfigSource::Default)
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl)
+    }
+
+    /// Whether `resource_group` passes the subscription filter.
+    pub fn includes(&self, resource_group: &ResourceGroup) -> bool {
+        self.includes_subscription(&resource_group.subscription_id.to_string())
+    }
+
+    /// Whether `subscription_id` passes the subscription filter.
+    pub fn includes_subscription(&self, subscription_id: &str) -> bool {
+        self.subscriptions.is_empty()
+            || self
+                .subscriptions
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(subscription_id))
+    }
+
+    /// Fail unless the Azure CLI is logged in to the expected tenant, if there is one.
+    ///
+    /// This only guards against working in the wrong tenant; it does not switch the Azure CLI's
+    /// login, which is shared with every other `az` session.
+    pub fn check_tenant(&self, current_tenant_id: &str) -> eyre::Result<()> {
+        match &self.expected_tenant {
+            Some(tenant) if !tenant.eq_ignore_ascii_case(current_tenant_id) => eyre::bail!(
+                "The Azure CLI is logged in to tenant {current_tenant_id} but {} expects {tenant}; run `az login --tenant {tenant}`",
+                self.source("expected_tenant")
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The effective settings as TOML, each commented with where it came from.
+    pub fn render(&self) -> String {
+        let quote = |x: &str| serde_json::Value::from(x).to_string();
+        let entries = ENV_VARS.map(|(setting, _)| {
+            let value = match setting {
+                "expected_tenant" => self.expected_tenant.as_deref().map(quote),
+                "subscriptions" => {
+                    Some(serde_json::Value::from(self.subscriptions.clone()).to_string())
+                }
+                "output_format" => self
+                    .output_format
+                    .to_possible_value()
+                    .map(|x| quote(x.get_name())),
+                "theme" => self.theme.to_possible_value().map(|x| quote(x.get_name())),
+                "cache_ttl" => Some(self.cache_ttl.to_string()),
+                _ => unreachable!("every setting is rendered"),
+            };
+            let entry = match value {
+                Some(value) => format!("{setting} = {value}"),
+                None => format!("# {setting} is not set"),
+            };
+            (entry, self.source(setting))
+        });
+        let mut entries = Vec::from(entries);
+        if self.keys.is_empty() {
+            entries.push(("# keys are not set".to_string(), &ConfigSource::Default));
+        }
+        for (action, keys) in &self.keys {
+            let setting = format!("keys.{action}");
+            let value = serde_json::Value::from(keys.clone());
+            entries.push((format!("{setting} = {value}"), self.source(&setting)));
+        }
+        let width = entries
+            .iter()
+            .map(|(entry, _)| entry.len())
+            .max()
+            .unwrap_or(0);
+        entries
+            .iter()
+            .map(|(entry, source)| format!("{entry:<width$}  # {source}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Directory holding `config.toml`.
+///
+/// Honours `RATUI_CONFIG_DIR` so tests and CI can point it somewhere disposable.
+pub fn config_root() -> eyre::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("RATUI_CONFIG_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let Some(dir) = dirs::config_dir() else {
+        eyre::bail!("Could not determine the user config directory, set RATUI_CONFIG_DIR instead");
+    };
+    Ok(dir.join("role-assignment-tui"))
+}
+
+/// The nearest [`PROJECT_FILE_NAME`] in `dir` or its ancestors.
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+fn read_layer(path: &Path) -> eyre::Result<Option<ConfigLayer>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text)
+            .map(Some)
+            .wrap_err_with(|| format!("Reading config {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).wrap_err_with(|| format!("Reading config {}", path.display())),
+    }
+}
+
+/// One layer per `RATUI_*` variable that is set, read through `var`.
+pub fn env_layers(
+    var: impl Fn(&str) -> Option<String>,
+) -> eyre::Result<Vec<(ConfigSource, ConfigLayer)>> {
+    let mut rtn = Vec::new();
+    for (setting, name) in ENV_VARS {
+        let Some(value) = var(name) else {
+            continue;
+        };
+        let invalid = || format!("Invalid {name} {value:?}");
+        let mut layer = ConfigLayer::default();
+        match setting {
+            "expected_tenant" => layer.expected_tenant = Some(value.clone()),
+            "subscriptions" => {
+                layer.subscriptions = Some(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|x| !x.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            }
+            "output_format" => {
+                layer.output_format = Some(
+                    OutputFormat::from_str(&value, true)
+                        .map_err(|e| eyre::eyre!(e))
+                        .wrap_err_with(invalid)?,
+                )
+            }
+            "theme" => {
+                layer.theme = Some(
+                    ThemeName::from_str(&value, true)
+                        .map_err(|e| eyre::eyre!(e))
+                        .wrap_err_with(invalid)?,
+                )
+            }
+            "cache_ttl" => layer.cache_ttl = Some(value.parse().wrap_err_with(invalid)?),
+            _ => unreachable!("every setting has an environment variable"),
+        }
+        rtn.push((ConfigSource::Env(name), layer));
+    }
+    Ok(rtn)
+}
+
+fn flag_layers(global_args: &GlobalArgs) -> Vec<(ConfigSource, ConfigLayer)> {
+    let mut rtn = Vec::new();
+    if let Some(cache_ttl) = global_args.cache_ttl {
+        let layer = ConfigLayer {
+            cache_ttl: Some(cache_ttl),
+            ..Default::default()
+        };
+        rtn.push((ConfigSource::Flag("--cache-ttl"), layer));
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let user: ConfigLayer = toml::from_str(
+            r#"
+            # shared defaults
+            expected_tenant = "11111111-1111-1111-1111-111111111111"
+            theme = "light"
+            cache_ttl = 60
+            "#,
+        )
+        .unwrap();
+        let project: ConfigLayer = toml::from_str(
+            r#"
+            subscriptions = ["aaaa", "bbbb"]
+            cache_ttl = 120
+            "#,
+        )
+        .unwrap();
+        let env =
+            env_layers(|name| (name == "RATUI_CACHE_TTL").then(|| "300".to_string())).unwrap();
+        let config = Config::from_layers(
+            [
+                (ConfigSource::UserFile("user.toml".into()), user),
+                (ConfigSource::ProjectFile("project.toml".into()), project),
+            ]
+            .into_iter()
+            .chain(env),
+        );
+        assert_eq!(config.theme, ThemeName::Light);
+        assert_eq!(config.subscriptions, ["aaaa", "bbbb"]);
+        assert_eq!(config.cache_ttl, 300);
+        assert_eq!(config.output_format, OutputFormat::Json);
+        assert_eq!(
+            config.render(),
+            [
+                r#"expected_tenant = "11111111-1111-1111-1111-111111111111"  # user config user.toml"#,
+                r#"subscriptions = ["aaaa","bbbb"]                           # project config project.toml"#,
+                r#"output_format = "json"                                    # default"#,
+                r#"theme = "light"                                           # user config user.toml"#,
+                r#"cache_ttl = 300                                           # environment RATUI_CACHE_TTL"#,
+                r#"# keys are not set                                        # default"#,
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn merges_key_overrides_action_by_action() {
+        let user: ConfigLayer = toml::from_str(
+            r#"
+            [keys]
+            down = ["n"]
+            quit = ["Ctrl+c"]
+            "#,
+        )
+        .unwrap();
+        let project: ConfigLayer = toml::from_str(
+            r#"
+            [keys]
+            down = ["Down", "s"]
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_layers([
+            (ConfigSource::UserFile("user.toml".into()), user),
+            (ConfigSource::ProjectFile("project.toml".into()), project),
+        ]);
+        assert_eq!(config.keys["down"], ["Down", "s"]);
+        assert_eq!(config.keys["quit"], ["Ctrl+c"]);
+        assert_eq!(
+            config.source("keys.quit"),
+            &ConfigSource::UserFile("user.toml".into())
+        );
+        assert!(
+            config.render().ends_with(
+                &[
+                    r#"keys.down = ["Down","s"]      # project config project.toml"#,
+                    r#"keys.quit = ["Ctrl+c"]        # user config user.toml"#,
+                ]
+                .join("\n")
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_settings_and_invalid_env_values() {
+        assert!(toml::from_str::<ConfigLayer>("colour = \"red\"").is_err());
+        let error =
+            env_layers(|name| (name == "RATUI_THEME").then(|| "neon".to_string())).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("Invalid RATUI_THEME"),
+            "{error:#}"
+        );
+        let layers =
+            env_layers(|name| (name == "RATUI_SUBSCRIPTIONS").then(|| "aaaa, bbbb,".to_string()))
+                .unwrap();
+        assert_eq!(
+            layers[0].1.subscriptions,
+            Some(vec!["aaaa".into(), "bbbb".into()])
+        );
+    }
+
+    #[test]
+    fn checks_the_configured_tenant() {
+        let config = Config::from_layers([(
+            ConfigSource::Env("RATUI_EXPECTED_TENANT"),
+            ConfigLayer {
+                expected_tenant: Some("AAAA".into()),
+                ..Default::default()
+            },
+        )]);
+        config.check_tenant("aaaa").unwrap();
+        let error = config.check_tenant("bbbb").unwrap_err().to_string();
+        assert!(
+            error.contains("environment RATUI_EXPECTED_TENANT expects AAAA"),
+            "{error}"
+        );
+        Config::default().check_tenant("bbbb").unwrap();
+    }
+}
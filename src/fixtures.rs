@@ -0,0 +1,169 @@
+//! Small hand-built Azure values for tests.
+
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::deny_assignments::EVERYONE;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::hierarchy::ManagementGroupEntry;
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::hierarchy::SubscriptionEntry;
+use crate::azure::resources::ResourceEntry;
+use crate::synthetic::build;
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::Scope;
+use cloud_terrastodon_azure::prelude::ServicePrincipal;
+use cloud_terrastodon_azure::prelude::User;
+
+pub use crate::synthetic::build::rbac;
+
+pub const SUBSCRIPTION_ID: &str = "00000000-0000-0000-0000-00000000a001";
+
+pub fn resource_group(name: &str) -> ResourceGroup {
+    build::resource_group(SUBSCRIPTION_ID, name, "canadacentral").unwrap()
+}
+
+pub fn user(display_name: &str) -> User {
+    let mail_nickname = display_name.to_lowercase().replace(' ', ".");
+    build::user(
+        &build::uuid(&format!("user/{display_name}")),
+        display_name,
+        &format!("{mail_nickname}@example.com"),
+    )
+    .unwrap()
+}
+
+pub fn service_principal(display_name: &str) -> ServicePrincipal {
+    build::service_principal(
+        &build::uuid(&format!("service-principal/{display_name}")),
+        &build::uuid(&format!("application/{display_name}")),
+        display_name,
+    )
+    .unwrap()
+}
+
+pub fn group(display_name: &str) -> Group {
+    build::security_group(
+        &build::uuid(&format!("group/{display_name}")),
+        display_name,
+        "",
+    )
+    .unwrap()
+}
+
+pub fn built_in_role(display_name: &str, actions: &[&str]) -> RoleDefinition {
+    built_in_role_excluding(display_name, actions, &[])
+}
+
+/// Like [`built_in_role`] with `not_actions` carved out of `actions`, as Contributor has.
+pub fn built_in_role_excluding(
+    display_name: &str,
+    actions: &[&str],
+    not_actions: &[&str],
+) -> RoleDefinition {
+    build::role_definition(&build::RoleDefinitionSpec {
+        name: &build::uuid(&format!("role/{display_name}")),
+        display_name,
+        description: &format!("{display_name} role"),
+        custom: false,
+        actions,
+        not_actions,
+        data_actions: &[],
+        assignable_scopes: &["/".to_string()],
+    })
+    .unwrap()
+}
+
+pub fn role_assignment(
+    scope: &impl Scope,
+    role_definition: &RoleDefinition,
+    principal_id: impl Into<PrincipalId>,
+) -> RoleAssignment {
+    role_assignment_at(&scope.expanded_form(), role_definition, principal_id)
+}
+
+/// Like [`role_assignment`] for scopes we only have as strings, such as resources.
+pub fn role_assignment_at(
+    scope: &str,
+    role_definition: &RoleDefinition,
+    principal_id: impl Into<PrincipalId>,
+) -> RoleAssignment {
+    build::role_assignment(scope, role_definition, &principal_id.into()).unwrap()
+}
+
+/// A tenant root group holding the fixture subscription.
+pub fn hierarchy() -> ScopeHierarchy {
+    ScopeHierarchy {
+        management_groups: vec![ManagementGroupEntry {
+            name: "tenant-root".to_string(),
+            display_name: "Tenant Root Group".to_string(),
+            parent_name: None,
+        }],
+        subscriptions: vec![SubscriptionEntry {
+            subscription_id: SUBSCRIPTION_ID.to_string(),
+            display_name: "sub-demo".to_string(),
+            management_group_name: Some("tenant-root".to_string()),
+        }],
+    }
+}
+
+pub fn resource(resource_group: &ResourceGroup, name: &str, kind: &str) -> ResourceEntry {
+    ResourceEntry {
+        id: format!(
+            "{}/providers/{kind}/{name}",
+            resource_group.id.expanded_form()
+        ),
+        name: name.to_string(),
+        kind: kind.to_string(),
+    }
+}
+
+/// A deny assignment for everyone at `scope` except `exclude_principal_ids`.
+pub fn deny_assignment(
+    scope: &str,
+    actions: &[&str],
+    exclude_principal_ids: &[&str],
+) -> DenyAssignment {
+    DenyAssignment {
+        id: format!(
+            "{scope}/providers/Microsoft.Authorization/denyAssignments/{}",
+            build::uuid(&format!("deny/{scope}"))
+        ),
+        name: "deny-fixture".to_string(),
+        description: None,
+        scope: scope.to_string(),
+        actions: actions.iter().map(|x| x.to_string()).collect(),
+        not_actions: Vec::new(),
+        data_actions: Vec::new(),
+        not_data_actions: Vec::new(),
+        principal_ids: vec![EVERYONE.to_string()],
+        exclude_principal_ids: exclude_principal_ids
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+        do_not_apply_to_child_scopes: false,
+    }
+}
+
+/// An eligibility for the first half of 2026.
+pub fn eligible_assignment(
+    scope: &str,
+    role_definition: &RoleDefinition,
+    principal_id: impl Into<PrincipalId>,
+) -> EligibleAssignment {
+    let principal_id = principal_id.into();
+    EligibleAssignment {
+        id: format!(
+            "{scope}/providers/Microsoft.Authorization/roleEligibilityScheduleInstances/{}",
+            build::uuid(&format!("eligible/{scope}/{principal_id}"))
+        ),
+        scope: scope.to_string(),
+        role_definition_id: role_definition.id.expanded_form(),
+        principal_id,
+        member_type: Some("Direct".to_string()),
+        start_date_time: "2026-01-01T00:00:00Z".parse().ok(),
+        end_date_time: "2026-06-30T00:00:00Z".parse().ok(),
+    }
+}
@@ -1,37 +1,35 @@
+pub mod azure;
+pub mod cache;
 pub mod cli;
+pub mod config;
+#[cfg(test)]
+mod fixtures;
+pub mod logging;
+pub mod snapshot;
+pub mod synthetic;
 use clap::CommandFactory;
 use clap::FromArgMatches;
-use tracing::Level;
-use tracing::debug;
+use clap_complete::env::CompleteEnv;
 
 use crate::cli::Cli;
-
-/// Initialize tracing subscriber with the given log level.
-/// In debug builds, include file and line number without timestamp.
-/// In release builds, include timestamp and log level.
-pub fn init_tracing(level: Level) {
-    let builder = tracing_subscriber::fmt().with_max_level(level);
-    #[cfg(debug_assertions)]
-    let subscriber = builder
-        .with_target(false)
-        .with_file(true)
-        .with_line_number(true)
-        .without_time()
-        .with_writer(std::io::stderr)
-        .finish();
-    #[cfg(not(debug_assertions))]
-    let subscriber = builder.finish();
-    tracing::subscriber::set_global_default(subscriber).expect("Failed to set tracing subscriber");
-    debug!("Tracing initialized with level: {:?}", level);
-}
+use crate::cli::completion::COMPLETE_VAR;
+use crate::cli::relaunch::attach_console;
+use crate::logging::init_tracing;
 
 // Entrypoint for the program to reduce coupling to the name of this crate.
 pub fn main() -> eyre::Result<()> {
+    // Answers the shell and exits when run by a completion script
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
     color_eyre::install()?;
     let cli = Cli::command();
     let cli = Cli::from_arg_matches(&cli.get_matches())?;
 
-    init_tracing(cli.global_args.log_level());
+    if let Some(pid) = cli.global_args.console_pid {
+        attach_console(pid)?;
+    }
+    init_tracing(&cli.global_args)?;
 
     cli.invoke()?;
     Ok(())
@@ -0,0 +1,284 @@
+//! Tracing setup and the switchable destination log lines are written to.
+//!
+//! Logs go to stderr, or to `--log-file`. While the TUI owns the terminal they are redirected to
+//! a file and kept in a [`LogBuffer`] for the in-app log viewer, so they cannot corrupt the display.
+
+use crate::cache::cache_root;
+use crate::cli::global_args::GlobalArgs;
+use crate::cli::global_args::LogFormat;
+use eyre::Context;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+use tracing::Subscriber;
+use tracing::debug;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+
+static SINK: OnceLock<LogSink> = OnceLock::new();
+
+/// Initialize the global tracing subscriber from the logging flags and `RUST_LOG`.
+///
+/// `RUST_LOG` takes `EnvFilter` directives such as `role_assignment_tui=debug,warn`; levels it
+/// does not mention default to INFO, or DEBUG with `--debug`.
+/// Pretty logs in debug builds include file and line number without timestamp.
+pub fn init_tracing(global_args: &GlobalArgs) -> eyre::Result<()> {
+    let level = global_args.log_level();
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(level).into())
+        .from_env()
+        .wrap_err("Reading log filter directives from RUST_LOG")?;
+    let file = match &global_args.log_file {
+        Some(path) => Some(open_log_file(path)?),
+        None => None,
+    };
+    let ansi = file.is_none() && std::io::stderr().is_terminal();
+    let sink = SINK.get_or_init(LogSink::default);
+    sink.lock().file = file;
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(sink.clone());
+    let subscriber: Box<dyn Subscriber + Send + Sync> = match global_args.log_format() {
+        LogFormat::Json => Box::new(builder.json().with_ansi(false).finish()),
+        LogFormat::Pretty => {
+            let builder = builder.with_ansi(ansi);
+            #[cfg(debug_assertions)]
+            let builder = builder
+                .with_target(false)
+                .with_file(true)
+                .with_line_number(true)
+                .without_time();
+            Box::new(builder.finish())
+        }
+    };
+    tracing::subscriber::set_global_default(subscriber)
+        .wrap_err("Failed to set tracing subscriber")?;
+    debug!("Tracing initialized with level: {:?}", level);
+    Ok(())
+}
+
+/// Redirect logs away from the terminal until the returned guard is dropped.
+///
+/// Lines keep going to `--log-file` when one was given, otherwise to a log file in the cache
+/// directory, and are also kept in the guard's [`LogBuffer`].
+pub fn capture_for_tui() -> eyre::Result<TuiLogs> {
+    let buffer = LogBuffer::default();
+    let Some(sink) = SINK.get() else {
+        // Tracing was never initialized, so there is nothing to redirect
+        return Ok(TuiLogs { buffer, path: None });
+    };
+    let mut state = sink.lock();
+    let path = match &state.file {
+        Some(file) => file.path.clone(),
+        None => {
+            let path = cache_root()?.join("role-assignment-tui.log");
+            state.file = Some(open_log_file(&path)?);
+            state.temporary_file = true;
+            path
+        }
+    };
+    state.stderr = false;
+    state.capture = Some(buffer.clone());
+    Ok(TuiLogs {
+        buffer,
+        path: Some(path),
+    })
+}
+
+/// Logs captured while the TUI runs; dropping it sends them to the terminal again.
+pub struct TuiLogs {
+    pub buffer: LogBuffer,
+    /// The file the logs are also written to
+    pub path: Option<PathBuf>,
+}
+
+impl Drop for TuiLogs {
+    fn drop(&mut self) {
+        if let Some(sink) = SINK.get() {
+            let mut state = sink.lock();
+            if std::mem::take(&mut state.temporary_file) {
+                state.file = None;
+            }
+            state.stderr = true;
+            state.capture = None;
+        }
+    }
+}
+
+/// The most recent log lines, oldest first.
+#[derive(Clone, Default, Debug)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    const CAPACITY: usize = 1000;
+
+    pub fn push(&self, line: String) {
+        let mut lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if lines.len() == Self::CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        let lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+}
+
+fn open_log_file(path: &Path) -> eyre::Result<LogFile> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err_with(|| format!("Opening log file {}", path.display()))?;
+    Ok(LogFile {
+        path: path.to_path_buf(),
+        file,
+    })
+}
+
+#[derive(Default)]
+struct SinkState {
+    file: Option<LogFile>,
+    /// Whether `file` was opened by [`capture_for_tui`] and is closed again afterwards
+    temporary_file: bool,
+    /// Write to stderr when there is no file; off while the TUI owns the terminal
+    stderr: bool,
+    capture: Option<LogBuffer>,
+}
+
+/// The [`MakeWriter`] given to the subscriber, so the destination can change after it is set.
+#[derive(Clone)]
+struct LogSink(Arc<Mutex<SinkState>>);
+
+impl Default for LogSink {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(SinkState {
+            stderr: true,
+            ..Default::default()
+        })))
+    }
+}
+
+impl LogSink {
+    fn lock(&self) -> MutexGuard<'_, SinkState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogSink {
+    type Writer = LogSink;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl Write for LogSink {
+    /// The subscriber formats each event in full before writing it, so `buf` holds whole lines.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.lock();
+        if let Some(capture) = &state.capture {
+            for line in String::from_utf8_lossy(buf).lines() {
+                capture.push(strip_ansi(line));
+            }
+        }
+        let stderr = state.stderr;
+        match &mut state.file {
+            Some(file) => file
+                .file
+                .write_all(strip_ansi(&String::from_utf8_lossy(buf)).as_bytes())?,
+            None if stderr => std::io::stderr().write_all(buf)?,
+            None => {}
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.lock().file {
+            Some(file) => file.file.flush(),
+            None => std::io::stderr().flush(),
+        }
+    }
+}
+
+/// `text` without ANSI colour sequences, which only make sense on a terminal.
+fn strip_ansi(text: &str) -> String {
+    let mut rtn = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip `ESC [ params final`
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        rtn.push(c);
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_ansi_colours() {
+        assert_eq!(
+            strip_ansi("\x1b[2m2025\x1b[0m \x1b[32m INFO\x1b[0m done"),
+            "2025  INFO done"
+        );
+    }
+
+    #[test]
+    fn captures_lines_into_a_bounded_buffer() {
+        let sink = LogSink::default();
+        let buffer = LogBuffer::default();
+        {
+            let mut state = sink.lock();
+            state.stderr = false;
+            state.capture = Some(buffer.clone());
+        }
+        let mut writer = sink.make_writer();
+        for i in 0..LogBuffer::CAPACITY + 2 {
+            writer
+                .write_all(format!("\x1b[32mline {i}\x1b[0m\n").as_bytes())
+                .unwrap();
+        }
+        let last = LogBuffer::CAPACITY + 1;
+        assert_eq!(
+            buffer.tail(2),
+            [format!("line {}", last - 1), format!("line {last}")]
+        );
+        assert_eq!(buffer.tail(usize::MAX).len(), LogBuffer::CAPACITY);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::hierarchy::ManagementGroupEntry;
+use crate::azure::hierarchy::ScopeHierarchy;
+use crate::azure::hierarchy::SubscriptionEntry;
+use crate::azure::principals::PrincipalDirectory;
+use crate::azure::resources::ResourceEntry;
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::GroupId;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::ServicePrincipal;
+use cloud_terrastodon_azure::prelude::User;
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+
+/// Everything the TUI and reports know about a tenant, in a form that can be
+/// written to disk and loaded back without Azure access.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TenantSnapshot {
+    pub tenant_id: String,
+    pub management_groups: Vec<ManagementGroupEntry>,
+    pub subscriptions: Vec<SubscriptionEntry>,
+    pub resource_groups: Vec<ResourceGroup>,
+    /// Absent from snapshots written before resources were tracked
+    #[serde(default)]
+    pub resources: Vec<ResourceEntry>,
+    pub rbac: RoleDefinitionsAndAssignments,
+    #[serde(default)]
+    pub deny_assignments: Vec<DenyAssignment>,
+    /// PIM eligibilities, which grant nothing until activated
+    #[serde(default)]
+    pub eligible_assignments: Vec<EligibleAssignment>,
+    pub users: Vec<User>,
+    pub service_principals: Vec<ServicePrincipal>,
+    pub security_groups: Vec<Group>,
+    /// Direct group memberships; members may themselves be groups
+    pub group_memberships: Vec<GroupMembership>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroupMembership {
+    pub group_id: GroupId,
+    pub member_id: PrincipalId,
+}
+
+impl TenantSnapshot {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let contents =
+            std::fs::read(path).wrap_err_with(|| format!("Reading snapshot {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .wrap_err_with(|| format!("Parsing snapshot {}", path.display()))
+    }
+
+    pub fn hierarchy(&self) -> ScopeHierarchy {
+        ScopeHierarchy {
+            management_groups: self.management_groups.clone(),
+            subscriptions: self.subscriptions.clone(),
+        }
+    }
+
+    pub fn principals(&self) -> PrincipalDirectory {
+        let mut rtn = PrincipalDirectory::default();
+        rtn.add_users(&self.users);
+        rtn.add_service_principals(&self.service_principals);
+        rtn.add_groups(&self.security_groups);
+        rtn
+    }
+
+    /// Ids of every group `principal_id` belongs to, directly or through nested groups.
+    pub fn transitive_group_ids(&self, principal_id: &PrincipalId) -> Vec<GroupId> {
+        let mut rtn: Vec<GroupId> = Vec::new();
+        let mut pending = vec![*principal_id];
+        while let Some(member_id) = pending.pop() {
+            for membership in &self.group_memberships {
+                if membership.member_id == member_id && !rtn.contains(&membership.group_id) {
+                    rtn.push(membership.group_id);
+                    pending.push(membership.group_id.into());
+                }
+            }
+        }
+        rtn
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(path, json).wrap_err_with(|| format!("Writing snapshot {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::SyntheticTenantOptions;
+    use crate::synthetic::generate;
+
+    #[test]
+    fn follows_nested_group_memberships() {
+        let snapshot = generate(&SyntheticTenantOptions::medium(1)).unwrap();
+        let is_group = |id: PrincipalId| {
+            snapshot
+                .security_groups
+                .iter()
+                .any(|x| PrincipalId::from(x.id) == id)
+        };
+        let nested = snapshot
+            .group_memberships
+            .iter()
+            .find(|x| is_group(x.member_id))
+            .expect("the medium tenant nests groups");
+        let user = snapshot
+            .group_memberships
+            .iter()
+            .find(|x| PrincipalId::from(x.group_id) == nested.member_id && !is_group(x.member_id))
+            .expect("nested groups have users");
+
+        let group_ids = snapshot.transitive_group_ids(&user.member_id);
+        assert!(group_ids.contains(&user.group_id));
+        assert!(group_ids.contains(&nested.group_id));
+    }
+}
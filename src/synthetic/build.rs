@@ -0,0 +1,138 @@
+//! Builders for Azure values from plain strings.
+//!
+//! Values are deserialized from the same JSON shapes Azure returns so the
+//! builders stay valid without depending on constructor details.
+
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::ResourceGroup;
+use cloud_terrastodon_azure::prelude::RoleAssignment;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::RoleDefinitionsAndAssignments;
+use cloud_terrastodon_azure::prelude::ServicePrincipal;
+use cloud_terrastodon_azure::prelude::User;
+use eyre::Context;
+use serde_json::json;
+
+/// A stable, UUID-shaped identifier derived from `seed`.
+pub fn uuid(seed: &str) -> String {
+    // FNV-1a keeps ids stable across runs without pulling in a uuid dependency
+    let hash = seed.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!(
+        "{:08x}-{:04x}-4{:03x}-8{:03x}-{:012x}",
+        hash >> 32,
+        (hash >> 16) & 0xffff,
+        hash & 0xfff,
+        (hash >> 52) & 0xfff,
+        hash & 0xffff_ffff_ffff
+    )
+}
+
+pub fn resource_group(
+    subscription_id: &str,
+    name: &str,
+    location: &str,
+) -> eyre::Result<ResourceGroup> {
+    serde_json::from_value(json!({
+        "id": format!("/subscriptions/{subscription_id}/resourceGroups/{name}"),
+        "location": location,
+        "managed_by": null,
+        "name": name,
+        "properties": { "provisioningState": "Succeeded" },
+        "tags": {},
+        "subscription_id": subscription_id,
+    }))
+    .wrap_err_with(|| format!("Building resource group {name}"))
+}
+
+pub fn user(id: &str, display_name: &str, user_principal_name: &str) -> eyre::Result<User> {
+    serde_json::from_value(json!({
+        "id": id,
+        "displayName": display_name,
+        "userPrincipalName": user_principal_name,
+        "mail": user_principal_name,
+    }))
+    .wrap_err_with(|| format!("Building user {display_name}"))
+}
+
+pub fn service_principal(
+    id: &str,
+    app_id: &str,
+    display_name: &str,
+) -> eyre::Result<ServicePrincipal> {
+    serde_json::from_value(json!({
+        "id": id,
+        "appId": app_id,
+        "displayName": display_name,
+    }))
+    .wrap_err_with(|| format!("Building service principal {display_name}"))
+}
+
+pub fn security_group(id: &str, display_name: &str, description: &str) -> eyre::Result<Group> {
+    serde_json::from_value(json!({
+        "id": id,
+        "displayName": display_name,
+        "description": description,
+        "securityEnabled": true,
+    }))
+    .wrap_err_with(|| format!("Building group {display_name}"))
+}
+
+/// The parts of a role definition that vary between the roles we build.
+pub struct RoleDefinitionSpec<'a> {
+    pub name: &'a str,
+    pub display_name: &'a str,
+    pub description: &'a str,
+    pub custom: bool,
+    pub actions: &'a [&'a str],
+    pub not_actions: &'a [&'a str],
+    pub data_actions: &'a [&'a str],
+    pub assignable_scopes: &'a [String],
+}
+
+pub fn role_definition(spec: &RoleDefinitionSpec) -> eyre::Result<RoleDefinition> {
+    serde_json::from_value(json!({
+        "id": format!("/providers/Microsoft.Authorization/roleDefinitions/{}", spec.name),
+        "display_name": spec.display_name,
+        "description": spec.description,
+        "assignable_scopes": spec.assignable_scopes,
+        "permissions": [{
+            "actions": spec.actions,
+            "not_actions": spec.not_actions,
+            "data_actions": spec.data_actions,
+            "not_data_actions": [],
+        }],
+        "kind": if spec.custom { "Custom" } else { "AzureBuiltIn" },
+    }))
+    .wrap_err_with(|| format!("Building role definition {}", spec.display_name))
+}
+
+pub fn role_assignment(
+    scope: &str,
+    role_definition: &RoleDefinition,
+    principal_id: &PrincipalId,
+) -> eyre::Result<RoleAssignment> {
+    let name = uuid(&format!("{scope}/{}/{principal_id}", role_definition.id));
+    serde_json::from_value(json!({
+        "id": format!("{scope}/providers/Microsoft.Authorization/roleAssignments/{name}"),
+        "scope": scope,
+        "role_definition_id": role_definition.id,
+        "principal_id": principal_id,
+    }))
+    .wrap_err_with(|| format!("Building role assignment at {scope}"))
+}
+
+pub fn rbac(
+    role_definitions: impl IntoIterator<Item = RoleDefinition>,
+    role_assignments: impl IntoIterator<Item = RoleAssignment>,
+) -> RoleDefinitionsAndAssignments {
+    RoleDefinitionsAndAssignments {
+        role_definitions: role_definitions
+            .into_iter()
+            .map(|rd| (rd.id.clone(), rd))
+            .collect(),
+        role_assignments: role_assignments.into_iter().collect(),
+    }
+}
@@ -0,0 +1,632 @@
+//! Generates realistic fake tenants for tests, demos and benchmarks.
+
+pub mod build;
+
+use crate::azure::deny_assignments::DenyAssignment;
+use crate::azure::deny_assignments::EVERYONE;
+use crate::azure::eligible_assignments::EligibleAssignment;
+use crate::azure::hierarchy::ManagementGroupEntry;
+use crate::azure::hierarchy::SubscriptionEntry;
+use crate::azure::resources::ResourceEntry;
+use crate::snapshot::GroupMembership;
+use crate::snapshot::TenantSnapshot;
+use crate::synthetic::build::RoleDefinitionSpec;
+use chrono::DateTime;
+use cloud_terrastodon_azure::prelude::Group;
+use cloud_terrastodon_azure::prelude::PrincipalId;
+use cloud_terrastodon_azure::prelude::RoleDefinition;
+use cloud_terrastodon_azure::prelude::Scope;
+use std::collections::HashSet;
+
+const DEPARTMENTS: &[&str] = &[
+    "finance",
+    "sales",
+    "platform",
+    "data",
+    "identity",
+    "payments",
+    "search",
+    "analytics",
+    "hr",
+    "legal",
+    "support",
+    "marketing",
+];
+const ENVIRONMENTS: &[&str] = &["dev", "test", "uat", "prod", "sandbox"];
+const WORKLOADS: &[&str] = &[
+    "api",
+    "web",
+    "etl",
+    "ml",
+    "network",
+    "shared",
+    "monitoring",
+    "storage",
+    "batch",
+    "vault",
+];
+/// Resource types with the prefix used for their names.
+const RESOURCE_KINDS: &[(&str, &str)] = &[
+    ("Microsoft.Storage/storageAccounts", "st"),
+    ("Microsoft.KeyVault/vaults", "kv"),
+    ("Microsoft.Web/sites", "app"),
+    ("Microsoft.Compute/virtualMachines", "vm"),
+    ("Microsoft.Sql/servers", "sql"),
+];
+/// Actions and exceptions for generated deny assignments, like those left by managed apps.
+const DENY_PERMISSIONS: &[(&[&str], &[&str])] = &[
+    (&["*/delete"], &[]),
+    (&["Microsoft.Authorization/*/Write"], &[]),
+    (&["*"], &["*/read"]),
+    (&["*"], &[]),
+];
+/// Eligibility windows start on or after 2026-01-01 so generated tenants do not depend on the clock.
+const ELIGIBILITY_EPOCH: i64 = 1_767_225_600;
+const DAY: i64 = 24 * 60 * 60;
+const LOCATIONS: &[&str] = &["canadacentral", "canadaeast", "eastus2", "westeurope"];
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bob", "Carol", "Dmitri", "Erin", "Farah", "Gustavo", "Hana", "Ivan", "Jun", "Kemal",
+    "Lena", "Mateo", "Nia", "Oskar", "Priya", "Quinn", "Rosa", "Sven", "Tariq",
+];
+const LAST_NAMES: &[&str] = &[
+    "Anders", "Bianchi", "Chen", "Dubois", "Eze", "Fischer", "Garcia", "Haddad", "Ito", "Jensen",
+    "Kowalski", "Lopez", "Mensah", "Novak", "Okafor", "Patel", "Rossi", "Silva",
+];
+const TEAM_ROLES: &[&str] = &["admins", "developers", "operators", "readers", "auditors"];
+
+struct BuiltInRole {
+    name: &'static str,
+    display_name: &'static str,
+    actions: &'static [&'static str],
+    not_actions: &'static [&'static str],
+    data_actions: &'static [&'static str],
+}
+
+/// Well-known built-in roles, with their real definition GUIDs.
+const BUILT_IN_ROLES: &[BuiltInRole] = &[
+    BuiltInRole {
+        name: "8e3af657-a8ff-443c-a75c-2fe8c4bcb635",
+        display_name: "Owner",
+        actions: &["*"],
+        not_actions: &[],
+        data_actions: &[],
+    },
+    BuiltInRole {
+        name: "b24988ac-6180-42a0-ab88-20f7382dd24c",
+        display_name: "Contributor",
+        actions: &["*"],
+        not_actions: &[
+            "Microsoft.Authorization/*/Delete",
+            "Microsoft.Authorization/*/Write",
+            "Microsoft.Authorization/elevateAccess/Action",
+        ],
+        data_actions: &[],
+    },
+    BuiltInRole {
+        name: "acdd72a7-3385-48ef-bd42-f606fba81ae7",
+        display_name: "Reader",
+        actions: &["*/read"],
+        not_actions: &[],
+        data_actions: &[],
+    },
+    BuiltInRole {
+        name: "18d7d88d-d35e-4fb5-a5c3-7773c20a72d9",
+        display_name: "User Access Administrator",
+        actions: &["*/read", "Microsoft.Authorization/*", "Microsoft.Support/*"],
+        not_actions: &[],
+        data_actions: &[],
+    },
+    BuiltInRole {
+        name: "2a2b9908-6ea1-4ae2-8e65-a410df84e7d1",
+        display_name: "Storage Blob Data Reader",
+        actions: &["Microsoft.Storage/storageAccounts/blobServices/containers/read"],
+        not_actions: &[],
+        data_actions: &["Microsoft.Storage/storageAccounts/blobServices/containers/blobs/read"],
+    },
+    BuiltInRole {
+        name: "4633458b-17de-408a-b874-0445c86b69e6",
+        display_name: "Key Vault Secrets User",
+        actions: &[],
+        not_actions: &[],
+        data_actions: &["Microsoft.KeyVault/vaults/secrets/getSecret/action"],
+    },
+];
+
+/// Relative likelihood of picking each built-in role above, in order.
+const BUILT_IN_ROLE_WEIGHTS: &[u64] = &[2, 5, 8, 1, 3, 2];
+
+/// Size knobs for a generated tenant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticTenantOptions {
+    pub seed: u64,
+    pub management_groups: usize,
+    pub subscriptions: usize,
+    pub resource_groups_per_subscription: usize,
+    pub resources_per_resource_group: usize,
+    pub users: usize,
+    pub service_principals: usize,
+    pub groups: usize,
+    pub custom_roles: usize,
+    pub role_assignments: usize,
+    pub deny_assignments: usize,
+    pub eligible_assignments: usize,
+}
+
+impl SyntheticTenantOptions {
+    pub fn small(seed: u64) -> Self {
+        Self {
+            seed,
+            management_groups: 3,
+            subscriptions: 3,
+            resource_groups_per_subscription: 4,
+            resources_per_resource_group: 3,
+            users: 20,
+            service_principals: 8,
+            groups: 6,
+            custom_roles: 2,
+            role_assignments: 60,
+            deny_assignments: 2,
+            eligible_assignments: 10,
+        }
+    }
+
+    pub fn medium(seed: u64) -> Self {
+        Self {
+            seed,
+            management_groups: 8,
+            subscriptions: 15,
+            resource_groups_per_subscription: 12,
+            resources_per_resource_group: 6,
+            users: 800,
+            service_principals: 200,
+            groups: 80,
+            custom_roles: 8,
+            role_assignments: 3_000,
+            deny_assignments: 5,
+            eligible_assignments: 300,
+        }
+    }
+
+    pub fn large(seed: u64) -> Self {
+        Self {
+            seed,
+            management_groups: 25,
+            subscriptions: 80,
+            resource_groups_per_subscription: 25,
+            resources_per_resource_group: 10,
+            users: 30_000,
+            service_principals: 6_000,
+            groups: 1_500,
+            custom_roles: 30,
+            role_assignments: 60_000,
+            deny_assignments: 20,
+            eligible_assignments: 4_000,
+        }
+    }
+}
+
+/// Deterministic SplitMix64 generator so the same seed always yields the same tenant.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+
+    fn weighted(&mut self, weights: &[u64]) -> usize {
+        let mut roll = self.next_u64() % weights.iter().sum::<u64>();
+        for (i, weight) in weights.iter().enumerate() {
+            if roll < *weight {
+                return i;
+            }
+            roll -= weight;
+        }
+        weights.len() - 1
+    }
+
+    fn uuid(&mut self) -> String {
+        let (a, b) = (self.next_u64(), self.next_u64());
+        format!(
+            "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            a >> 32,
+            (a >> 16) & 0xffff,
+            a & 0xfff,
+            0x8000 | (b >> 48) & 0x3fff,
+            b & 0xffff_ffff_ffff
+        )
+    }
+}
+
+/// Generate a tenant in the snapshot format.
+pub fn generate(options: &SyntheticTenantOptions) -> eyre::Result<TenantSnapshot> {
+    let mut rng = Rng(options.seed);
+    let tenant_id = rng.uuid();
+
+    // Management groups hang off the root or off an earlier group, giving varied depth
+    let mut management_groups = vec![ManagementGroupEntry {
+        name: tenant_id.clone(),
+        display_name: "Tenant Root Group".to_string(),
+        parent_name: None,
+    }];
+    for i in 0..options.management_groups {
+        let parent = rng.pick(&management_groups).name.clone();
+        let department = rng.pick(DEPARTMENTS);
+        management_groups.push(ManagementGroupEntry {
+            name: format!("mg-{department}-{i}"),
+            display_name: format!("{} {i}", title_case(department)),
+            parent_name: Some(parent),
+        });
+    }
+
+    let mut subscriptions = Vec::new();
+    for i in 0..options.subscriptions {
+        let department = rng.pick(DEPARTMENTS);
+        let environment = rng.pick(ENVIRONMENTS);
+        subscriptions.push(SubscriptionEntry {
+            subscription_id: rng.uuid(),
+            display_name: format!("sub-{department}-{environment}-{i:02}"),
+            management_group_name: Some(rng.pick(&management_groups).name.clone()),
+        });
+    }
+
+    let mut resource_groups = Vec::new();
+    for subscription in &subscriptions {
+        for i in 0..options.resource_groups_per_subscription {
+            let workload = rng.pick(WORKLOADS);
+            let environment = rng.pick(ENVIRONMENTS);
+            let location = *rng.pick(LOCATIONS);
+            resource_groups.push(build::resource_group(
+                &subscription.subscription_id,
+                &format!("rg-{workload}-{environment}-{i:03}"),
+                location,
+            )?);
+        }
+    }
+
+    let mut resources = Vec::new();
+    for resource_group in &resource_groups {
+        for i in 0..options.resources_per_resource_group {
+            let (kind, prefix) = rng.pick(RESOURCE_KINDS);
+            let workload = rng.pick(WORKLOADS);
+            // Storage account names only allow lowercase letters and digits
+            let name = match *prefix {
+                "st" => format!("st{workload}{:04x}{i}", rng.below(0x10000)),
+                _ => format!("{prefix}-{workload}-{i:02}"),
+            };
+            resources.push(ResourceEntry {
+                id: format!(
+                    "{}/providers/{kind}/{name}",
+                    resource_group.id.expanded_form()
+                ),
+                name,
+                kind: kind.to_string(),
+            });
+        }
+    }
+
+    let mut users = Vec::new();
+    for i in 0..options.users {
+        let first = rng.pick(FIRST_NAMES);
+        let last = rng.pick(LAST_NAMES);
+        users.push(build::user(
+            &rng.uuid(),
+            &format!("{first} {last}"),
+            &format!(
+                "{}.{}{i}@contoso.example",
+                first.to_lowercase(),
+                last.to_lowercase()
+            ),
+        )?);
+    }
+
+    let mut service_principals = Vec::new();
+    for i in 0..options.service_principals {
+        let workload = rng.pick(WORKLOADS);
+        let environment = rng.pick(ENVIRONMENTS);
+        service_principals.push(build::service_principal(
+            &rng.uuid(),
+            &rng.uuid(),
+            &format!("sp-{workload}-{environment}-{i}"),
+        )?);
+    }
+
+    let mut security_groups: Vec<Group> = Vec::new();
+    let mut group_memberships = Vec::new();
+    for i in 0..options.groups {
+        let department = rng.pick(DEPARTMENTS);
+        let team_role = rng.pick(TEAM_ROLES);
+        let group = build::security_group(
+            &rng.uuid(),
+            &format!("grp-{department}-{team_role}-{i}"),
+            &format!("{} {team_role}", title_case(department)),
+        )?;
+        if !users.is_empty() {
+            for _ in 0..=rng.below(8) {
+                group_memberships.push(GroupMembership {
+                    group_id: group.id,
+                    member_id: rng.pick(&users).id.into(),
+                });
+            }
+        }
+        // Nest roughly a third of groups inside an earlier one; only earlier groups
+        // are eligible so memberships never form a cycle
+        if !security_groups.is_empty() && rng.chance(33) {
+            let parent = rng.pick(&security_groups);
+            group_memberships.push(GroupMembership {
+                group_id: parent.id,
+                member_id: group.id.into(),
+            });
+        }
+        security_groups.push(group);
+    }
+
+    let mut role_definitions = BUILT_IN_ROLES
+        .iter()
+        .map(|role| {
+            build::role_definition(&RoleDefinitionSpec {
+                name: role.name,
+                display_name: role.display_name,
+                description: &format!("Built-in {} role", role.display_name),
+                custom: false,
+                actions: role.actions,
+                not_actions: role.not_actions,
+                data_actions: role.data_actions,
+                assignable_scopes: &["/".to_string()],
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    for i in 0..options.custom_roles {
+        let department = rng.pick(DEPARTMENTS);
+        let assignable_scope = match subscriptions.is_empty() {
+            true => "/".to_string(),
+            false => rng.pick(&subscriptions).id(),
+        };
+        role_definitions.push(build::role_definition(&RoleDefinitionSpec {
+            name: &rng.uuid(),
+            display_name: &format!("Custom {} Operator {i}", title_case(department)),
+            description: &format!("Operate {department} workloads without changing access"),
+            custom: true,
+            actions: &[
+                "*/read",
+                "Microsoft.Compute/virtualMachines/start/action",
+                "Microsoft.Compute/virtualMachines/restart/action",
+                "Microsoft.Web/sites/restart/action",
+            ],
+            not_actions: &[],
+            data_actions: &[],
+            assignable_scopes: &[assignable_scope],
+        })?);
+    }
+
+    let principals: Vec<PrincipalId> = users
+        .iter()
+        .map(|x| x.id.into())
+        .chain(security_groups.iter().map(|x| x.id.into()))
+        .chain(service_principals.iter().map(|x| x.id.into()))
+        .collect();
+    let mut role_assignments = Vec::new();
+    let mut seen = HashSet::new();
+    if !principals.is_empty() {
+        // Allow a few collisions before giving up so tiny tenants still terminate
+        let mut attempts = options.role_assignments * 4;
+        while role_assignments.len() < options.role_assignments && attempts > 0 {
+            attempts -= 1;
+            let scope = match rng.weighted(&[10, 20, 55, 15]) {
+                0 => rng.pick(&management_groups).id(),
+                1 if !subscriptions.is_empty() => rng.pick(&subscriptions).id(),
+                2 if !resource_groups.is_empty() => rng.pick(&resource_groups).id.expanded_form(),
+                3 if !resources.is_empty() => rng.pick(&resources).id.clone(),
+                _ => rng.pick(&management_groups).id(),
+            };
+            let role_definition = pick_role(&mut rng, &role_definitions, &scope);
+            let principal_id = rng.pick(&principals);
+            if !seen.insert((scope.clone(), role_definition.id.clone(), *principal_id)) {
+                continue;
+            }
+            let mut role_assignment =
+                build::role_assignment(&scope, role_definition, principal_id)?;
+            // Blob data roles are where ABAC conditions show up in practice
+            let blob_data_role = role_definition.permissions.iter().any(|x| {
+                x.data_actions
+                    .iter()
+                    .any(|action| action.starts_with("Microsoft.Storage/"))
+            });
+            if blob_data_role && rng.chance(50) {
+                let project = rng.pick(WORKLOADS);
+                role_assignment.condition = Some(format!(
+                    "((!(ActionMatches{{'Microsoft.Storage/storageAccounts/blobServices/containers/blobs/read'}})) OR (@Resource[Microsoft.Storage/storageAccounts/blobServices/containers/blobs/tags:project<$key_case_sensitive$>] StringEquals '{project}'))"
+                ));
+                role_assignment.condition_version = Some("2.0".to_string());
+                role_assignment.description = Some(format!("Only blobs tagged project={project}"));
+            }
+            role_assignments.push(role_assignment);
+        }
+    }
+
+    // Deny everyone except one service principal, the shape managed applications leave behind
+    let mut deny_assignments = Vec::new();
+    if !resource_groups.is_empty() {
+        for i in 0..options.deny_assignments {
+            let scope = match rng.chance(25) && !subscriptions.is_empty() {
+                true => rng.pick(&subscriptions).id(),
+                false => rng.pick(&resource_groups).id.expanded_form(),
+            };
+            let (actions, not_actions) = rng.pick(DENY_PERMISSIONS);
+            let exclude_principal_ids = match service_principals.is_empty() {
+                true => Vec::new(),
+                false => vec![rng.pick(&service_principals).id.to_string()],
+            };
+            let workload = rng.pick(WORKLOADS);
+            deny_assignments.push(DenyAssignment {
+                id: format!(
+                    "{scope}/providers/Microsoft.Authorization/denyAssignments/{}",
+                    rng.uuid()
+                ),
+                name: format!("deny-{workload}-{i}"),
+                description: Some(format!("Created by managed application {workload}")),
+                scope,
+                actions: actions.iter().map(|x| x.to_string()).collect(),
+                not_actions: not_actions.iter().map(|x| x.to_string()).collect(),
+                data_actions: Vec::new(),
+                not_data_actions: Vec::new(),
+                principal_ids: vec![EVERYONE.to_string()],
+                exclude_principal_ids,
+                do_not_apply_to_child_scopes: false,
+            });
+        }
+    }
+
+    // Eligibilities favour the powerful roles, which is what PIM is usually used for
+    let mut eligible_assignments = Vec::new();
+    if !principals.is_empty() {
+        for _ in 0..options.eligible_assignments {
+            let scope = match rng.weighted(&[10, 40, 50]) {
+                0 => rng.pick(&management_groups).id(),
+                1 if !subscriptions.is_empty() => rng.pick(&subscriptions).id(),
+                _ if !resource_groups.is_empty() => rng.pick(&resource_groups).id.expanded_form(),
+                _ => rng.pick(&management_groups).id(),
+            };
+            let role_definition = &role_definitions[rng.weighted(&[4, 4, 1, 2, 0, 0])];
+            let start = ELIGIBILITY_EPOCH + rng.below(180) as i64 * DAY;
+            let end = match rng.chance(20) {
+                true => None,
+                false => Some(start + (30 + rng.below(335)) as i64 * DAY),
+            };
+            eligible_assignments.push(EligibleAssignment {
+                id: format!(
+                    "{scope}/providers/Microsoft.Authorization/roleEligibilityScheduleInstances/{}",
+                    rng.uuid()
+                ),
+                scope,
+                role_definition_id: role_definition.id.expanded_form(),
+                principal_id: *rng.pick(&principals),
+                member_type: Some("Direct".to_string()),
+                start_date_time: DateTime::from_timestamp(start, 0),
+                end_date_time: end.and_then(|end| DateTime::from_timestamp(end, 0)),
+            });
+        }
+    }
+
+    Ok(TenantSnapshot {
+        tenant_id,
+        management_groups,
+        subscriptions,
+        resource_groups,
+        resources,
+        rbac: build::rbac(role_definitions, role_assignments),
+        deny_assignments,
+        eligible_assignments,
+        users,
+        service_principals,
+        security_groups,
+        group_memberships,
+    })
+}
+
+/// Pick a role that may be assigned at `scope`, favouring the common built-ins.
+fn pick_role<'a>(
+    rng: &mut Rng,
+    role_definitions: &'a [RoleDefinition],
+    scope: &str,
+) -> &'a RoleDefinition {
+    let custom = &role_definitions[BUILT_IN_ROLES.len()..];
+    if !custom.is_empty() && rng.chance(15) {
+        let role_definition = rng.pick(custom);
+        let assignable = role_definition.assignable_scopes.iter().any(|assignable| {
+            let assignable = assignable.to_string();
+            assignable == "/" || scope.starts_with(&assignable)
+        });
+        if assignable {
+            return role_definition;
+        }
+    }
+    &role_definitions[rng.weighted(BUILT_IN_ROLE_WEIGHTS)]
+}
+
+fn title_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_tenant() {
+        let a = generate(&SyntheticTenantOptions::small(7)).unwrap();
+        let b = generate(&SyntheticTenantOptions::small(7)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&a).unwrap(),
+            serde_json::to_value(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn respects_size_knobs() {
+        let options = SyntheticTenantOptions::small(1);
+        let tenant = generate(&options).unwrap();
+        assert_eq!(
+            tenant.management_groups.len(),
+            options.management_groups + 1
+        );
+        assert_eq!(tenant.subscriptions.len(), options.subscriptions);
+        assert_eq!(
+            tenant.resource_groups.len(),
+            options.subscriptions * options.resource_groups_per_subscription
+        );
+        assert_eq!(
+            tenant.resources.len(),
+            tenant.resource_groups.len() * options.resources_per_resource_group
+        );
+        assert_eq!(tenant.users.len(), options.users);
+        assert_eq!(tenant.service_principals.len(), options.service_principals);
+        assert_eq!(tenant.security_groups.len(), options.groups);
+        assert_eq!(
+            tenant.rbac.role_definitions.len(),
+            BUILT_IN_ROLES.len() + options.custom_roles
+        );
+        assert_eq!(tenant.rbac.role_assignments.len(), options.role_assignments);
+        assert_eq!(tenant.deny_assignments.len(), options.deny_assignments);
+        assert_eq!(
+            tenant.eligible_assignments.len(),
+            options.eligible_assignments
+        );
+        assert!(
+            tenant
+                .eligible_assignments
+                .iter()
+                .all(|x| x.role_definition(&tenant.rbac).is_some())
+        );
+    }
+
+    #[test]
+    fn snapshot_roundtrips_through_json() {
+        let tenant = generate(&SyntheticTenantOptions::small(3)).unwrap();
+        let json = serde_json::to_string(&tenant).unwrap();
+        let parsed: TenantSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&tenant).unwrap(),
+            serde_json::to_value(&parsed).unwrap()
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::process::Command;
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_role-assignment-tui"))
+        .args(args)
+        .env_remove("COMPLETE")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn prints_a_registration_script_for_each_shell() {
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let script = run(&["completions", shell]);
+        assert!(script.contains("role-assignment-tui"), "{shell}: {script}");
+    }
+}
+
+#[test]
+fn man_page_lists_every_subcommand() {
+    let page = run(&["man"]);
+    for subcommand in [
+        "list-resource-groups",
+        "resource-group-tui",
+        "generate-tenant",
+        "completions",
+        "man",
+    ] {
+        assert!(
+            page.contains(subcommand),
+            "{subcommand} missing from {page}"
+        );
+    }
+
+    let dir = std::env::temp_dir().join(format!("man-test-{}", std::process::id()));
+    run(&["man", "--output-dir", dir.to_str().unwrap()]);
+    let pages = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        pages.contains(&"role-assignment-tui.1".to_owned()),
+        "{pages:?}"
+    );
+    assert!(
+        pages.contains(&"role-assignment-tui-list-resource-groups.1".to_owned()),
+        "{pages:?}"
+    );
+}
@@ -0,0 +1,72 @@
+#[test]
+fn config_show_reports_each_layer() {
+    let root = std::env::temp_dir().join(format!("config-show-test-{}", std::process::id()));
+    let user_dir = root.join("user");
+    let project_dir = root.join("project");
+    let working_dir = project_dir.join("nested");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::create_dir_all(&working_dir).unwrap();
+    std::fs::write(
+        user_dir.join("config.toml"),
+        "theme = \"light\"\ncache_ttl = 60\n\n[keys]\nquit = [\"Ctrl+c\"]\n",
+    )
+    .unwrap();
+    let project_file = project_dir.join(".role-assignment-tui.toml");
+    std::fs::write(
+        &project_file,
+        "cache_ttl = 120\noutput_format = \"json-lines\"\n",
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_role-assignment-tui"))
+        .args(["--cache-ttl", "5", "config", "show"])
+        .current_dir(&working_dir)
+        .env("RATUI_CONFIG_DIR", &user_dir)
+        .env("RATUI_THEME", "monochrome")
+        .env_remove("RATUI_EXPECTED_TENANT")
+        .env_remove("RATUI_SUBSCRIPTIONS")
+        .env_remove("RATUI_OUTPUT_FORMAT")
+        .env_remove("RATUI_CACHE_TTL")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let line = |setting: &str| {
+        stdout
+            .lines()
+            .find(|line| line.starts_with(setting) || line.starts_with(&format!("# {setting}")))
+            .unwrap_or_else(|| panic!("{setting} missing from\n{stdout}"))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(
+        line("expected_tenant"),
+        "# expected_tenant is not set # default"
+    );
+    assert_eq!(
+        line("output_format"),
+        format!(
+            "output_format = \"json-lines\" # project config {}",
+            project_file.display()
+        )
+    );
+    assert_eq!(
+        line("theme"),
+        "theme = \"monochrome\" # environment RATUI_THEME"
+    );
+    assert_eq!(line("cache_ttl"), "cache_ttl = 5 # flag --cache-ttl");
+    assert_eq!(
+        line("keys.quit"),
+        format!(
+            "keys.quit = [\"Ctrl+c\"] # user config {}",
+            user_dir.join("config.toml").display()
+        )
+    );
+}
@@ -0,0 +1,16 @@
+use std::process::Stdio;
+
+#[test]
+fn names_missing_arguments_instead_of_prompting_without_a_terminal() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_role-assignment-tui"))
+        .args(["role-assignment", "create", "--role", "Reader"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Missing --assignee-object-id, --scope"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,104 @@
+use role_assignment_tui::cli::Cli;
+use role_assignment_tui::cli::command::Command;
+use role_assignment_tui::cli::command::generate_tenant::GenerateTenantArgs;
+use role_assignment_tui::cli::command::generate_tenant::TenantSize;
+use role_assignment_tui::cli::global_args::GlobalArgs;
+use role_assignment_tui::cli::relaunch;
+use role_assignment_tui::cli::to_args::Invocable;
+use role_assignment_tui::cli::to_args::ToArgs;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The built binary of this crate, launched with `args`.
+struct Binary(Vec<OsString>);
+
+impl Invocable for Binary {
+    fn executable(&self) -> PathBuf {
+        PathBuf::from(env!("CARGO_BIN_EXE_role-assignment-tui"))
+    }
+
+    fn args(&self) -> Vec<OsString> {
+        self.0.clone()
+    }
+}
+
+fn generate_tenant(seed: u64, output: PathBuf) -> GenerateTenantArgs {
+    GenerateTenantArgs {
+        seed,
+        size: TenantSize::Small,
+        management_groups: None,
+        subscriptions: Some(2),
+        resource_groups_per_subscription: None,
+        resources_per_resource_group: None,
+        users: None,
+        service_principals: None,
+        groups: None,
+        custom_roles: None,
+        role_assignments: None,
+        deny_assignments: None,
+        eligible_assignments: None,
+        output: Some(output),
+    }
+}
+
+#[test]
+fn relaunched_invocation_matches_in_process_run() {
+    let dir = std::env::temp_dir().join(format!("relaunch-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let relaunched_path = dir.join("relaunched.json");
+    let in_process_path = dir.join("in-process.json");
+
+    let cli = Cli {
+        global_args: GlobalArgs {
+            console_pid: Some(std::process::id()),
+            ..Default::default()
+        },
+        command: Command::GenerateTenant(generate_tenant(7, relaunched_path.clone())),
+    };
+    let status = relaunch::run(&Binary(cli.to_args())).unwrap();
+    assert!(status.success(), "relaunch failed with {status}");
+
+    generate_tenant(7, in_process_path.clone())
+        .invoke()
+        .unwrap();
+    // Compare as values since map order differs between processes
+    let read = |path: &PathBuf| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+    };
+    let relaunched = read(&relaunched_path);
+    let in_process = read(&in_process_path);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(relaunched, in_process);
+}
+
+#[test]
+fn propagates_the_exit_code_of_the_relaunched_process() {
+    let status = relaunch::run(&Binary(vec![
+        "--console-pid".into(),
+        std::process::id().to_string().into(),
+        "no-such-command".into(),
+    ]))
+    .unwrap();
+    // clap's usage error
+    assert_eq!(relaunch::exit_code(status), 2);
+}
+
+#[test]
+fn reports_the_relaunched_write_failure_to_the_parent() {
+    // A fresh config dir has no login, so the relaunched delete fails whether or not az exists
+    let dir = std::env::temp_dir().join(format!("relaunch-write-test-{}", std::process::id()));
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_role-assignment-tui"))
+        .args(["role-assignment", "--write-config-dir"])
+        .arg(&dir)
+        .args(["delete", "--id", "/subscriptions/x/roleAssignments/y"])
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Write as login in"), "{stderr}");
+    assert!(
+        stderr.contains("Deleting role assignment /subscriptions/x/roleAssignments/y"),
+        "{stderr}"
+    );
+}